@@ -13,13 +13,13 @@ pub enum Statement {
     DoWhile(Vec<Statement>, Box<Expr>),
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum Expr {
     Id(String),
     Int(i32),
     BinOp(Box<Expr>, Operator, Box<Expr>),
 }
-#[derive(Debug, Clone, PartialEq, Eq, Copy)]
+#[derive(Debug, Clone, PartialEq, Eq, Copy, Hash, PartialOrd, Ord)]
 pub enum Operator {
     Plus,
     Minus,
@@ -30,7 +30,7 @@ pub enum Operator {
 }
 
 pub struct ExprIdIterator {
-    seq: Vec<Box<Expr>>,
+    seq: Vec<Expr>,
 }
 
 // Implementations
@@ -41,7 +41,7 @@ impl Program {
 }
 
 impl ExprIdIterator {
-    fn new(expr: Box<Expr>) -> Self {
+    fn new(expr: Expr) -> Self {
         ExprIdIterator { seq: vec![expr] }
     }
 }
@@ -51,13 +51,13 @@ impl Iterator for ExprIdIterator {
 
     fn next(&mut self) -> Option<Self::Item> {
         while let Some(expr) = self.seq.pop() {
-            match *expr {
+            match expr {
                 Expr::Id(id) => return Some(id),
                 Expr::Int(_) => continue,
                 Expr::BinOp(l, _, r) => {
                     // Order, left branch should be next ;)
-                    self.seq.push(r);
-                    self.seq.push(l);
+                    self.seq.push(*r);
+                    self.seq.push(*l);
                 }
             }
         }
@@ -66,8 +66,54 @@ impl Iterator for ExprIdIterator {
 }
 
 impl Expr {
+    // Takes the Box the AST holds expressions in, callers rely on that
+    #[allow(clippy::boxed_local)]
     pub fn iter(self: Box<Self>) -> ExprIdIterator {
-        ExprIdIterator::new(self)
+        ExprIdIterator::new(*self)
+    }
+
+    // Every BinOp in the expression, outermost first
+    pub fn get_binops(&self) -> Vec<&Expr> {
+        let mut res = Vec::new();
+        let mut stack = vec![self];
+        while let Some(e) = stack.pop() {
+            if let Expr::BinOp(l, _, r) = e {
+                res.push(e);
+                stack.push(r);
+                stack.push(l);
+            }
+        }
+        res
+    }
+
+    pub fn contains_id(&self, id: &str) -> bool {
+        match self {
+            Expr::Id(i) => i == id,
+            Expr::Int(_) => false,
+            Expr::BinOp(l, _, r) => l.contains_id(id) || r.contains_id(id),
+        }
+    }
+}
+
+impl Operator {
+    // Arithmetic wraps around on overflow, `<` gives 1 or 0.
+    // Division and modulo by zero have no value.
+    pub fn apply(&self, l: i32, r: i32) -> Option<i32> {
+        match self {
+            Operator::Plus => Some(l.wrapping_add(r)),
+            Operator::Minus => Some(l.wrapping_sub(r)),
+            Operator::Mult => Some(l.wrapping_mul(r)),
+            Operator::Div if r == 0 => None,
+            Operator::Div => Some(l.wrapping_div(r)),
+            Operator::Mod if r == 0 => None,
+            Operator::Mod => Some(l.wrapping_rem(r)),
+            Operator::LessThan => Some((l < r) as i32),
+        }
+    }
+
+    // Whether `l op r` always equals `r op l`
+    pub fn is_commutative(&self) -> bool {
+        matches!(self, Operator::Plus | Operator::Mult)
     }
 }
 
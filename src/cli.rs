@@ -0,0 +1,606 @@
+use crate::ast::*;
+use crate::diagnostics::{check_program, parse_source, render_all, Diagnostic, Severity, SourceFile};
+use crate::dot::DotOptions;
+use crate::json::{document, tokens_to_json, Json, ToJson};
+use crate::lexer::Lexer;
+use crate::liveness::ControlFlowGraph;
+use crate::repl::Repl;
+use crate::watch::Watcher;
+use std::collections::BTreeSet;
+use std::fmt::Display;
+use std::io::{BufReader, Read, Write};
+
+pub const EXIT_OK: i32 = 0;
+// The program could not be lexed or parsed, or check found errors
+pub const EXIT_INPUT: i32 = 1;
+pub const EXIT_USAGE: i32 = 2;
+// Reading the input or writing the output failed
+pub const EXIT_IO: i32 = 3;
+
+pub const USAGE: &str = "\
+usage: live-crab <command> [options] [file]
+
+Reads the program from file, or stdin if it is missing or `-`.
+
+commands:
+  tokens      the tokens of the program
+  check       warnings about the program: uninitialized reads, dead stores, unreachable code
+  ast         the parsed program
+  cfg         the control flow graph
+  live        live-in and live-out of every node
+  fixpoint    live-in and live-out after every iteration of the solver, changes marked with *
+  dot         the control flow graph in DOT, edges labelled with live variables
+  ranges      the edges each variable is live on
+  regalloc    registers from coloring the interference graph
+  repl        type statements and inspect the program as it grows, starting from file if given
+  watch       rerun a command whenever file changes, printing its diagnostics
+
+options:
+  -s, --solver <naive|fast>   liveness solver (default fast)
+  -f, --format <format>       output format: text, dot, markdown, csv, latex or json (default text, dot for `dot`);
+                              live can output any, cfg and regalloc text, dot or json,
+                              tokens, ast and fixpoint text or json
+  -o, --output <file>         write the output to file instead of stdout
+  -k, --registers <n>         registers for regalloc (default 3)
+      --var <name>            only this variable's range for ranges
+      --color                 color diagnostics
+      --html                  draw DOT nodes as tables of use/def and live in/out
+      --highlight <var>       fill the DOT nodes var is live at, color the edges it is live on
+      --highlight-color <c>   Graphviz color for --highlight (default lightblue)
+      --no-clusters           do not group the DOT nodes of loops
+  -c, --command <command>     the command watch reruns (default live)
+      --dot <file>            for watch, the same as -f dot -o <file>
+      --interval <ms>         how often watch looks at the file (default 500)
+  -h, --help                  print this help
+";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Tokens,
+    Check,
+    Ast,
+    Cfg,
+    Live,
+    Fixpoint,
+    Dot,
+    Ranges,
+    Regalloc,
+    Repl,
+    Watch,
+}
+
+const COMMANDS: [Command; 11] = [
+    Command::Tokens,
+    Command::Check,
+    Command::Ast,
+    Command::Cfg,
+    Command::Live,
+    Command::Fixpoint,
+    Command::Dot,
+    Command::Ranges,
+    Command::Regalloc,
+    Command::Repl,
+    Command::Watch,
+];
+
+impl Command {
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Command::Tokens => "tokens",
+            Command::Check => "check",
+            Command::Ast => "ast",
+            Command::Cfg => "cfg",
+            Command::Live => "live",
+            Command::Fixpoint => "fixpoint",
+            Command::Dot => "dot",
+            Command::Ranges => "ranges",
+            Command::Regalloc => "regalloc",
+            Command::Repl => "repl",
+            Command::Watch => "watch",
+        }
+    }
+    fn from_name(s: &str) -> Option<Command> {
+        COMMANDS.iter().find(|c| c.get_name() == s).cloned()
+    }
+
+    // The formats it can output, the first one is the default
+    fn formats(&self) -> &'static [Format] {
+        match self {
+            Command::Check | Command::Ranges | Command::Repl => &[Format::Text],
+            Command::Tokens | Command::Ast | Command::Fixpoint => &[Format::Text, Format::Json],
+            Command::Cfg | Command::Regalloc => &[Format::Text, Format::Dot, Format::Json],
+            Command::Watch => &[Format::Text, Format::Dot],
+            Command::Live => &[
+                Format::Text,
+                Format::Dot,
+                Format::Markdown,
+                Format::Csv,
+                Format::Latex,
+                Format::Json,
+            ],
+            Command::Dot => &[Format::Dot],
+        }
+    }
+}
+
+// perform_liveness_analysis or fast_perform_liveness_analysis
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Solver {
+    Naive,
+    Fast,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Dot,
+    Markdown,
+    Csv,
+    Latex,
+    Json,
+}
+
+impl Format {
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Format::Text => "text",
+            Format::Dot => "dot",
+            Format::Markdown => "markdown",
+            Format::Csv => "csv",
+            Format::Latex => "latex",
+            Format::Json => "json",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Options {
+    command: Command,
+    input: Option<String>,
+    output: Option<String>,
+    solver: Solver,
+    format: Format,
+    registers: usize,
+    var: Option<String>,
+    color: bool,
+    watched: Command,
+    interval: u64,
+    dot: DotOptions,
+}
+
+impl Options {
+    pub fn get_command(&self) -> Command {
+        self.command
+    }
+    // None reads stdin
+    pub fn get_input(&self) -> Option<&String> {
+        self.input.as_ref()
+    }
+    // None writes to stdout
+    pub fn get_output(&self) -> Option<&String> {
+        self.output.as_ref()
+    }
+    pub fn get_solver(&self) -> Solver {
+        self.solver
+    }
+    pub fn get_format(&self) -> Format {
+        self.format
+    }
+    pub fn get_registers(&self) -> usize {
+        self.registers
+    }
+    pub fn get_var(&self) -> Option<&String> {
+        self.var.as_ref()
+    }
+    pub fn get_color(&self) -> bool {
+        self.color
+    }
+    // The command watch reruns
+    pub fn get_watched(&self) -> Command {
+        self.watched
+    }
+    // In milliseconds
+    pub fn get_interval(&self) -> u64 {
+        self.interval
+    }
+    pub fn get_dot_options(&self) -> &DotOptions {
+        &self.dot
+    }
+    // The options of the command watch reruns
+    pub(crate) fn watched_options(&self) -> Options {
+        Options {
+            command: self.watched,
+            ..self.clone()
+        }
+    }
+    // What diagnostics call the input
+    pub fn get_source_name(&self) -> &str {
+        self.input.as_deref().unwrap_or("<stdin>")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CliError {
+    Usage(String),
+    // Rendered diagnostics for the lex or parse error, or the errors check found
+    Input(String),
+    Io(String),
+}
+
+impl CliError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CliError::Usage(_) => EXIT_USAGE,
+            CliError::Input(_) => EXIT_INPUT,
+            CliError::Io(_) => EXIT_IO,
+        }
+    }
+}
+
+impl Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CliError::Usage(msg) | CliError::Input(msg) | CliError::Io(msg) => write!(f, "{msg}"),
+        }
+    }
+}
+
+// Ok(None) when help was asked for
+pub fn parse_args(args: &[String]) -> Result<Option<Options>, CliError> {
+    let usage = |msg: String| Err(CliError::Usage(msg));
+    let mut command = None;
+    let mut input = None;
+    let mut output = None;
+    let mut solver = Solver::Fast;
+    let mut format = None;
+    let mut registers = 3;
+    let mut var = None;
+    let mut color = false;
+    let mut watched = None;
+    let mut interval = 500;
+    let mut dot = DotOptions::new();
+
+    let mut it = args.iter();
+    while let Some(arg) = it.next() {
+        let mut value = |name: &str| match it.next() {
+            Some(v) => Ok(v.clone()),
+            None => Err(CliError::Usage(format!("{name} needs a value"))),
+        };
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-s" | "--solver" => {
+                solver = match value(arg)?.as_str() {
+                    "naive" => Solver::Naive,
+                    "fast" => Solver::Fast,
+                    s => return usage(format!("unknown solver `{s}`, expected naive or fast")),
+                }
+            }
+            "-f" | "--format" => {
+                format = match value(arg)?.as_str() {
+                    "text" => Some(Format::Text),
+                    "dot" => Some(Format::Dot),
+                    "markdown" | "md" => Some(Format::Markdown),
+                    "csv" => Some(Format::Csv),
+                    "latex" | "tex" => Some(Format::Latex),
+                    "json" => Some(Format::Json),
+                    s => return usage(format!("unknown format `{s}`, expected text, dot, markdown, csv, latex or json")),
+                }
+            }
+            "-o" | "--output" => output = Some(value(arg)?),
+            "-k" | "--registers" => {
+                let k = value(arg)?;
+                registers = match k.parse() {
+                    Ok(k) if k > 0 => k,
+                    _ => return usage(format!("`{k}` is not a positive number of registers")),
+                }
+            }
+            "--var" => var = Some(value(arg)?),
+            "--color" => color = true,
+            "-c" | "--command" => {
+                let c = value(arg)?;
+                watched = match Command::from_name(&c) {
+                    Some(c) => Some(c),
+                    None => return usage(format!("unknown command `{c}`")),
+                }
+            }
+            "--html" => dot = dot.with_html_labels(true),
+            "--highlight" => dot = dot.with_highlight(&value(arg)?),
+            "--highlight-color" => dot = dot.with_highlight_color(&value(arg)?),
+            "--no-clusters" => dot = dot.with_clusters(false),
+            "--dot" => {
+                format = Some(Format::Dot);
+                output = Some(value(arg)?);
+            }
+            "--interval" => {
+                let ms = value(arg)?;
+                interval = match ms.parse() {
+                    Ok(ms) if ms > 0 => ms,
+                    _ => return usage(format!("`{ms}` is not a positive number of milliseconds")),
+                }
+            }
+            "-" => input = Some(arg.clone()),
+            s if s.starts_with('-') => return usage(format!("unknown option `{s}`")),
+            s if command.is_none() => match Command::from_name(s) {
+                Some(c) => command = Some(c),
+                None => return usage(format!("unknown command `{s}`")),
+            },
+            s if input.is_none() => input = Some(s.to_string()),
+            s => return usage(format!("unexpected argument `{s}`")),
+        }
+    }
+
+    let command = match command {
+        Some(c) => c,
+        None => return usage(String::from("missing command")),
+    };
+    let watched = match (command, watched) {
+        (Command::Watch, Some(Command::Check | Command::Repl | Command::Watch)) => {
+            return usage(String::from("`watch` can rerun any command but check, repl and watch"))
+        }
+        (Command::Watch, Some(c)) => c,
+        (Command::Watch, None) => Command::Live,
+        (_, Some(_)) => return usage(String::from("--command is only for `watch`")),
+        (c, None) => c,
+    };
+    if command == Command::Watch && input.as_ref().is_none_or(|i| i == "-") {
+        return usage(String::from("`watch` needs a file"));
+    }
+    let format = format.unwrap_or(watched.formats()[0]);
+    if !watched.formats().contains(&format) {
+        return usage(format!("`{}` cannot output {}", watched.get_name(), format.get_name()));
+    }
+    if command == Command::Repl && output.is_some() {
+        return usage(String::from("`repl` writes to stdout only"));
+    }
+    Ok(Some(Options {
+        command,
+        input: input.filter(|i| i != "-"),
+        output,
+        solver,
+        format,
+        registers,
+        var,
+        color,
+        watched,
+        interval,
+        dot,
+    }))
+}
+
+fn input_error(opts: &Options, src: &str, ds: &[Diagnostic]) -> CliError {
+    let file = SourceFile::new(opts.get_source_name(), src);
+    CliError::Input(render_all(ds, &file, opts.color))
+}
+
+pub(crate) fn analyzed_cfg(p: &Program, solver: Solver) -> ControlFlowGraph {
+    let mut cfg = ControlFlowGraph::from(p);
+    match solver {
+        Solver::Naive => cfg.perform_liveness_analysis(),
+        Solver::Fast => cfg.fast_perform_liveness_analysis(),
+    }
+    cfg
+}
+
+fn fmt_set<'a>(vars: impl Iterator<Item = &'a String>) -> String {
+    let vars: BTreeSet<&String> = vars.collect();
+    let vars: Vec<&str> = vars.into_iter().map(|v| v.as_str()).collect();
+    format!("{{{}}}", vars.join(", "))
+}
+
+fn program_vars(cfg: &ControlFlowGraph) -> BTreeSet<String> {
+    cfg.get_nodes()
+        .iter()
+        .flat_map(|n| n.get_uses().iter().chain(n.get_defs().iter()).cloned())
+        .collect()
+}
+
+// live-in and live-out of every node, the CFG has to be analyzed
+pub(crate) fn live_table(cfg: &ControlFlowGraph) -> String {
+    let mut res = String::from("node\tstatement\tin\tout\n");
+    for (idx, n) in cfg.get_nodes().iter().enumerate() {
+        res.push_str(&format!(
+            "{idx}\t{}\t{}\t{}\n",
+            n.get_node_kind(),
+            fmt_set(cfg.get_live_in(idx).iter()),
+            fmt_set(cfg.get_live_out(idx).iter())
+        ));
+    }
+    res
+}
+
+// The table textbooks work liveness exercises in: use and def of every node,
+// then in and out after each iteration, with the entries it changed marked
+//
+//                   1              2
+// node  use  def    in     out     in     out
+// 0          {a}    {}     {a}*    {}     {a}
+pub(crate) fn fixpoint_table(cfg: &mut ControlFlowGraph, solver: Solver) -> String {
+    let trace = match solver {
+        Solver::Naive => cfg.trace_liveness_analysis(),
+        Solver::Fast => cfg.fast_trace_liveness_analysis(),
+    };
+    let mark = |set: &BTreeSet<String>, changed: bool| match changed {
+        true => fmt_set(set.iter()) + "*",
+        false => fmt_set(set.iter()),
+    };
+    let mut top = vec![String::new(); 3];
+    let mut rows = vec![vec![String::from("node"), String::from("use"), String::from("def")]];
+    for it in 1..=trace.get_iteration_count() {
+        top.extend([it.to_string(), String::new()]);
+        rows[0].extend([String::from("in"), String::from("out")]);
+    }
+    for (idx, n) in cfg.get_nodes().iter().enumerate() {
+        let mut row = vec![idx.to_string(), fmt_set(n.get_uses().iter()), fmt_set(n.get_defs().iter())];
+        for it in trace.get_iterations() {
+            row.push(mark(it.get_live_in(idx), it.in_changed(idx)));
+            row.push(mark(it.get_live_out(idx), it.out_changed(idx)));
+        }
+        rows.push(row);
+    }
+    rows.insert(0, top);
+
+    let widths: Vec<usize> = (0..rows[0].len())
+        .map(|c| rows.iter().map(|r| r[c].chars().count()).max().unwrap_or(0))
+        .collect();
+    let mut res = String::new();
+    for row in rows.iter() {
+        let cells: Vec<String> = row.iter().zip(widths.iter()).map(|(c, w)| format!("{c:w$}")).collect();
+        res.push_str(cells.join("  ").trim_end());
+        res.push('\n');
+    }
+    let visits = match solver {
+        Solver::Naive => "in order",
+        Solver::Fast => "backwards",
+    };
+    res.push_str(&format!(
+        "{} iterations visiting the nodes {visits}, the last one changes nothing\n",
+        trace.get_iteration_count()
+    ));
+    res
+}
+
+// x: 0 -> 1, 1 -> 2
+pub(crate) fn range_line(cfg: &ControlFlowGraph, var: &str) -> String {
+    let mut range = cfg.get_live_range(var.to_string());
+    range.sort();
+    let edges: Vec<String> = range.iter().map(|(a, b)| format!("{a} -> {b}")).collect();
+    format!("{var}: {}\n", edges.join(", "))
+}
+
+// What the command writes for the given source
+pub fn render(opts: &Options, src: &str) -> Result<String, CliError> {
+    if opts.command == Command::Tokens {
+        let tokens = Lexer::new(src)
+            .tokenize_with_spans()
+            .map_err(|e| input_error(opts, src, &[Diagnostic::from_lex_error(&e)]))?;
+        if opts.format == Format::Json {
+            return Ok(document("tokens", tokens_to_json(&tokens)).pretty() + "\n");
+        }
+        return Ok(tokens.iter().map(|(t, _)| format!("{t:?}\n")).collect());
+    }
+    let parsed = parse_source(src).map_err(|d| input_error(opts, src, &[d]))?;
+    let p = parsed.get_program();
+
+    let res = match (opts.command, opts.format) {
+        (Command::Check, _) => {
+            let ds = check_program(&parsed);
+            if ds.iter().any(|d| d.get_severity() == Severity::Error) {
+                return Err(input_error(opts, src, &ds));
+            }
+            render_all(&ds, &SourceFile::new(opts.get_source_name(), src), opts.color)
+        }
+        (Command::Ast, Format::Json) => document("program", p.to_json()).pretty() + "\n",
+        (Command::Ast, _) => p.to_string(),
+        (Command::Cfg, Format::Json) => document("cfg", ControlFlowGraph::from(p).to_json()).pretty() + "\n",
+        (Command::Live, Format::Json) => document("cfg", analyzed_cfg(p, opts.solver).to_json()).pretty() + "\n",
+        (Command::Fixpoint, Format::Json) => {
+            let mut cfg = ControlFlowGraph::from(p);
+            let trace = match opts.solver {
+                Solver::Naive => cfg.trace_liveness_analysis(),
+                Solver::Fast => cfg.fast_trace_liveness_analysis(),
+            };
+            document("liveness_trace", trace.to_json()).pretty() + "\n"
+        }
+        (Command::Cfg, Format::Text) => ControlFlowGraph::from(p).to_string(),
+        (Command::Cfg, Format::Dot) => ControlFlowGraph::from(p).generate_dot_with(&opts.dot) + "\n",
+        (Command::Live | Command::Dot, Format::Dot) => analyzed_cfg(p, opts.solver).generate_dot_with(&opts.dot) + "\n",
+        (Command::Live, Format::Markdown) => analyzed_cfg(p, opts.solver).liveness_table().to_markdown(),
+        (Command::Live, Format::Csv) => analyzed_cfg(p, opts.solver).liveness_table().to_csv(),
+        (Command::Live, Format::Latex) => analyzed_cfg(p, opts.solver).liveness_table().to_latex(),
+        (Command::Live, _) => live_table(&analyzed_cfg(p, opts.solver)),
+        (Command::Fixpoint, _) => fixpoint_table(&mut ControlFlowGraph::from(p), opts.solver),
+        (Command::Ranges, _) => {
+            let cfg = analyzed_cfg(p, opts.solver);
+            let vars = match &opts.var {
+                Some(v) => BTreeSet::from([v.clone()]),
+                None => program_vars(&cfg),
+            };
+            vars.iter().map(|v| range_line(&cfg, v)).collect()
+        }
+        (Command::Regalloc, format) => {
+            let graph = analyzed_cfg(p, opts.solver).interference_graph();
+            let coloring = graph.color(opts.registers);
+            match format {
+                Format::Dot => return Ok(graph.generate_dot() + "\n"),
+                Format::Json => {
+                    let data = Json::object(vec![("graph", graph.to_json()), ("coloring", coloring.to_json())]);
+                    return Ok(document("regalloc", data).pretty() + "\n");
+                }
+                _ => (),
+            }
+            let mut res = String::from("var\treg\tinterferes\n");
+            for v in graph.get_vars() {
+                let reg = match coloring.get_color(v) {
+                    Some(c) => format!("r{c}"),
+                    None => String::from("spill"),
+                };
+                res.push_str(&format!("{v}\t{reg}\t{}\n", fmt_set(graph.get_neighbours(v).into_iter())));
+            }
+            res
+        }
+        // parse_args only allows the formats of each command
+        (Command::Tokens | Command::Repl | Command::Watch, _) | (Command::Cfg | Command::Dot, _) => unreachable!(),
+    };
+    Ok(res)
+}
+
+// The file is loaded as if by :load, then the session reads stdin
+fn repl(opts: &Options, stdin: &mut dyn Read, out: &mut dyn Write) -> Result<(), CliError> {
+    let mut repl = Repl::new().with_solver(opts.solver);
+    let io_err = |e: std::io::Error| CliError::Io(format!("cannot write output: {e}"));
+    if let Some(path) = &opts.input {
+        write!(out, "{}", repl.handle_line(&format!(":load {path}"))).map_err(io_err)?;
+    }
+    repl.run(&mut BufReader::new(stdin), out).map_err(io_err)
+}
+
+fn execute(opts: &Options, stdin: &mut dyn Read, out: &mut dyn Write) -> Result<(), CliError> {
+    match opts.command {
+        Command::Repl => return repl(opts, stdin, out),
+        Command::Watch => return Watcher::new(opts).run(out),
+        _ => (),
+    }
+    let src = match &opts.input {
+        Some(path) => std::fs::read_to_string(path).map_err(|e| CliError::Io(format!("cannot read {path}: {e}")))?,
+        None => {
+            let mut s = String::new();
+            stdin
+                .read_to_string(&mut s)
+                .map_err(|e| CliError::Io(format!("cannot read stdin: {e}")))?;
+            s
+        }
+    };
+    let res = render(opts, &src)?;
+    match &opts.output {
+        Some(path) => std::fs::write(path, res).map_err(|e| CliError::Io(format!("cannot write {path}: {e}"))),
+        None => out
+            .write_all(res.as_bytes())
+            .map_err(|e| CliError::Io(format!("cannot write output: {e}"))),
+    }
+}
+
+// Runs the command line (without the program name) and returns the exit code
+pub fn run(args: &[String], stdin: &mut dyn Read, out: &mut dyn Write, err: &mut dyn Write) -> i32 {
+    let res = match parse_args(args) {
+        Ok(None) => {
+            let _ = write!(out, "{USAGE}");
+            return EXIT_OK;
+        }
+        Ok(Some(opts)) => execute(&opts, stdin, out),
+        Err(e) => Err(e),
+    };
+    match res {
+        Ok(()) => EXIT_OK,
+        Err(CliError::Input(diagnostics)) => {
+            let _ = write!(err, "{diagnostics}");
+            EXIT_INPUT
+        }
+        Err(e) => {
+            let _ = writeln!(err, "error: {e}");
+            if let CliError::Usage(_) = e {
+                let _ = write!(err, "\n{USAGE}");
+            }
+            e.exit_code()
+        }
+    }
+}
@@ -0,0 +1,254 @@
+use crate::ast::*;
+use crate::liveness::{ControlFlowGraph, NodeKind};
+use crate::rewrite::ProgramEdits;
+use std::collections::{BTreeMap, BTreeSet};
+
+// The constant propagation lattice of a single variable:
+// Unknown (not seen yet) > Constant(c) > Overdefined (not a constant)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConstValue {
+    Unknown,
+    Constant(i32),
+    Overdefined,
+}
+
+impl ConstValue {
+    pub fn meet(self, other: ConstValue) -> ConstValue {
+        match (self, other) {
+            (ConstValue::Unknown, v) | (v, ConstValue::Unknown) => v,
+            (ConstValue::Constant(a), ConstValue::Constant(b)) if a == b => ConstValue::Constant(a),
+            _ => ConstValue::Overdefined,
+        }
+    }
+}
+
+pub type ConstEnv = BTreeMap<String, ConstValue>;
+
+// Value of an expression, variables missing from `env` are Overdefined
+pub fn eval_const(e: &Expr, env: &ConstEnv) -> ConstValue {
+    match e {
+        Expr::Int(n) => ConstValue::Constant(*n),
+        Expr::Id(id) => env.get(id).copied().unwrap_or(ConstValue::Overdefined),
+        Expr::BinOp(l, op, r) => match (eval_const(l, env), eval_const(r, env)) {
+            (ConstValue::Constant(a), ConstValue::Constant(b)) => {
+                op.apply(a, b).map_or(ConstValue::Overdefined, ConstValue::Constant)
+            }
+            (ConstValue::Overdefined, _) | (_, ConstValue::Overdefined) => ConstValue::Overdefined,
+            _ => ConstValue::Unknown,
+        },
+    }
+}
+
+// Replaces constant variables and folds constant subexpressions. i32::MIN
+// is left unfolded, as there is no literal for it the parser would accept.
+pub fn fold_expr(e: &Expr, env: &ConstEnv) -> Expr {
+    match eval_const(e, env) {
+        ConstValue::Constant(c) if c != i32::MIN => Expr::Int(c),
+        _ => match e {
+            Expr::BinOp(l, op, r) => Expr::BinOp(
+                Box::new(fold_expr(l, env)),
+                *op,
+                Box::new(fold_expr(r, env)),
+            ),
+            e => e.clone(),
+        },
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConstantAnalysis {
+    ins: Vec<ConstEnv>,
+    outs: Vec<ConstEnv>,
+    executable: Vec<bool>,
+}
+
+impl ConstantAnalysis {
+    pub fn get_in(&self, n: usize) -> &ConstEnv {
+        &self.ins[n]
+    }
+    pub fn get_out(&self, n: usize) -> &ConstEnv {
+        &self.outs[n]
+    }
+    // Value of `var` on entry to node n
+    pub fn get_value(&self, n: usize, var: &str) -> ConstValue {
+        self.ins[n].get(var).copied().unwrap_or(ConstValue::Overdefined)
+    }
+    // Whether any execution can reach the node, given the constant conditions
+    pub fn is_executable(&self, n: usize) -> bool {
+        self.executable[n]
+    }
+}
+
+impl ControlFlowGraph {
+    // Conditional constant propagation. Every variable is Overdefined at the
+    // entry, as it may be an input, and only edges a condition can take are
+    // followed.
+    pub fn constant_propagation(&self) -> ConstantAnalysis {
+        let nodes = self.get_nodes();
+        let n = nodes.len();
+
+        let mut preds = vec![Vec::new(); n];
+        let mut vars = BTreeSet::new();
+        for (idx, node) in nodes.iter().enumerate() {
+            for s in node.get_succs() {
+                preds[*s].push(idx);
+            }
+            vars.extend(node.get_uses().iter().cloned());
+            vars.extend(node.get_defs().iter().cloned());
+        }
+        let unknown: ConstEnv = vars.iter().map(|v| (v.clone(), ConstValue::Unknown)).collect();
+        let entry: ConstEnv = vars.iter().map(|v| (v.clone(), ConstValue::Overdefined)).collect();
+
+        let mut ins = vec![unknown.clone(); n];
+        let mut outs = vec![unknown.clone(); n];
+        let mut executable = vec![false; n];
+        let mut exec_edges: BTreeSet<(usize, usize)> = BTreeSet::new();
+        if n > 0 {
+            executable[0] = true;
+        }
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (idx, node) in nodes.iter().enumerate() {
+                if !executable[idx] {
+                    continue;
+                }
+                let mut env = if idx == 0 { entry.clone() } else { unknown.clone() };
+                for p in preds[idx].iter() {
+                    if exec_edges.contains(&(*p, idx)) {
+                        for (var, val) in env.iter_mut() {
+                            *val = val.meet(outs[*p][var]);
+                        }
+                    }
+                }
+
+                let mut out = env.clone();
+                let targets: Vec<usize> = match node.get_node_kind() {
+                    NodeKind::Assignment(lvl, e) => {
+                        if let Expr::Id(x) = lvl.as_ref() {
+                            out.insert(x.clone(), eval_const(e, &env));
+                        }
+                        node.get_succs().iter().cloned().collect()
+                    }
+                    NodeKind::Empty => node.get_succs().iter().cloned().collect(),
+                    NodeKind::Condition(e) => match eval_const(e, &env) {
+                        ConstValue::Constant(c) if c != 0 => node.get_true_succ().into_iter().collect(),
+                        ConstValue::Constant(_) => node.get_false_succ().into_iter().collect(),
+                        ConstValue::Overdefined => node.get_succs().iter().cloned().collect(),
+                        ConstValue::Unknown => Vec::new(),
+                    },
+                    NodeKind::Return(_) => Vec::new(),
+                };
+
+                for t in targets {
+                    changed |= exec_edges.insert((idx, t));
+                    if !executable[t] {
+                        executable[t] = true;
+                        changed = true;
+                    }
+                }
+                if env != ins[idx] || out != outs[idx] {
+                    changed = true;
+                    ins[idx] = env;
+                    outs[idx] = out;
+                }
+            }
+        }
+
+        ConstantAnalysis {
+            ins,
+            outs,
+            executable,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ConstPropResult {
+    program: Program,
+    analysis: ConstantAnalysis,
+    before: ControlFlowGraph,
+    after: ControlFlowGraph,
+}
+
+impl ConstPropResult {
+    pub fn get_program(&self) -> &Program {
+        &self.program
+    }
+    // The analysis of the original program
+    pub fn get_analysis(&self) -> &ConstantAnalysis {
+        &self.analysis
+    }
+    // CFG of the original program, with liveness performed
+    pub fn get_before(&self) -> &ControlFlowGraph {
+        &self.before
+    }
+    // CFG of the rewritten program, with liveness performed
+    pub fn get_after(&self) -> &ControlFlowGraph {
+        &self.after
+    }
+}
+
+// Constant propagation and folding: uses of constant variables are replaced,
+// constant subexpressions are folded, and branches on constant conditions
+// are removed.
+pub fn propagate_constants(p: &Program) -> ConstPropResult {
+    let mut before = ControlFlowGraph::from(p);
+    let analysis = before.constant_propagation();
+
+    let mut edits = ProgramEdits::new();
+    for (idx, node) in before.get_nodes().iter().enumerate() {
+        if !analysis.is_executable(idx) {
+            continue;
+        }
+        let env = analysis.get_in(idx);
+        let kind = node.get_node_kind();
+        let folded = match kind {
+            NodeKind::Assignment(lvl, e) => NodeKind::Assignment(lvl.clone(), Box::new(fold_expr(e, env))),
+            NodeKind::Return(e) => NodeKind::Return(Box::new(fold_expr(e, env))),
+            NodeKind::Condition(e) => NodeKind::Condition(Box::new(fold_expr(e, env))),
+            NodeKind::Empty => NodeKind::Empty,
+        };
+        if folded != *kind {
+            edits.replace(idx, folded);
+        }
+    }
+
+    let program = Program::new(simplify_constant_branches(&edits.apply(p).stmts));
+    before.fast_perform_liveness_analysis();
+    let mut after = ControlFlowGraph::from(&program);
+    after.fast_perform_liveness_analysis();
+
+    ConstPropResult {
+        program,
+        analysis,
+        before,
+        after,
+    }
+}
+
+// if (1) { b }  => b          if (0) { b }        => nothing
+// while (0) { b } => nothing  do { b } while (0); => b
+pub fn simplify_constant_branches(stmts: &[Statement]) -> Vec<Statement> {
+    let mut res = Vec::new();
+    for stmt in stmts {
+        match stmt {
+            Statement::If(cond, body) => match cond.as_ref() {
+                Expr::Int(0) => {}
+                Expr::Int(_) => res.extend(simplify_constant_branches(body)),
+                _ => res.push(Statement::If(cond.clone(), simplify_constant_branches(body))),
+            },
+            Statement::While(cond, body) => match cond.as_ref() {
+                Expr::Int(0) => {}
+                _ => res.push(Statement::While(cond.clone(), simplify_constant_branches(body))),
+            },
+            Statement::DoWhile(body, cond) => match cond.as_ref() {
+                Expr::Int(0) => res.extend(simplify_constant_branches(body)),
+                _ => res.push(Statement::DoWhile(simplify_constant_branches(body), cond.clone())),
+            },
+            s => res.push(s.clone()),
+        }
+    }
+    res
+}
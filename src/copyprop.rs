@@ -0,0 +1,160 @@
+use crate::ast::*;
+use crate::dataflow::{DataflowProblem, DataflowResult, Direction, Meet};
+use crate::deadcode::eliminate_dead_assignments;
+use crate::interference::InterferenceGraph;
+use crate::liveness::{ControlFlowGraph, NodeKind};
+use crate::rewrite::ProgramEdits;
+use std::collections::BTreeSet;
+
+// A copy `a = b`, stored as (a, b)
+pub type CopyPair = (String, String);
+
+fn get_copy(kind: &NodeKind) -> Option<CopyPair> {
+    match kind {
+        NodeKind::Assignment(lvl, e) => match (lvl.as_ref(), e.as_ref()) {
+            (Expr::Id(a), Expr::Id(b)) if a != b => Some((a.clone(), b.clone())),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+impl ControlFlowGraph {
+    // Available copies: `a = b` holds on every path to the node, and neither
+    // a nor b has been redefined since
+    pub fn available_copies(&self) -> DataflowResult<CopyPair> {
+        let nodes = self.get_nodes();
+        let universe: BTreeSet<CopyPair> = nodes.iter().filter_map(|n| get_copy(n.get_node_kind())).collect();
+
+        let mut gen = Vec::new();
+        let mut kill = Vec::new();
+        for n in nodes.iter() {
+            let copy = get_copy(n.get_node_kind());
+            let k: BTreeSet<CopyPair> = universe
+                .iter()
+                .filter(|(a, b)| n.get_defs().contains(a) || n.get_defs().contains(b))
+                .filter(|c| Some(*c) != copy.as_ref())
+                .cloned()
+                .collect();
+            gen.push(copy.into_iter().collect());
+            kill.push(k);
+        }
+
+        DataflowProblem {
+            direction: Direction::Forward,
+            meet: Meet::Intersection,
+            gen,
+            kill,
+            boundary: BTreeSet::new(),
+            universe,
+        }
+        .solve(self)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct CopyPropResult {
+    program: Program,
+    replaced: usize,
+    removed: Vec<Statement>,
+    before: ControlFlowGraph,
+    after: ControlFlowGraph,
+}
+
+impl CopyPropResult {
+    pub fn get_program(&self) -> &Program {
+        &self.program
+    }
+    // Number of variable uses that were replaced by the copy source
+    pub fn get_replaced(&self) -> usize {
+        self.replaced
+    }
+    // Assignments removed as dead afterwards
+    pub fn get_removed(&self) -> &Vec<Statement> {
+        &self.removed
+    }
+    // CFG of the original program, with liveness performed
+    pub fn get_before(&self) -> &ControlFlowGraph {
+        &self.before
+    }
+    // CFG of the rewritten program, with liveness performed
+    pub fn get_after(&self) -> &ControlFlowGraph {
+        &self.after
+    }
+    pub fn get_interference_before(&self) -> InterferenceGraph {
+        self.before.interference_graph()
+    }
+    pub fn get_interference_after(&self) -> InterferenceGraph {
+        self.after.interference_graph()
+    }
+}
+
+// Copy propagation: a use of `a` is replaced by `b` wherever the copy
+// `a = b` is available, then copies left dead are removed.
+pub fn propagate_copies(p: &Program) -> CopyPropResult {
+    let mut before = ControlFlowGraph::from(p);
+    let copies = before.available_copies();
+
+    let mut edits = ProgramEdits::new();
+    let mut replaced = 0;
+    for (idx, n) in before.get_nodes().iter().enumerate() {
+        let avail = copies.get_in(idx);
+        if avail.is_empty() {
+            continue;
+        }
+        let kind = n.get_node_kind();
+        let new_kind = match kind {
+            NodeKind::Assignment(lvl, e) => {
+                NodeKind::Assignment(lvl.clone(), Box::new(replace_copies(e, avail, &mut replaced)))
+            }
+            NodeKind::Return(e) => NodeKind::Return(Box::new(replace_copies(e, avail, &mut replaced))),
+            NodeKind::Condition(e) => {
+                NodeKind::Condition(Box::new(replace_copies(e, avail, &mut replaced)))
+            }
+            NodeKind::Empty => NodeKind::Empty,
+        };
+        if new_kind != *kind {
+            edits.replace(idx, new_kind);
+        }
+    }
+
+    let (program, removed) = eliminate_dead_assignments(&edits.apply(p));
+    before.fast_perform_liveness_analysis();
+    let mut after = ControlFlowGraph::from(&program);
+    after.fast_perform_liveness_analysis();
+
+    CopyPropResult {
+        program,
+        replaced,
+        removed,
+        before,
+        after,
+    }
+}
+
+// Chains of copies (b = a; c = b) are followed to the first source, so a
+// use of c becomes a
+fn replace_copies(e: &Expr, avail: &BTreeSet<CopyPair>, replaced: &mut usize) -> Expr {
+    match e {
+        Expr::Id(id) => {
+            let mut cur = id;
+            let mut seen = BTreeSet::from([id]);
+            while let Some((_, src)) = avail.iter().find(|(a, _)| a == cur) {
+                if !seen.insert(src) {
+                    break;
+                }
+                cur = src;
+            }
+            if cur != id {
+                *replaced += 1;
+            }
+            Expr::Id(cur.clone())
+        }
+        Expr::Int(n) => Expr::Int(*n),
+        Expr::BinOp(l, op, r) => Expr::BinOp(
+            Box::new(replace_copies(l, avail, replaced)),
+            *op,
+            Box::new(replace_copies(r, avail, replaced)),
+        ),
+    }
+}
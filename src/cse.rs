@@ -0,0 +1,135 @@
+use crate::ast::*;
+use crate::liveness::ControlFlowGraph;
+use crate::rewrite::{NameGenerator, ProgramEdits};
+use std::collections::BTreeSet;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct CseResult {
+    program: Program,
+    temps: Vec<(Expr, String)>,
+    eliminated: usize,
+    before: ControlFlowGraph,
+    after: ControlFlowGraph,
+}
+
+impl CseResult {
+    pub fn get_program(&self) -> &Program {
+        &self.program
+    }
+    // The temporary introduced for each redundant expression
+    pub fn get_temps(&self) -> &Vec<(Expr, String)> {
+        &self.temps
+    }
+    // Number of computations replaced by a temporary
+    pub fn get_eliminated(&self) -> usize {
+        self.eliminated
+    }
+    // CFG of the original program, with liveness performed
+    pub fn get_before(&self) -> &ControlFlowGraph {
+        &self.before
+    }
+    // CFG of the rewritten program, with liveness performed
+    pub fn get_after(&self) -> &ControlFlowGraph {
+        &self.after
+    }
+}
+
+// Global common subexpression elimination.
+//
+// Every expression that is recomputed while available gets a temporary `t`.
+// Each computation of it that is not available is turned into `t = e`
+// followed by a use of `t`, and each available one just uses `t`. Since an
+// expression is available only if it was computed on every path, `t` always
+// holds its value where it is used. The temporaries stay live from the
+// computations to the reuses, so live ranges grow.
+pub fn eliminate_common_subexpressions(p: &Program) -> CseResult {
+    let mut before = ControlFlowGraph::from(p);
+    let avail = before.available_expressions();
+
+    // Redundant expressions, outermost first so a reused expression does not
+    // also get temporaries for its parts
+    let mut names = NameGenerator::new("t", p);
+    let mut temps: Vec<(Expr, String)> = Vec::new();
+    for (idx, n) in before.get_nodes().iter().enumerate() {
+        let mut stack: Vec<&Expr> = n.get_node_kind().get_expr().into_iter().collect();
+        while let Some(e) = stack.pop() {
+            if let Expr::BinOp(l, _, r) = e {
+                if avail.get_in(idx).contains(e) {
+                    if !temps.iter().any(|(t, _)| t == e) {
+                        temps.push((e.clone(), names.fresh()));
+                    }
+                } else {
+                    stack.push(r);
+                    stack.push(l);
+                }
+            }
+        }
+    }
+
+    let mut edits = ProgramEdits::new();
+    let mut eliminated = 0;
+    if !temps.is_empty() {
+        for (idx, n) in before.get_nodes().iter().enumerate() {
+            let kind = n.get_node_kind();
+            let Some(e) = kind.get_expr() else {
+                continue;
+            };
+            let mut pre = Vec::new();
+            let e = replace_redundant(e, avail.get_in(idx), &temps, &mut pre, &mut eliminated);
+            let new_kind = kind.with_expr(e);
+            for stmt in pre {
+                edits.insert_before(idx, stmt);
+            }
+            if new_kind != *kind {
+                edits.replace(idx, new_kind);
+            }
+        }
+    }
+
+    let program = edits.apply(p);
+    before.fast_perform_liveness_analysis();
+    let mut after = ControlFlowGraph::from(&program);
+    after.fast_perform_liveness_analysis();
+
+    CseResult {
+        program,
+        temps,
+        eliminated,
+        before,
+        after,
+    }
+}
+
+fn replace_redundant(
+    e: &Expr,
+    avail: &BTreeSet<Expr>,
+    temps: &[(Expr, String)],
+    pre: &mut Vec<Statement>,
+    eliminated: &mut usize,
+) -> Expr {
+    let Expr::BinOp(l, op, r) = e else {
+        return e.clone();
+    };
+    let temp = temps.iter().find(|(t, _)| t == e).map(|(_, name)| name);
+    if let Some(t) = temp {
+        if avail.contains(e) {
+            *eliminated += 1;
+            return Expr::Id(t.clone());
+        }
+    }
+    let inner = Expr::BinOp(
+        Box::new(replace_redundant(l, avail, temps, pre, eliminated)),
+        *op,
+        Box::new(replace_redundant(r, avail, temps, pre, eliminated)),
+    );
+    match temp {
+        Some(t) => {
+            pre.push(Statement::Assignment(
+                Box::new(Expr::Id(t.clone())),
+                Box::new(inner),
+            ));
+            Expr::Id(t.clone())
+        }
+        None => inner,
+    }
+}
@@ -0,0 +1,314 @@
+use crate::ast::*;
+use crate::liveness::{ControlFlowGraph, NodeKind};
+use std::collections::BTreeSet;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Forward,
+    Backward,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Meet {
+    // may-analysis
+    Union,
+    // must-analysis
+    Intersection,
+}
+
+// A classic gen/kill dataflow problem over the nodes of a CFG.
+//
+// Forward:  in[n]  = meet out[p], p = pred[n]     out[n] = gen[n] U (in[n] - kill[n])
+// Backward: out[n] = meet in[s],  s = succ[n]     in[n]  = gen[n] U (out[n] - kill[n])
+//
+// `boundary` is what flows into the entry (forward) or out of the exits
+// (backward). Exits are the nodes the program can end after, which includes
+// conditions with a branch to the end. Intersection problems start from
+// `universe` everywhere else.
+#[derive(Debug, Clone)]
+pub struct DataflowProblem<T> {
+    pub direction: Direction,
+    pub meet: Meet,
+    pub gen: Vec<BTreeSet<T>>,
+    pub kill: Vec<BTreeSet<T>>,
+    pub boundary: BTreeSet<T>,
+    pub universe: BTreeSet<T>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DataflowResult<T> {
+    ins: Vec<BTreeSet<T>>,
+    outs: Vec<BTreeSet<T>>,
+    iterations: usize,
+}
+
+impl<T> DataflowResult<T> {
+    pub fn get_in(&self, n: usize) -> &BTreeSet<T> {
+        &self.ins[n]
+    }
+    pub fn get_out(&self, n: usize) -> &BTreeSet<T> {
+        &self.outs[n]
+    }
+    pub fn get_ins(&self) -> &Vec<BTreeSet<T>> {
+        &self.ins
+    }
+    pub fn get_outs(&self) -> &Vec<BTreeSet<T>> {
+        &self.outs
+    }
+    pub fn get_iterations(&self) -> usize {
+        self.iterations
+    }
+}
+
+impl<T: Clone + Ord> DataflowProblem<T> {
+    pub fn solve(&self, cfg: &ControlFlowGraph) -> DataflowResult<T> {
+        let succs: Vec<Vec<usize>> = cfg
+            .get_nodes()
+            .iter()
+            .map(|n| n.get_succs().iter().cloned().collect())
+            .collect();
+        let exits: Vec<usize> = (0..succs.len()).filter(|n| cfg.get_node(*n).is_exit()).collect();
+        self.solve_graph(&succs, 0, &exits)
+    }
+
+    // Solves the problem over any graph given as successor lists, such as a
+    // CFG with extra nodes on some edges. `entry` gets the forward boundary,
+    // `exits` and nodes without successors the backward one.
+    pub fn solve_graph(&self, succs: &[Vec<usize>], entry: usize, exits: &[usize]) -> DataflowResult<T> {
+        let n = succs.len();
+        let succs = succs.to_vec();
+        let mut preds = vec![Vec::new(); n];
+        for (idx, ss) in succs.iter().enumerate() {
+            for s in ss {
+                preds[*s].push(idx);
+            }
+        }
+        // Edges to meet over, and whether the node touches the boundary
+        let (sources, order): (Vec<Vec<usize>>, Vec<usize>) = match self.direction {
+            Direction::Forward => (preds, (0..n).collect()),
+            Direction::Backward => (succs, (0..n).rev().collect()),
+        };
+        let on_boundary = |idx: usize, srcs: &Vec<usize>| match self.direction {
+            Direction::Forward => idx == entry,
+            Direction::Backward => srcs.is_empty() || exits.contains(&idx),
+        };
+
+        let init = match self.meet {
+            Meet::Union => BTreeSet::new(),
+            Meet::Intersection => self.universe.clone(),
+        };
+        // before: the side the meet is taken on, after: the transferred side
+        let mut before = vec![init.clone(); n];
+        let mut after = vec![init; n];
+
+        let mut iterations = 0;
+        let mut changed = true;
+        while changed {
+            changed = false;
+            iterations += 1;
+            for idx in order.iter() {
+                let idx = *idx;
+                let mut incoming: Vec<&BTreeSet<T>> =
+                    sources[idx].iter().map(|s| &after[*s]).collect();
+                if on_boundary(idx, &sources[idx]) {
+                    incoming.push(&self.boundary);
+                }
+                let met = meet(self.meet, &incoming);
+                let transferred: BTreeSet<T> = met
+                    .difference(&self.kill[idx])
+                    .cloned()
+                    .chain(self.gen[idx].iter().cloned())
+                    .collect();
+                if met != before[idx] || transferred != after[idx] {
+                    changed = true;
+                }
+                before[idx] = met;
+                after[idx] = transferred;
+            }
+        }
+
+        let (ins, outs) = match self.direction {
+            Direction::Forward => (before, after),
+            Direction::Backward => (after, before),
+        };
+        DataflowResult {
+            ins,
+            outs,
+            iterations,
+        }
+    }
+}
+
+fn meet<T: Clone + Ord>(meet: Meet, sets: &[&BTreeSet<T>]) -> BTreeSet<T> {
+    match meet {
+        Meet::Union => sets.iter().flat_map(|s| s.iter().cloned()).collect(),
+        Meet::Intersection => match sets.split_first() {
+            // no incoming edges, nothing is known to hold
+            None => BTreeSet::new(),
+            Some((first, rest)) => first
+                .iter()
+                .filter(|f| rest.iter().all(|s| s.contains(*f)))
+                .cloned()
+                .collect(),
+        },
+    }
+}
+
+// The variable defined by a node, if any
+pub fn get_def_var(kind: &NodeKind) -> Option<&String> {
+    match kind {
+        NodeKind::Assignment(lvl, _) => match lvl.as_ref() {
+            Expr::Id(id) => Some(id),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+impl ControlFlowGraph {
+    // Reaching definitions, a definition is identified by its node index
+    pub fn reaching_definitions(&self) -> DataflowResult<usize> {
+        let nodes = self.get_nodes();
+        let defs: Vec<Option<&String>> = nodes
+            .iter()
+            .map(|n| get_def_var(n.get_node_kind()))
+            .collect();
+
+        let mut gen = Vec::new();
+        let mut kill = Vec::new();
+        for (idx, d) in defs.iter().enumerate() {
+            match d {
+                Some(var) => {
+                    gen.push(BTreeSet::from([idx]));
+                    kill.push(
+                        (0..defs.len())
+                            .filter(|o| *o != idx && defs[*o] == Some(*var))
+                            .collect(),
+                    );
+                }
+                None => {
+                    gen.push(BTreeSet::new());
+                    kill.push(BTreeSet::new());
+                }
+            }
+        }
+
+        DataflowProblem {
+            direction: Direction::Forward,
+            meet: Meet::Union,
+            gen,
+            kill,
+            boundary: BTreeSet::new(),
+            universe: BTreeSet::new(),
+        }
+        .solve(self)
+    }
+
+    // Definitions of `var` reaching the start of node `n`
+    pub fn reaching_defs_of(&self, reaching: &DataflowResult<usize>, n: usize, var: &str) -> BTreeSet<usize> {
+        reaching
+            .get_in(n)
+            .iter()
+            .filter(|d| get_def_var(self.get_node(**d).get_node_kind()).is_some_and(|v| v == var))
+            .cloned()
+            .collect()
+    }
+}
+
+// Every BinOp computed somewhere in the CFG
+pub fn get_expression_universe(cfg: &ControlFlowGraph) -> BTreeSet<Expr> {
+    cfg.get_nodes()
+        .iter()
+        .flat_map(|n| n.get_node_kind().get_binops())
+        .cloned()
+        .collect()
+}
+
+// The same BinOps in order of first occurrence, outermost first
+pub fn get_expression_order(cfg: &ControlFlowGraph) -> Vec<Expr> {
+    let mut res: Vec<Expr> = Vec::new();
+    for n in cfg.get_nodes() {
+        for e in n.get_node_kind().get_binops() {
+            if !res.contains(e) {
+                res.push(e.clone());
+            }
+        }
+    }
+    res
+}
+
+// Per node, the expressions of `universe` using a variable the node defines
+pub fn get_expression_kills(cfg: &ControlFlowGraph, universe: &BTreeSet<Expr>) -> Vec<BTreeSet<Expr>> {
+    cfg.get_nodes()
+        .iter()
+        .map(|n| {
+            universe
+                .iter()
+                .filter(|e| n.get_defs().iter().any(|d| e.contains_id(d)))
+                .cloned()
+                .collect()
+        })
+        .collect()
+}
+
+impl ControlFlowGraph {
+    // Available expressions: e has been computed on every path to the node,
+    // and none of its operands have been redefined since
+    pub fn available_expressions(&self) -> DataflowResult<Expr> {
+        let universe = get_expression_universe(self);
+        let kill = get_expression_kills(self, &universe);
+        let gen = self
+            .get_nodes()
+            .iter()
+            .zip(kill.iter())
+            .map(|(n, k)| {
+                n.get_node_kind()
+                    .get_binops()
+                    .into_iter()
+                    .filter(|e| !k.contains(*e))
+                    .cloned()
+                    .collect()
+            })
+            .collect();
+
+        DataflowProblem {
+            direction: Direction::Forward,
+            meet: Meet::Intersection,
+            gen,
+            kill,
+            boundary: BTreeSet::new(),
+            universe,
+        }
+        .solve(self)
+    }
+
+    // Very busy expressions: on every path from the node, e is computed
+    // before any of its operands is redefined
+    pub fn very_busy_expressions(&self) -> DataflowResult<Expr> {
+        let universe = get_expression_universe(self);
+        let kill = get_expression_kills(self, &universe);
+        // The expression is evaluated before the node defines anything, so
+        // all of it counts, even if it uses the variable being assigned
+        let gen = self
+            .get_nodes()
+            .iter()
+            .map(|n| n.get_node_kind().get_binops().into_iter().cloned().collect())
+            .collect();
+
+        DataflowProblem {
+            direction: Direction::Backward,
+            meet: Meet::Intersection,
+            gen,
+            kill,
+            boundary: BTreeSet::new(),
+            universe,
+        }
+        .solve(self)
+    }
+
+    // Anticipated expressions are the very busy ones: computed on every path
+    // from the node before an operand changes
+    pub fn anticipated_expressions(&self) -> DataflowResult<Expr> {
+        self.very_busy_expressions()
+    }
+}
@@ -0,0 +1,54 @@
+use crate::ast::*;
+use crate::liveness::{ControlFlowGraph, NodeKind};
+use crate::rewrite::{node_kind_to_statement, ProgramEdits};
+
+// Removes assignments whose variable is dead right after them, repeating
+// until nothing changes since a removal can kill earlier assignments.
+// Assignments that may divide by zero are kept, as removing them would
+// change what the program does. Variables read before they are assigned are
+// taken to be inputs: a run missing one of them can fail before the pass
+// and succeed after it, if the read was removed. Returns the new program and
+// what was removed.
+pub fn eliminate_dead_assignments(p: &Program) -> (Program, Vec<Statement>) {
+    let mut program = p.clone();
+    let mut removed = Vec::new();
+
+    loop {
+        let mut cfg = ControlFlowGraph::from(&program);
+        cfg.fast_perform_liveness_analysis();
+
+        let mut edits = ProgramEdits::new();
+        for (idx, n) in cfg.get_nodes().iter().enumerate() {
+            if let NodeKind::Assignment(lvl, e) = n.get_node_kind() {
+                let Expr::Id(x) = lvl.as_ref() else {
+                    continue;
+                };
+                if !cfg.get_live_out(idx).contains(x) && !may_fail(e) {
+                    edits.remove(idx);
+                    removed.push(node_kind_to_statement(n.get_node_kind()));
+                }
+            }
+        }
+        if edits.is_empty() {
+            break;
+        }
+        program = edits.apply(&program);
+    }
+
+    (program, removed)
+}
+
+// Whether evaluating the expression can divide by zero
+pub fn may_fail(e: &Expr) -> bool {
+    match e {
+        Expr::BinOp(l, op, r) => {
+            let divisor_ok = match (op, r.as_ref()) {
+                (Operator::Div | Operator::Mod, Expr::Int(n)) => *n != 0,
+                (Operator::Div | Operator::Mod, _) => false,
+                _ => true,
+            };
+            !divisor_ok || may_fail(l) || may_fail(r)
+        }
+        _ => false,
+    }
+}
@@ -0,0 +1,351 @@
+use crate::ast::*;
+use crate::dataflow::{get_def_var, DataflowProblem, Direction, Meet};
+use crate::lexer::{LexError, Lexer, Span, Token};
+use crate::liveness::{ControlFlowGraph, NodeKind};
+use crate::parser::{ParseError, Parser};
+use std::collections::BTreeSet;
+use std::fmt::Display;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+// A message about a piece of the source, rendered like rustc does
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    severity: Severity,
+    message: String,
+    span: Span,
+    label: Option<String>,
+    notes: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn error(message: &str, span: Span) -> Self {
+        Self::new(Severity::Error, message, span)
+    }
+    pub fn warning(message: &str, span: Span) -> Self {
+        Self::new(Severity::Warning, message, span)
+    }
+    fn new(severity: Severity, message: &str, span: Span) -> Self {
+        Diagnostic {
+            severity,
+            message: message.to_string(),
+            span,
+            label: None,
+            notes: Vec::new(),
+        }
+    }
+    // Text next to the carets
+    pub fn with_label(mut self, label: &str) -> Self {
+        self.label = Some(label.to_string());
+        self
+    }
+    pub fn with_note(mut self, note: &str) -> Self {
+        self.notes.push(note.to_string());
+        self
+    }
+
+    pub fn get_severity(&self) -> Severity {
+        self.severity
+    }
+    pub fn get_message(&self) -> &String {
+        &self.message
+    }
+    pub fn get_span(&self) -> Span {
+        self.span
+    }
+    pub fn get_label(&self) -> Option<&String> {
+        self.label.as_ref()
+    }
+    pub fn get_notes(&self) -> &Vec<String> {
+        &self.notes
+    }
+
+    pub fn from_lex_error(e: &LexError) -> Self {
+        Diagnostic::error(e.get_msg(), e.get_span())
+    }
+    // Needs a parser given spans, otherwise points at the start
+    pub fn from_parse_error(e: &ParseError) -> Self {
+        Diagnostic::error(e.get_msg(), e.get_span().unwrap_or_default())
+    }
+}
+
+// The source diagnostics point into, with its name for the headers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceFile {
+    name: String,
+    src: String,
+    line_starts: Vec<usize>,
+}
+
+impl SourceFile {
+    pub fn new(name: &str, src: &str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(src.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        SourceFile {
+            name: name.to_string(),
+            src: src.to_string(),
+            line_starts,
+        }
+    }
+    pub fn get_name(&self) -> &String {
+        &self.name
+    }
+    pub fn get_src(&self) -> &String {
+        &self.src
+    }
+    // 1-based line and column (in chars) of a byte offset
+    pub fn line_col(&self, offset: usize) -> (usize, usize) {
+        let offset = offset.min(self.src.len());
+        let line = self.line_starts.partition_point(|s| *s <= offset) - 1;
+        let col = self.src[self.line_starts[line]..offset].chars().count();
+        (line + 1, col + 1)
+    }
+    // The text of a 1-based line, without its newline
+    pub fn get_line(&self, line: usize) -> &str {
+        let start = self.line_starts[line - 1];
+        let end = match self.line_starts.get(line) {
+            Some(next) => next - 1,
+            None => self.src.len(),
+        };
+        self.src[start..end].trim_end_matches('\r')
+    }
+}
+
+// ANSI escapes, or nothing without color
+struct Style {
+    color: bool,
+}
+
+impl Style {
+    fn paint(&self, code: &str, s: &str) -> String {
+        match self.color {
+            true => format!("\x1b[{code}m{s}\x1b[0m"),
+            false => s.to_string(),
+        }
+    }
+    fn severity(&self, sev: Severity, s: &str) -> String {
+        match sev {
+            Severity::Error => self.paint("1;31", s),
+            Severity::Warning => self.paint("1;33", s),
+        }
+    }
+    fn gutter(&self, s: &str) -> String {
+        self.paint("1;34", s)
+    }
+    fn bold(&self, s: &str) -> String {
+        self.paint("1", s)
+    }
+}
+
+// error: message
+//  --> file:line:col
+//   |
+// 3 | the line
+//   |     ^^^^ label
+//   |
+//   = note: ...
+//
+// A span over several lines is underlined to the end of its first line.
+pub fn render(d: &Diagnostic, file: &SourceFile, color: bool) -> String {
+    let style = Style { color };
+    let (line, col) = file.line_col(d.span.get_start());
+    let text = file.get_line(line);
+    let width = line.to_string().len();
+    let pad = " ".repeat(width);
+
+    let start = col - 1;
+    let len = match file.line_col(d.span.get_end()) {
+        (end_line, end_col) if end_line == line => end_col - col,
+        _ => text.chars().count() - start,
+    };
+    let carets = "^".repeat(len.max(1));
+    let under = match &d.label {
+        Some(l) => format!("{carets} {l}"),
+        None => carets,
+    };
+
+    let mut res = format!(
+        "{}{}\n",
+        style.severity(d.severity, &d.severity.to_string()),
+        style.bold(&format!(": {}", d.message))
+    );
+    res.push_str(&format!("{pad}{} {}:{line}:{col}\n", style.gutter("-->"), file.name));
+    res.push_str(&format!("{pad} {}\n", style.gutter("|")));
+    res.push_str(&format!("{} {text}\n", style.gutter(&format!("{line} |"))));
+    res.push_str(&format!(
+        "{pad} {} {}{}\n",
+        style.gutter("|"),
+        " ".repeat(start),
+        style.severity(d.severity, &under)
+    ));
+    if !d.notes.is_empty() {
+        res.push_str(&format!("{pad} {}\n", style.gutter("|")));
+        for note in d.notes.iter() {
+            res.push_str(&format!("{pad} {} {}: {note}\n", style.gutter("="), style.bold("note")));
+        }
+    }
+    res
+}
+
+// Every diagnostic with an empty line after each
+pub fn render_all(ds: &[Diagnostic], file: &SourceFile, color: bool) -> String {
+    ds.iter().map(|d| render(d, file, color) + "\n").collect()
+}
+
+// A program parsed with spans
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedSource {
+    program: Program,
+    tokens: Vec<(Token, Span)>,
+    node_spans: Vec<Span>,
+}
+
+impl ParsedSource {
+    pub fn get_program(&self) -> &Program {
+        &self.program
+    }
+    pub fn get_tokens(&self) -> &Vec<(Token, Span)> {
+        &self.tokens
+    }
+    // Indexed by CFG node
+    pub fn get_node_spans(&self) -> &Vec<Span> {
+        &self.node_spans
+    }
+}
+
+// Lexes and parses, or the error that stopped it
+pub fn parse_source(src: &str) -> Result<ParsedSource, Diagnostic> {
+    let tokens = Lexer::new(src)
+        .tokenize_with_spans()
+        .map_err(|e| Diagnostic::from_lex_error(&e))?;
+    let mut parser = Parser::with_spans(tokens.clone());
+    let program = parser.try_parse().map_err(|e| Diagnostic::from_parse_error(&e))?;
+    Ok(ParsedSource {
+        program,
+        tokens,
+        node_spans: parser.get_node_spans().clone(),
+    })
+}
+
+// Lex or parse errors, or else warnings about the program, in source order
+pub fn check_source(src: &str) -> Vec<Diagnostic> {
+    match parse_source(src) {
+        Ok(parsed) => check_program(&parsed),
+        Err(d) => vec![d],
+    }
+}
+
+// Reads of variables that may not be assigned yet, assignments nobody reads
+// and statements that are never reached
+pub fn check_program(parsed: &ParsedSource) -> Vec<Diagnostic> {
+    let cfg = ControlFlowGraph::from(&parsed.program);
+    let nodes = cfg.get_nodes();
+    let spans = &parsed.node_spans;
+    let dom = cfg.dominator_tree();
+    let mut res = Vec::new();
+
+    let vars: BTreeSet<String> = nodes
+        .iter()
+        .flat_map(|n| n.get_uses().iter().chain(n.get_defs().iter()).cloned())
+        .collect();
+    let unassigned = |meet: Meet| {
+        DataflowProblem {
+            direction: Direction::Forward,
+            meet,
+            gen: vec![BTreeSet::new(); nodes.len()],
+            kill: nodes.iter().map(|n| n.get_defs().iter().cloned().collect()).collect(),
+            boundary: vars.clone(),
+            universe: vars.clone(),
+        }
+        .solve(&cfg)
+    };
+    let maybe = unassigned(Meet::Union);
+    let surely = unassigned(Meet::Intersection);
+    let live = DataflowProblem {
+        direction: Direction::Backward,
+        meet: Meet::Union,
+        gen: nodes.iter().map(|n| n.get_uses().iter().cloned().collect()).collect(),
+        kill: nodes.iter().map(|n| n.get_defs().iter().cloned().collect()).collect(),
+        boundary: BTreeSet::new(),
+        universe: BTreeSet::new(),
+    }
+    .solve(&cfg);
+
+    for (idx, n) in nodes.iter().enumerate() {
+        if !dom.is_reachable(idx) {
+            continue;
+        }
+        let uses: BTreeSet<&String> = n.get_uses().iter().collect();
+        for v in uses.into_iter().filter(|v| maybe.get_in(idx).contains(*v)) {
+            let span = use_span(parsed, spans[idx], n.get_node_kind(), v);
+            let d = match surely.get_in(idx).contains(v) {
+                true => Diagnostic::warning(&format!("`{v}` is used before it is assigned"), span)
+                    .with_label("never assigned before this")
+                    .with_note("running the program needs it as an input"),
+                false => Diagnostic::warning(&format!("`{v}` may be used before it is assigned"), span)
+                    .with_label("not assigned on every path to here"),
+            };
+            res.push(d);
+        }
+        if let Some(x) = get_def_var(n.get_node_kind()) {
+            if !live.get_out(idx).contains(x) {
+                res.push(
+                    Diagnostic::warning(&format!("value assigned to `{x}` is never read"), spans[idx])
+                        .with_label("overwritten or unused after this"),
+                );
+            }
+        }
+    }
+
+    // One warning per run of unreachable nodes
+    let mut idx = 0;
+    while idx < nodes.len() {
+        if dom.is_reachable(idx) {
+            idx += 1;
+            continue;
+        }
+        let first = idx;
+        while idx < nodes.len() && !dom.is_reachable(idx) {
+            idx += 1;
+        }
+        let mut d = Diagnostic::warning("unreachable code", spans[first].to(spans[idx - 1]))
+            .with_label("no path from the start of the program gets here");
+        if first > 0 && matches!(nodes[first - 1].get_node_kind(), NodeKind::Return(_)) {
+            d = d.with_note("it follows a return");
+        }
+        res.push(d);
+    }
+
+    res.sort_by_key(|d| (d.span, d.severity));
+    res
+}
+
+// The first read of var in the node, or the whole node
+fn use_span(parsed: &ParsedSource, node: Span, kind: &NodeKind, var: &str) -> Span {
+    let mut tokens = parsed
+        .tokens
+        .iter()
+        .filter(|(_, s)| node.get_start() <= s.get_start() && s.get_end() <= node.get_end());
+    // skip the assigned variable
+    if let NodeKind::Assignment(_, _) = kind {
+        tokens.next();
+    }
+    tokens
+        .find(|(t, _)| *t == Token::Id(var.to_string()))
+        .map(|(_, s)| *s)
+        .unwrap_or(node)
+}
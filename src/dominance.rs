@@ -0,0 +1,287 @@
+use crate::liveness::ControlFlowGraph;
+use std::collections::BTreeSet;
+
+// A dominator (or post-dominator) tree over the nodes of a CFG.
+// Nodes that cannot be reached from the root (the entry, or the exit for
+// post-dominators) are not part of the tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DominatorTree {
+    roots: Vec<usize>,
+    idom: Vec<Option<usize>>,
+    children: Vec<Vec<usize>>,
+    frontier: Vec<BTreeSet<usize>>,
+    reachable: Vec<bool>,
+}
+
+impl DominatorTree {
+    // Builds the tree with Cooper, Harvey and Kennedy's "A Simple, Fast
+    // Dominance Algorithm". `root` may be a virtual node at index
+    // `succs.len() - 1`, which is then left out of the resulting tree.
+    fn build(root: usize, succs: &[Vec<usize>], virtual_root: bool) -> Self {
+        let n = succs.len();
+        let mut preds = vec![Vec::new(); n];
+        for (idx, ss) in succs.iter().enumerate() {
+            for s in ss {
+                preds[*s].push(idx);
+            }
+        }
+
+        let postorder = postorder(root, succs);
+        let mut po_num = vec![usize::MAX; n];
+        for (i, b) in postorder.iter().enumerate() {
+            po_num[*b] = i;
+        }
+
+        let mut idom: Vec<Option<usize>> = vec![None; n];
+        idom[root] = Some(root);
+        let mut changed = true;
+        while changed {
+            changed = false;
+            // reverse postorder
+            for b in postorder.iter().rev() {
+                if *b == root {
+                    continue;
+                }
+                let mut new_idom = None;
+                for p in preds[*b].iter() {
+                    if idom[*p].is_none() {
+                        continue;
+                    }
+                    new_idom = match new_idom {
+                        None => Some(*p),
+                        Some(cur) => Some(intersect(*p, cur, &idom, &po_num)),
+                    };
+                }
+                if new_idom != idom[*b] {
+                    idom[*b] = new_idom;
+                    changed = true;
+                }
+            }
+        }
+
+        let reachable: Vec<bool> = idom.iter().map(|d| d.is_some()).collect();
+        // The root has no immediate dominator
+        idom[root] = None;
+
+        // DF: walk up the tree from every pred of b until idom[b] is hit.
+        // The root counts as a join point through the implicit entry edge.
+        let mut frontier = vec![BTreeSet::new(); n];
+        for b in 0..n {
+            if !reachable[b] {
+                continue;
+            }
+            for p in preds[b].iter() {
+                if !reachable[*p] {
+                    continue;
+                }
+                let mut runner = Some(*p);
+                while runner.is_some() && runner != idom[b] {
+                    let r = runner.unwrap();
+                    frontier[r].insert(b);
+                    runner = idom[r];
+                }
+            }
+        }
+
+        let mut roots = vec![root];
+        if virtual_root {
+            // Drop the virtual node, nodes it immediately dominates become roots
+            roots = (0..root).filter(|b| idom[*b] == Some(root)).collect();
+            for d in idom.iter_mut() {
+                if *d == Some(root) {
+                    *d = None;
+                }
+            }
+            idom.truncate(root);
+            frontier.truncate(root);
+            for f in frontier.iter_mut() {
+                f.remove(&root);
+            }
+        }
+        let mut reachable = reachable;
+        reachable.truncate(idom.len());
+
+        let mut children = vec![Vec::new(); idom.len()];
+        for (b, d) in idom.iter().enumerate() {
+            if let Some(d) = d {
+                children[*d].push(b);
+            }
+        }
+
+        DominatorTree {
+            roots,
+            idom,
+            children,
+            frontier,
+            reachable,
+        }
+    }
+
+    pub fn get_roots(&self) -> &Vec<usize> {
+        &self.roots
+    }
+
+    pub fn get_idom(&self, n: usize) -> Option<usize> {
+        self.idom[n]
+    }
+
+    pub fn get_idoms(&self) -> &Vec<Option<usize>> {
+        &self.idom
+    }
+
+    pub fn get_children(&self, n: usize) -> &Vec<usize> {
+        &self.children[n]
+    }
+
+    pub fn get_frontier(&self, n: usize) -> &BTreeSet<usize> {
+        &self.frontier[n]
+    }
+
+    pub fn get_frontiers(&self) -> &Vec<BTreeSet<usize>> {
+        &self.frontier
+    }
+
+    pub fn is_reachable(&self, n: usize) -> bool {
+        self.reachable[n]
+    }
+
+    // a dom b, every node dominates itself
+    pub fn dominates(&self, a: usize, b: usize) -> bool {
+        if !self.reachable[a] || !self.reachable[b] {
+            return false;
+        }
+        let mut runner = Some(b);
+        while let Some(r) = runner {
+            if r == a {
+                return true;
+            }
+            runner = self.idom[r];
+        }
+        false
+    }
+
+    pub fn strictly_dominates(&self, a: usize, b: usize) -> bool {
+        a != b && self.dominates(a, b)
+    }
+
+    // Nodes in the order of a depth first walk of the tree
+    pub fn preorder(&self) -> Vec<usize> {
+        let mut res = Vec::new();
+        let mut stack: Vec<usize> = self.roots.iter().rev().cloned().collect();
+        while let Some(n) = stack.pop() {
+            res.push(n);
+            stack.extend(self.children[n].iter().rev());
+        }
+        res
+    }
+
+    pub fn generate_dot(&self, cfg: &ControlFlowGraph) -> String {
+        let mut sb = String::from("digraph DomTree {\n\tnode [shape=rectangle];\n\n");
+
+        let mut edges = String::new();
+        for n in self.preorder() {
+            let exp = format!("{}", cfg.get_node(n).get_node_kind());
+            sb.push_str(&format!("\tblock{n} [label=\"{n}: {exp}\"];\n"));
+            for c in self.children[n].iter() {
+                edges.push_str(&format!("\tblock{n} -> block{c};\n"));
+            }
+        }
+        sb.push('\n');
+        sb.push_str(&edges);
+        sb.push('}');
+
+        sb
+    }
+}
+
+fn intersect(mut a: usize, mut b: usize, idom: &[Option<usize>], po_num: &[usize]) -> usize {
+    while a != b {
+        while po_num[a] < po_num[b] {
+            a = idom[a].unwrap();
+        }
+        while po_num[b] < po_num[a] {
+            b = idom[b].unwrap();
+        }
+    }
+    a
+}
+
+fn postorder(root: usize, succs: &[Vec<usize>]) -> Vec<usize> {
+    let mut res = Vec::new();
+    let mut visited = vec![false; succs.len()];
+    // (node, index of the next succ to visit)
+    let mut stack = vec![(root, 0)];
+    visited[root] = true;
+    while let Some((n, i)) = stack.pop() {
+        if let Some(s) = succs[n].get(i) {
+            stack.push((n, i + 1));
+            if !visited[*s] {
+                visited[*s] = true;
+                stack.push((*s, 0));
+            }
+        } else {
+            res.push(n);
+        }
+    }
+    res
+}
+
+impl ControlFlowGraph {
+    fn sorted_succs(&self) -> Vec<Vec<usize>> {
+        self.get_nodes()
+            .iter()
+            .map(|n| n.get_succs().iter().cloned().collect())
+            .collect()
+    }
+
+    // The entry is always node 0
+    pub fn dominator_tree(&self) -> DominatorTree {
+        let succs = self.sorted_succs();
+        if succs.is_empty() {
+            return DominatorTree::build(0, &[vec![]], true);
+        }
+        DominatorTree::build(0, &succs, false)
+    }
+
+    // Post-dominators are dominators of the reversed CFG, rooted in a virtual
+    // exit node that every way out of the program leads to: returns, the
+    // last statement and conditions branching past the end.
+    pub fn post_dominator_tree(&self) -> DominatorTree {
+        let succs = self.sorted_succs();
+        let exit = succs.len();
+        let mut rev = vec![Vec::new(); exit + 1];
+        for (idx, ss) in succs.iter().enumerate() {
+            if self.get_node(idx).is_exit() {
+                rev[exit].push(idx);
+            }
+            for s in ss {
+                rev[*s].push(idx);
+            }
+        }
+        DominatorTree::build(exit, &rev, true)
+    }
+
+    pub fn immediate_dominators(&self) -> Vec<Option<usize>> {
+        self.dominator_tree().get_idoms().clone()
+    }
+
+    pub fn immediate_post_dominators(&self) -> Vec<Option<usize>> {
+        self.post_dominator_tree().get_idoms().clone()
+    }
+
+    pub fn dominance_frontiers(&self) -> Vec<BTreeSet<usize>> {
+        self.dominator_tree().get_frontiers().clone()
+    }
+
+    pub fn post_dominance_frontiers(&self) -> Vec<BTreeSet<usize>> {
+        self.post_dominator_tree().get_frontiers().clone()
+    }
+
+    pub fn generate_dominator_dot(&self) -> String {
+        self.dominator_tree().generate_dot(self)
+    }
+
+    pub fn generate_post_dominator_dot(&self) -> String {
+        self.post_dominator_tree().generate_dot(self)
+    }
+}
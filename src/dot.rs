@@ -0,0 +1,175 @@
+use crate::liveness::{ControlFlowGraph, Node};
+use std::collections::BTreeSet;
+
+// How generate_dot_with draws the CFG. The defaults are what generate_dot
+// gives: statements in rectangles, live variables on the edges and loops in
+// clusters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DotOptions {
+    html: bool,
+    highlight: Option<String>,
+    highlight_color: String,
+    clusters: bool,
+}
+
+impl DotOptions {
+    pub fn new() -> Self {
+        DotOptions {
+            html: false,
+            highlight: None,
+            highlight_color: String::from("lightblue"),
+            clusters: true,
+        }
+    }
+
+    // Nodes as tables of the statement, use/def and live in/out
+    pub fn with_html_labels(mut self, html: bool) -> Self {
+        self.html = html;
+        self
+    }
+    // Fill the nodes var is live in or out of, and draw the edges it is live
+    // on in the same color. Needs the liveness analysis.
+    pub fn with_highlight(mut self, var: &str) -> Self {
+        self.highlight = Some(var.to_string());
+        self
+    }
+    // Any Graphviz color, a name or "#rrggbb"
+    pub fn with_highlight_color(mut self, color: &str) -> Self {
+        self.highlight_color = color.to_string();
+        self
+    }
+    pub fn with_clusters(mut self, clusters: bool) -> Self {
+        self.clusters = clusters;
+        self
+    }
+
+    pub fn get_html_labels(&self) -> bool {
+        self.html
+    }
+    pub fn get_highlight(&self) -> Option<&String> {
+        self.highlight.as_ref()
+    }
+    pub fn get_highlight_color(&self) -> &String {
+        &self.highlight_color
+    }
+    pub fn get_clusters(&self) -> bool {
+        self.clusters
+    }
+}
+
+impl Default for DotOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// For HTML-like labels, where the text goes between tags
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// For quoted strings
+fn escape_quoted(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn join<'a>(vars: impl Iterator<Item = &'a String>) -> String {
+    vars.map(|v| v.as_str()).collect::<Vec<_>>().join(", ")
+}
+
+impl ControlFlowGraph {
+    // The attributes of a node
+    fn dot_node(&self, node: &Node, opts: &DotOptions) -> String {
+        let idx = node.get_node_idx();
+        let stmt = node.get_node_kind().to_string();
+        let highlighted = self.is_highlighted(idx, opts);
+        if !opts.html {
+            let mut attrs = format!("label=\"{}\"", escape_quoted(&stmt));
+            if highlighted {
+                let color = escape_quoted(&opts.highlight_color);
+                attrs.push_str(&format!(", style=filled, fillcolor=\"{color}\""));
+            }
+            return attrs;
+        }
+        let row = |name: &str, vars: &BTreeSet<String>| {
+            format!("<TR><TD>{name}</TD><TD>{}</TD></TR>", escape_html(&join(vars.iter())))
+        };
+        let mut rows = vec![
+            format!("<TR><TD COLSPAN=\"2\"><B>{idx}</B>: {}</TD></TR>", escape_html(&stmt)),
+            row("use", node.get_uses()),
+            row("def", node.get_defs()),
+        ];
+        if self.is_analyzed() {
+            rows.push(row("in", self.get_live_in(idx)));
+            rows.push(row("out", self.get_live_out(idx)));
+        }
+        let bg = match highlighted {
+            true => format!(" BGCOLOR=\"{}\"", escape_html(&opts.highlight_color)),
+            false => String::new(),
+        };
+        format!(
+            "shape=plaintext, label=<<TABLE BORDER=\"0\" CELLBORDER=\"1\" CELLSPACING=\"0\"{bg}>{}</TABLE>>",
+            rows.join("")
+        )
+    }
+
+    fn is_highlighted(&self, idx: usize, opts: &DotOptions) -> bool {
+        match &opts.highlight {
+            Some(v) if self.is_analyzed() => {
+                self.get_live_in(idx).contains(v) || self.get_live_out(idx).contains(v)
+            }
+            _ => false,
+        }
+    }
+
+    pub fn generate_dot_with(&self, opts: &DotOptions) -> String {
+        let mut sb = String::from("digraph CFG {\n\tnode [shape=rectangle];\n\n");
+
+        let mut blocks = String::new();
+        let mut edges = String::new();
+
+        let block_lines: Vec<String> = self
+            .get_nodes()
+            .iter()
+            .map(|node| format!("\tblock{} [{}];\n", node.get_node_idx(), self.dot_node(node, opts)))
+            .collect();
+
+        // Nodes inside loops are drawn in (nested) clusters, placed where
+        // the first node of the outermost loop would have been
+        let forest = self.loop_forest();
+        let clusters = match opts.clusters {
+            true => forest.dot_clusters(&block_lines),
+            false => Vec::new(),
+        };
+
+        for node in self.get_nodes() {
+            let idx = node.get_node_idx();
+            if !opts.clusters || forest.get_innermost_loop(idx).is_none() {
+                blocks.push_str(&block_lines[idx]);
+            } else if let Some((_, c)) = clusters.iter().find(|(first, _)| *first == idx) {
+                blocks.push_str(c);
+            }
+
+            for s in node.get_succs() {
+                let live: BTreeSet<&String> = match self.is_analyzed() {
+                    true => self.get_live_out(idx).intersection(self.get_live_in(*s)).collect(),
+                    false => BTreeSet::new(),
+                };
+                let mut attrs = format!("label=\"{}\"", escape_quoted(&join(live.iter().cloned())));
+                if opts.highlight.as_ref().is_some_and(|v| live.contains(v)) {
+                    let color = escape_quoted(&opts.highlight_color);
+                    attrs.push_str(&format!(", color=\"{color}\", penwidth=2"));
+                }
+                edges.push_str(&format!("\tblock{idx} -> block{s} [{attrs}];\n"));
+            }
+        }
+        sb.push_str(&blocks);
+        sb.push('\n');
+        sb.push_str(&edges);
+        sb.push('}');
+        sb
+    }
+}
@@ -0,0 +1,155 @@
+use crate::liveness::ControlFlowGraph;
+use std::collections::BTreeSet;
+
+// One node of the liveness table, sets sorted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableRow {
+    node: usize,
+    statement: String,
+    uses: Vec<String>,
+    defs: Vec<String>,
+    succs: Vec<usize>,
+    live_in: Vec<String>,
+    live_out: Vec<String>,
+}
+
+impl TableRow {
+    pub fn get_node(&self) -> usize {
+        self.node
+    }
+    pub fn get_statement(&self) -> &String {
+        &self.statement
+    }
+    pub fn get_uses(&self) -> &Vec<String> {
+        &self.uses
+    }
+    pub fn get_defs(&self) -> &Vec<String> {
+        &self.defs
+    }
+    pub fn get_succs(&self) -> &Vec<usize> {
+        &self.succs
+    }
+    pub fn get_live_in(&self) -> &Vec<String> {
+        &self.live_in
+    }
+    pub fn get_live_out(&self) -> &Vec<String> {
+        &self.live_out
+    }
+}
+
+// use, def, succ, in and out of every node, for handouts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LivenessTable {
+    rows: Vec<TableRow>,
+}
+
+const HEADER: [&str; 7] = ["node", "statement", "use", "def", "succ", "in", "out"];
+
+fn sorted(set: &BTreeSet<String>) -> Vec<String> {
+    set.iter().cloned().collect()
+}
+
+impl LivenessTable {
+    pub fn get_rows(&self) -> &Vec<TableRow> {
+        &self.rows
+    }
+
+    // The columns of each row, sets joined with sep
+    fn cells(&self, sep: &str) -> Vec<[String; 7]> {
+        self.rows
+            .iter()
+            .map(|r| {
+                let succs: Vec<String> = r.succs.iter().map(|s| s.to_string()).collect();
+                [
+                    r.node.to_string(),
+                    r.statement.clone(),
+                    r.uses.join(sep),
+                    r.defs.join(sep),
+                    succs.join(sep),
+                    r.live_in.join(sep),
+                    r.live_out.join(sep),
+                ]
+            })
+            .collect()
+    }
+
+    // | node | statement | use | ... with the statement as code
+    pub fn to_markdown(&self) -> String {
+        let mut res = format!("| {} |\n", HEADER.join(" | "));
+        res.push_str("|---:|---|---|---|---|---|---|\n");
+        for mut row in self.cells(", ") {
+            row[1] = format!("`{}`", row[1]);
+            res.push_str(&format!("| {} |\n", row.join(" | ")));
+        }
+        res
+    }
+
+    // Sets are separated by spaces, fields quoted only when they need it
+    pub fn to_csv(&self) -> String {
+        let field = |s: &str| match s.contains([',', '"', '\n']) {
+            true => format!("\"{}\"", s.replace('"', "\"\"")),
+            false => s.to_string(),
+        };
+        let mut res = format!("{}\n", HEADER.join(","));
+        for row in self.cells(" ") {
+            let fields: Vec<String> = row.iter().map(|f| field(f)).collect();
+            res.push_str(&format!("{}\n", fields.join(",")));
+        }
+        res
+    }
+
+    // A tabular environment, without the table around it
+    pub fn to_latex(&self) -> String {
+        let mut res = String::from("\\begin{tabular}{rllllll}\n\\hline\n");
+        res.push_str(&format!("{} \\\\\n\\hline\n", HEADER.join(" & ")));
+        for mut row in self.cells(", ") {
+            row[1] = format!("\\texttt{{{}}}", latex_escape(&row[1]));
+            for cell in row.iter_mut().skip(2) {
+                *cell = latex_escape(cell);
+            }
+            res.push_str(&format!("{} \\\\\n", row.join(" & ")));
+        }
+        res.push_str("\\hline\n\\end{tabular}\n");
+        res
+    }
+}
+
+fn latex_escape(s: &str) -> String {
+    let mut res = String::new();
+    for c in s.chars() {
+        match c {
+            '\\' => res.push_str("\\textbackslash{}"),
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                res.push('\\');
+                res.push(c);
+            }
+            '~' => res.push_str("\\textasciitilde{}"),
+            '^' => res.push_str("\\textasciicircum{}"),
+            '<' => res.push_str("\\textless{}"),
+            '>' => res.push_str("\\textgreater{}"),
+            c => res.push(c),
+        }
+    }
+    res
+}
+
+impl ControlFlowGraph {
+    // Expects the liveness analysis to have been performed
+    pub fn liveness_table(&self) -> LivenessTable {
+        let rows = self
+            .get_nodes()
+            .iter()
+            .enumerate()
+            .map(|(idx, n)| TableRow {
+                node: idx,
+                statement: n.get_node_kind().to_string(),
+                uses: sorted(n.get_uses()),
+                defs: sorted(n.get_defs()),
+                succs: n.get_succs().iter().cloned().collect(),
+                live_in: sorted(self.get_live_in(idx)),
+                live_out: sorted(self.get_live_out(idx)),
+            })
+            .collect();
+        LivenessTable { rows }
+    }
+}
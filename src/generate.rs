@@ -0,0 +1,171 @@
+use crate::ast::*;
+
+// Small deterministic PRNG (splitmix64), so a program only depends on its seed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng { state: seed }
+    }
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+    // Uniform in 0..n, n > 0
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+    // Uniform in lo..=hi, lo <= hi
+    pub fn range(&mut self, lo: i32, hi: i32) -> i32 {
+        // hi - lo can be too big for an i32
+        let span = (hi as i64 - lo as i64) as u64 + 1;
+        (lo as i64 + (self.next_u64() % span) as i64) as i32
+    }
+    // True with probability num/den
+    pub fn chance(&mut self, num: usize, den: usize) -> bool {
+        self.below(den) < num
+    }
+}
+
+const OPERATORS: [Operator; 6] = [
+    Operator::Plus,
+    Operator::Minus,
+    Operator::Mult,
+    Operator::Div,
+    Operator::Mod,
+    Operator::LessThan,
+];
+
+// Generates random well-formed programs. Expressions lean left, as the parser
+// has no parentheses, so every generated program survives being printed and
+// parsed again. Loops are not guaranteed to end, run them with a step limit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProgramGenerator {
+    rng: Rng,
+    vars: Vec<String>,
+    max_depth: usize,
+    max_stmts: usize,
+    max_ops: usize,
+    max_const: i32,
+}
+
+impl ProgramGenerator {
+    pub fn new(seed: u64) -> Self {
+        ProgramGenerator {
+            rng: Rng::new(seed),
+            vars: ["a", "b", "c", "d"].iter().map(|v| v.to_string()).collect(),
+            max_depth: 3,
+            max_stmts: 4,
+            max_ops: 2,
+            max_const: 5,
+        }
+    }
+
+    // Variables to draw from, must be valid identifiers and not keywords
+    pub fn with_vars(mut self, vars: &[&str]) -> Self {
+        assert!(!vars.is_empty(), "Need at least one variable");
+        self.vars = vars.iter().map(|v| v.to_string()).collect();
+        self
+    }
+    // How deep ifs and loops may nest, 0 gives straight-line code
+    pub fn with_max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+    // Statements per block, at least 1
+    pub fn with_max_stmts(mut self, n: usize) -> Self {
+        self.max_stmts = n.max(1);
+        self
+    }
+    // Operators per expression
+    pub fn with_max_ops(mut self, n: usize) -> Self {
+        self.max_ops = n;
+        self
+    }
+    // Literals are drawn from -n..=n. i32::MIN counts as i32::MAX, as
+    // -2147483648 has no literal.
+    pub fn with_max_const(mut self, n: i32) -> Self {
+        self.max_const = n.unsigned_abs().min(i32::MAX as u32) as i32;
+        self
+    }
+
+    pub fn get_vars(&self) -> &Vec<String> {
+        &self.vars
+    }
+    pub fn get_max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    // The next program, never empty
+    pub fn generate(&mut self) -> Program {
+        let n = 1 + self.rng.below(self.max_stmts);
+        Program::new(self.block(n, 0))
+    }
+
+    fn block(&mut self, n: usize, depth: usize) -> Vec<Statement> {
+        (0..n).map(|_| self.statement(depth)).collect()
+    }
+
+    // Bodies may be empty
+    fn body(&mut self, depth: usize) -> Vec<Statement> {
+        let n = self.rng.below(self.max_stmts + 1);
+        self.block(n, depth + 1)
+    }
+
+    fn statement(&mut self, depth: usize) -> Statement {
+        let choice = match depth < self.max_depth {
+            true => self.rng.below(10),
+            false => self.rng.below(6),
+        };
+        match choice {
+            0 if self.rng.chance(1, 2) => Statement::Return(Box::new(self.expr())),
+            0..=5 => Statement::Assignment(Box::new(self.var()), Box::new(self.expr())),
+            6 | 7 => Statement::If(Box::new(self.expr()), self.body(depth)),
+            8 => Statement::While(Box::new(self.expr()), self.body(depth)),
+            _ => {
+                let body = self.body(depth);
+                Statement::DoWhile(body, Box::new(self.expr()))
+            }
+        }
+    }
+
+    fn var(&mut self) -> Expr {
+        let idx = self.rng.below(self.vars.len());
+        Expr::Id(self.vars[idx].clone())
+    }
+
+    fn atom(&mut self) -> Expr {
+        match self.rng.chance(2, 3) {
+            true => self.var(),
+            false => Expr::Int(self.rng.range(-self.max_const, self.max_const)),
+        }
+    }
+
+    fn expr(&mut self) -> Expr {
+        let mut e = self.atom();
+        for _ in 0..self.rng.below(self.max_ops + 1) {
+            let op = OPERATORS[self.rng.below(OPERATORS.len())];
+            e = Expr::BinOp(Box::new(e), op, Box::new(self.atom()));
+        }
+        e
+    }
+}
+
+impl Iterator for ProgramGenerator {
+    type Item = Program;
+
+    fn next(&mut self) -> Option<Program> {
+        Some(self.generate())
+    }
+}
+
+// A program from the default generator
+pub fn random_program(seed: u64) -> Program {
+    ProgramGenerator::new(seed).generate()
+}
@@ -0,0 +1,213 @@
+use crate::ast::*;
+use crate::dominance::DominatorTree;
+use crate::liveness::{ControlFlowGraph, NodeKind};
+use crate::ssa::SsaForm;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+// What a value is known to be. Leaves are SSA names or constants, operands of
+// `+` and `*` are sorted.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+enum Key {
+    Leaf(Expr),
+    Op(Operator, Box<Key>, Box<Key>),
+    // Phis are only compared with the other phis of the same node
+    Phi(usize, Vec<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GvnResult {
+    ssa: SsaForm,
+    values: BTreeMap<String, Expr>,
+    replaced: Vec<usize>,
+}
+
+impl GvnResult {
+    // The SSA form with redundant computations replaced
+    pub fn get_ssa(&self) -> &SsaForm {
+        &self.ssa
+    }
+    // Every SSA name and its value number: the first name computing the same
+    // value, or a constant
+    pub fn get_values(&self) -> &BTreeMap<String, Expr> {
+        &self.values
+    }
+    pub fn get_value_number(&self, name: &str) -> Option<&Expr> {
+        self.values.get(name)
+    }
+    // Nodes whose computation was replaced by a name or constant
+    pub fn get_replaced(&self) -> &Vec<usize> {
+        &self.replaced
+    }
+    // Sets of two or more SSA names known to hold the same value, sorted
+    pub fn get_classes(&self) -> Vec<Vec<String>> {
+        let mut classes: BTreeMap<&Expr, BTreeSet<&String>> = BTreeMap::new();
+        for (name, vn) in self.values.iter() {
+            classes.entry(vn).or_default().insert(name);
+        }
+        let mut res: Vec<Vec<String>> = classes
+            .into_values()
+            .filter(|c| c.len() > 1)
+            .map(|c| c.into_iter().cloned().collect())
+            .collect();
+        res.sort();
+        res
+    }
+}
+
+// Dominator-based value numbering (Briggs, Cooper and Simpson).
+//
+// The dominator tree is walked in preorder with a scoped table of the
+// expressions computed in dominating nodes. An assignment whose expression is
+// already in the table gets the value number of the name computing it, and
+// its computation is replaced by that name. Constants are folded and the same
+// identities as local value numbering apply. A phi whose arguments all have
+// the same value number takes it, as does a phi with the same arguments as an
+// earlier one in its node. Arguments coming in over a back edge are not
+// numbered yet when the phi is visited, so loops are not looked through.
+pub fn global_value_numbering(p: &Program) -> GvnResult {
+    let cfg = ControlFlowGraph::from(p);
+    let ssa = cfg.ssa_form();
+    number_ssa(&ssa)
+}
+
+pub fn number_ssa(ssa: &SsaForm) -> GvnResult {
+    let dom = ssa.get_dominator_tree().clone();
+    let mut gvn = Gvn {
+        ssa: ssa.clone(),
+        values: ssa.get_entry_names().iter().map(|n| (n.clone(), Expr::Id(n.clone()))).collect(),
+        table: HashMap::new(),
+        replaced: Vec::new(),
+    };
+    for root in dom.get_roots().iter().filter(|r| dom.is_reachable(**r)) {
+        gvn.visit(*root, &dom);
+    }
+    gvn.replaced.sort();
+
+    GvnResult {
+        ssa: gvn.ssa,
+        values: gvn.values,
+        replaced: gvn.replaced,
+    }
+}
+
+struct Gvn {
+    ssa: SsaForm,
+    values: BTreeMap<String, Expr>,
+    table: HashMap<Key, Expr>,
+    replaced: Vec<usize>,
+}
+
+impl Gvn {
+    // Names not numbered yet stand for themselves
+    fn leaf(&self, name: &str) -> Expr {
+        self.values.get(name).cloned().unwrap_or(Expr::Id(name.to_string()))
+    }
+
+    fn key(&self, e: &Expr) -> Key {
+        match e {
+            Expr::Id(name) => Key::Leaf(self.leaf(name)),
+            Expr::Int(n) => Key::Leaf(Expr::Int(*n)),
+            Expr::BinOp(l, op, r) => {
+                let l = self.key(l);
+                let r = self.key(r);
+                let key = match (op, &l, &r) {
+                    (_, Key::Leaf(Expr::Int(a)), Key::Leaf(Expr::Int(b))) if op.apply(*a, *b).is_some() => {
+                        return Key::Leaf(Expr::Int(op.apply(*a, *b).unwrap()));
+                    }
+                    (Operator::Plus | Operator::Minus, _, Key::Leaf(Expr::Int(0)))
+                    | (Operator::Mult | Operator::Div, _, Key::Leaf(Expr::Int(1))) => return l,
+                    (Operator::Plus, Key::Leaf(Expr::Int(0)), _) | (Operator::Mult, Key::Leaf(Expr::Int(1)), _) => {
+                        return r
+                    }
+                    _ if op.is_commutative() && r < l => Key::Op(*op, Box::new(r), Box::new(l)),
+                    _ => Key::Op(*op, Box::new(l), Box::new(r)),
+                };
+                match self.table.get(&key) {
+                    Some(name) => Key::Leaf(name.clone()),
+                    None => key,
+                }
+            }
+        }
+    }
+
+    // Every part of the expression with a known value replaced by it
+    fn rewrite(&self, e: &Expr) -> Expr {
+        let key = self.key(e);
+        if let Key::Leaf(l) = key {
+            return l;
+        }
+        match e {
+            // an identity, only one side is needed
+            Expr::BinOp(l, _, _) if self.key(l) == key => self.rewrite(l),
+            Expr::BinOp(_, _, r) if self.key(r) == key => self.rewrite(r),
+            Expr::BinOp(l, op, r) => Expr::BinOp(Box::new(self.rewrite(l)), *op, Box::new(self.rewrite(r))),
+            _ => e.clone(),
+        }
+    }
+
+    fn visit(&mut self, n: usize, dom: &DominatorTree) {
+        let mut added: Vec<Key> = Vec::new();
+
+        for phi in self.ssa.get_node(n).get_phis().clone() {
+            let args: Vec<Expr> = phi.get_args().iter().map(|(_, a)| self.leaf(a)).collect();
+            let vn = if !args.is_empty() && args.iter().all(|a| *a == args[0]) {
+                args[0].clone()
+            } else {
+                let key = Key::Phi(n, args);
+                match self.table.get(&key) {
+                    Some(vn) => vn.clone(),
+                    None => {
+                        let vn = Expr::Id(phi.get_dest().clone());
+                        self.table.insert(key.clone(), vn.clone());
+                        added.push(key);
+                        vn
+                    }
+                }
+            };
+            self.values.insert(phi.get_dest().clone(), vn);
+        }
+
+        let kind = self.ssa.get_node(n).get_node_kind().clone();
+        if let Some(e) = kind.get_expr() {
+            let new = self.rewrite(e);
+            if matches!(e, Expr::BinOp(_, _, _)) && !matches!(new, Expr::BinOp(_, _, _)) {
+                self.replaced.push(n);
+            }
+            if let NodeKind::Assignment(lvl, _) = &kind {
+                if let Expr::Id(name) = lvl.as_ref() {
+                    let vn = match self.key(e) {
+                        Key::Leaf(l) => l,
+                        key => {
+                            let vn = Expr::Id(name.clone());
+                            self.table.insert(key.clone(), vn.clone());
+                            added.push(key);
+                            vn
+                        }
+                    };
+                    self.values.insert(name.clone(), vn);
+                }
+            }
+            self.ssa.get_node_mut(n).set_node_kind(kind.with_expr(new));
+        }
+
+        for s in self.ssa.get_node(n).get_succs().clone() {
+            let phis = self.ssa.get_node(s).get_phis().clone();
+            for (i, phi) in phis.iter().enumerate() {
+                if let Some(arg) = phi.get_arg(Some(n)) {
+                    let vn = self.leaf(arg);
+                    if let Expr::Id(name) = vn {
+                        self.ssa.get_node_mut(s).get_phis_mut()[i].set_arg(Some(n), name);
+                    }
+                }
+            }
+        }
+
+        for c in dom.get_children(n) {
+            self.visit(*c, dom);
+        }
+
+        for key in added {
+            self.table.remove(&key);
+        }
+    }
+}
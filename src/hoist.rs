@@ -0,0 +1,129 @@
+use crate::ast::*;
+use crate::dataflow::{get_expression_kills, get_expression_order, DataflowProblem, Direction, Meet};
+use crate::liveness::{ControlFlowGraph, NodeKind};
+use crate::rewrite::{NameGenerator, ProgramEdits};
+use std::collections::BTreeSet;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HoistResult {
+    program: Program,
+    hoisted: Vec<Statement>,
+}
+
+impl HoistResult {
+    pub fn get_program(&self) -> &Program {
+        &self.program
+    }
+    // Statements inserted above a branch, in the order they were hoisted
+    pub fn get_hoisted(&self) -> &Vec<Statement> {
+        &self.hoisted
+    }
+}
+
+// Code hoisting.
+//
+// When an expression `e` is very busy right after an `if` condition, every
+// path from the branch computes it anyway, so it can be computed once into a
+// fresh variable `h` before the `if`. Each computation of `e` that is only
+// reached while `h` still holds its value then uses `h` instead. A hoist is
+// only done when it replaces at least two computations, so the program gets
+// smaller. Loop conditions are left alone, as inserting before them puts the
+// statement both before the loop and in its body. So are conditions with a
+// branch to the end of the program, as that path computes nothing: hoisting
+// could add a division by zero the program never did.
+//
+// One expression is hoisted at a time, and the program is re-flattened after
+// each, until nothing more can be hoisted.
+pub fn hoist_very_busy_expressions(p: &Program) -> HoistResult {
+    let mut program = p.clone();
+    let mut names = NameGenerator::new("h", p);
+    let mut hoisted = Vec::new();
+
+    loop {
+        let cfg = ControlFlowGraph::from(&program);
+        match hoist_one(&cfg, &mut names) {
+            Some((edits, stmt)) => {
+                program = edits.apply(&program);
+                hoisted.push(stmt);
+            }
+            None => break,
+        }
+    }
+
+    HoistResult { program, hoisted }
+}
+
+fn hoist_one(cfg: &ControlFlowGraph, names: &mut NameGenerator) -> Option<(ProgramEdits, Statement)> {
+    let busy = cfg.very_busy_expressions();
+    let back_edges = cfg.back_edges();
+
+    let exprs = get_expression_order(cfg);
+
+    for (c, n) in cfg.get_nodes().iter().enumerate() {
+        if !matches!(n.get_node_kind(), NodeKind::Condition(_))
+            || n.is_exit()
+            || back_edges.iter().any(|(m, h)| *m == c || *h == c)
+        {
+            continue;
+        }
+        for e in exprs.iter().filter(|e| busy.get_out(c).contains(*e)) {
+            let sites = get_hoist_sites(cfg, c, e);
+            if sites.len() < 2 {
+                continue;
+            }
+            let h = names.fresh();
+            let stmt = Statement::Assignment(Box::new(Expr::Id(h.clone())), Box::new(e.clone()));
+            let mut edits = ProgramEdits::new();
+            edits.insert_before(c, stmt.clone());
+            for m in sites {
+                let kind = cfg.get_node(m).get_node_kind();
+                let old = kind.get_expr().expect("a site computes e");
+                edits.replace(m, kind.with_expr(replace_expr(old, e, &h)));
+            }
+            return Some((edits, stmt));
+        }
+    }
+    None
+}
+
+// Nodes computing `e` where a copy of it made just before node `c` is still
+// valid on every path
+fn get_hoist_sites(cfg: &ControlFlowGraph, c: usize, e: &Expr) -> Vec<usize> {
+    let universe = BTreeSet::from([e.clone()]);
+    let kill = get_expression_kills(cfg, &universe);
+    let gen = (0..cfg.get_nodes().len())
+        .map(|m| if m == c { universe.clone() } else { BTreeSet::new() })
+        .collect();
+    let holds = DataflowProblem {
+        direction: Direction::Forward,
+        meet: Meet::Intersection,
+        gen,
+        kill,
+        boundary: BTreeSet::new(),
+        universe,
+    }
+    .solve(cfg);
+
+    cfg.get_nodes()
+        .iter()
+        .enumerate()
+        .filter(|(m, n)| {
+            holds.get_in(*m).contains(e) && n.get_node_kind().get_binops().contains(&e)
+        })
+        .map(|(m, _)| m)
+        .collect()
+}
+
+fn replace_expr(e: &Expr, target: &Expr, h: &str) -> Expr {
+    if e == target {
+        return Expr::Id(h.to_string());
+    }
+    match e {
+        Expr::BinOp(l, op, r) => Expr::BinOp(
+            Box::new(replace_expr(l, target, h)),
+            *op,
+            Box::new(replace_expr(r, target, h)),
+        ),
+        _ => e.clone(),
+    }
+}
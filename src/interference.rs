@@ -0,0 +1,156 @@
+use crate::ast::*;
+use crate::liveness::{ControlFlowGraph, NodeKind};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+// Variables that are live at the same time, and so cannot share a register
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InterferenceGraph {
+    vars: BTreeSet<String>,
+    // (a, b) with a < b
+    edges: BTreeSet<(String, String)>,
+}
+
+impl InterferenceGraph {
+    pub fn get_vars(&self) -> Vec<&String> {
+        self.vars.iter().collect()
+    }
+    pub fn get_edges(&self) -> Vec<&(String, String)> {
+        self.edges.iter().collect()
+    }
+    pub fn interferes(&self, a: &str, b: &str) -> bool {
+        let (a, b) = if a < b { (a, b) } else { (b, a) };
+        self.edges.contains(&(a.to_string(), b.to_string()))
+    }
+    pub fn get_neighbours(&self, v: &str) -> Vec<&String> {
+        self.edges
+            .iter()
+            .filter_map(|(a, b)| {
+                if a == v {
+                    Some(b)
+                } else if b == v {
+                    Some(a)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    pub fn generate_dot(&self) -> String {
+        let mut sb = String::from("graph Interference {\n\tnode [shape=circle];\n\n");
+        for v in self.vars.iter() {
+            sb.push_str(&format!("\t\"{v}\";\n"));
+        }
+        sb.push('\n');
+        for (a, b) in self.edges.iter() {
+            sb.push_str(&format!("\t\"{a}\" -- \"{b}\";\n"));
+        }
+        sb.push('}');
+        sb
+    }
+}
+
+// Registers given to variables by InterferenceGraph::color
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Coloring {
+    k: usize,
+    colors: BTreeMap<String, usize>,
+    spilled: BTreeSet<String>,
+}
+
+impl Coloring {
+    // Number of registers
+    pub fn get_k(&self) -> usize {
+        self.k
+    }
+    // Register (0..k) of every variable that got one
+    pub fn get_colors(&self) -> &BTreeMap<String, usize> {
+        &self.colors
+    }
+    pub fn get_color(&self, v: &str) -> Option<usize> {
+        self.colors.get(v).cloned()
+    }
+    // Variables that have to live in memory
+    pub fn get_spilled(&self) -> &BTreeSet<String> {
+        &self.spilled
+    }
+}
+
+impl InterferenceGraph {
+    // Colors the graph with k registers (Chaitin's simplify/select, with
+    // Briggs' optimistic spilling). Variables with fewer than k neighbours
+    // are removed first, otherwise the one with the most neighbours becomes
+    // a spill candidate. Popping them back, each gets the lowest register its
+    // colored neighbours do not use, or is spilled if there is none. Ties go
+    // to the smallest name.
+    pub fn color(&self, k: usize) -> Coloring {
+        let mut left: BTreeSet<&String> = self.vars.iter().collect();
+        let mut stack = Vec::new();
+        while !left.is_empty() {
+            let degree = |v: &String| self.get_neighbours(v).iter().filter(|n| left.contains(*n)).count();
+            let next = match left.iter().find(|v| degree(v) < k) {
+                Some(v) => *v,
+                // max_by_key keeps the last maximum, so go from the back
+                None => *left.iter().rev().max_by_key(|v| degree(v)).unwrap(),
+            };
+            left.remove(next);
+            stack.push(next);
+        }
+
+        let mut colors: BTreeMap<String, usize> = BTreeMap::new();
+        let mut spilled = BTreeSet::new();
+        while let Some(v) = stack.pop() {
+            let taken: HashSet<usize> = self
+                .get_neighbours(v)
+                .iter()
+                .filter_map(|n| colors.get(*n).cloned())
+                .collect();
+            match (0..k).find(|c| !taken.contains(c)) {
+                Some(c) => {
+                    colors.insert(v.clone(), c);
+                }
+                None => {
+                    spilled.insert(v.clone());
+                }
+            }
+        }
+        Coloring { k, colors, spilled }
+    }
+}
+
+impl ControlFlowGraph {
+    // A variable defined at n interferes with everything live out of n. For a
+    // copy `a = b` the source b is left out, as a and b may share a register.
+    // Liveness is computed on a copy of the CFG if it has not been yet.
+    pub fn interference_graph(&self) -> InterferenceGraph {
+        if !self.is_analyzed() {
+            let mut cfg = self.clone();
+            cfg.fast_perform_liveness_analysis();
+            return cfg.interference_graph();
+        }
+        let mut vars = BTreeSet::new();
+        let mut edges = BTreeSet::new();
+        for (idx, n) in self.get_nodes().iter().enumerate() {
+            vars.extend(n.get_uses().iter().cloned());
+            vars.extend(n.get_defs().iter().cloned());
+
+            let copy_src: HashSet<&String> = match n.get_node_kind() {
+                NodeKind::Assignment(_, e) => match e.as_ref() {
+                    Expr::Id(src) => HashSet::from([src]),
+                    _ => HashSet::new(),
+                },
+                _ => HashSet::new(),
+            };
+            for d in n.get_defs() {
+                for l in self.get_live_out(idx) {
+                    if l == d || copy_src.contains(l) {
+                        continue;
+                    }
+                    let edge = if d < l { (d.clone(), l.clone()) } else { (l.clone(), d.clone()) };
+                    edges.insert(edge);
+                }
+            }
+        }
+        InterferenceGraph { vars, edges }
+    }
+}
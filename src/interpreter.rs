@@ -0,0 +1,345 @@
+use crate::ast::*;
+use crate::liveness::{ControlFlowGraph, NodeKind};
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+// Variable values, sorted by name
+pub type Env = BTreeMap<String, i32>;
+
+// Ways a run can go wrong. Arithmetic wraps around on overflow, so that is
+// not one of them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuntimeError {
+    DivisionByZero,
+    // Read of a variable that was neither an input nor assigned yet
+    Uninitialized(String),
+    // More statements and conditions were run than the limit allows
+    StepLimitExceeded(usize),
+}
+
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuntimeError::DivisionByZero => write!(f, "division by zero"),
+            RuntimeError::Uninitialized(var) => write!(f, "read of uninitialized variable `{var}`"),
+            RuntimeError::StepLimitExceeded(n) => write!(f, "step limit of {n} exceeded"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Execution {
+    returned: Option<i32>,
+    env: Env,
+    steps: usize,
+}
+
+impl Execution {
+    // The returned value, None if the program ran off its end
+    pub fn get_returned(&self) -> Option<i32> {
+        self.returned
+    }
+    // Variables when the program stopped
+    pub fn get_env(&self) -> &Env {
+        &self.env
+    }
+    // Statements run and conditions evaluated, one per CFG node visited
+    pub fn get_steps(&self) -> usize {
+        self.steps
+    }
+}
+
+pub const DEFAULT_STEP_LIMIT: usize = 10_000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Interpreter {
+    inputs: Env,
+    step_limit: usize,
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Interpreter {
+            inputs: Env::new(),
+            step_limit: DEFAULT_STEP_LIMIT,
+        }
+    }
+
+    // Start the program with `var` set to `value`
+    pub fn with_input(mut self, var: &str, value: i32) -> Self {
+        self.inputs.insert(var.to_string(), value);
+        self
+    }
+    pub fn with_inputs(mut self, inputs: &Env) -> Self {
+        self.inputs.extend(inputs.iter().map(|(k, v)| (k.clone(), *v)));
+        self
+    }
+    pub fn with_step_limit(mut self, limit: usize) -> Self {
+        self.step_limit = limit;
+        self
+    }
+
+    pub fn get_inputs(&self) -> &Env {
+        &self.inputs
+    }
+    pub fn get_step_limit(&self) -> usize {
+        self.step_limit
+    }
+
+    pub fn run(&self, p: &Program) -> Result<Execution, RuntimeError> {
+        let mut machine = self.machine(false);
+        let res = machine.exec_block(&p.stmts, 0);
+        machine.finish(res).outcome
+    }
+
+    // Runs the program like `run`, recording each statement and condition
+    // by the index of its CFG node
+    pub fn trace_program(&self, p: &Program) -> Trace {
+        let mut machine = self.machine(true);
+        let res = machine.exec_block(&p.stmts, 0);
+        machine.finish(res)
+    }
+
+    // Runs the flattened program node by node from node 0. For a CFG straight
+    // from a program the trace is the same as `trace_program`'s.
+    pub fn trace(&self, cfg: &ControlFlowGraph) -> Trace {
+        let mut machine = self.machine(true);
+        let res = machine.exec_cfg(cfg);
+        machine.finish(res)
+    }
+
+    fn machine(&self, tracing: bool) -> Machine {
+        Machine {
+            env: self.inputs.clone(),
+            steps: 0,
+            limit: self.step_limit,
+            trace: tracing.then(Vec::new),
+        }
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Operands are evaluated left to right, so the leftmost error is reported
+pub fn eval_expr(e: &Expr, env: &Env) -> Result<i32, RuntimeError> {
+    match e {
+        Expr::Int(n) => Ok(*n),
+        Expr::Id(var) => env.get(var).cloned().ok_or_else(|| RuntimeError::Uninitialized(var.clone())),
+        Expr::BinOp(l, op, r) => {
+            let l = eval_expr(l, env)?;
+            let r = eval_expr(r, env)?;
+            op.apply(l, r).ok_or(RuntimeError::DivisionByZero)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceStep {
+    node: usize,
+    env: Env,
+}
+
+impl TraceStep {
+    pub fn get_node(&self) -> usize {
+        self.node
+    }
+    // Variables right after the node ran
+    pub fn get_env(&self) -> &Env {
+        &self.env
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trace {
+    steps: Vec<TraceStep>,
+    outcome: Result<Execution, RuntimeError>,
+}
+
+impl Trace {
+    // One step per node run. A node that failed is not in here.
+    pub fn get_steps(&self) -> &Vec<TraceStep> {
+        &self.steps
+    }
+    // The visited nodes, in order
+    pub fn get_nodes(&self) -> Vec<usize> {
+        self.steps.iter().map(|s| s.node).collect()
+    }
+    pub fn get_outcome(&self) -> &Result<Execution, RuntimeError> {
+        &self.outcome
+    }
+}
+
+// One row per step: the step, the node and the variables after it
+impl Display for Trace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "step\tnode\tenv")?;
+        for (i, step) in self.steps.iter().enumerate() {
+            let env: Vec<String> = step.env.iter().map(|(k, v)| format!("{k} = {v}")).collect();
+            writeln!(f, "{i}\t{}\t{}", step.node, env.join(", "))?;
+        }
+        match &self.outcome {
+            Ok(e) => match e.returned {
+                Some(v) => writeln!(f, "returned {v}"),
+                None => writeln!(f, "finished"),
+            },
+            Err(err) => writeln!(f, "error: {err}"),
+        }
+    }
+}
+
+enum Flow {
+    Next,
+    Return(i32),
+}
+
+struct Machine {
+    env: Env,
+    steps: usize,
+    limit: usize,
+    trace: Option<Vec<TraceStep>>,
+}
+
+// Number of CFG nodes the statements flatten to
+fn node_count(stmts: &[Statement]) -> usize {
+    stmts
+        .iter()
+        .map(|s| match s {
+            Statement::If(_, body) | Statement::While(_, body) | Statement::DoWhile(body, _) => 1 + node_count(body),
+            _ => 1,
+        })
+        .sum()
+}
+
+impl Machine {
+    fn step(&mut self) -> Result<(), RuntimeError> {
+        self.steps += 1;
+        if self.steps > self.limit {
+            return Err(RuntimeError::StepLimitExceeded(self.limit));
+        }
+        Ok(())
+    }
+
+    fn record(&mut self, node: usize) {
+        if let Some(trace) = self.trace.as_mut() {
+            trace.push(TraceStep {
+                node,
+                env: self.env.clone(),
+            });
+        }
+    }
+
+    fn finish(self, res: Result<Flow, RuntimeError>) -> Trace {
+        let outcome = res.map(|flow| Execution {
+            returned: match flow {
+                Flow::Next => None,
+                Flow::Return(v) => Some(v),
+            },
+            env: self.env,
+            steps: self.steps,
+        });
+        Trace {
+            steps: self.trace.unwrap_or_default(),
+            outcome,
+        }
+    }
+
+    fn assign(&mut self, lvl: &Expr, e: &Expr) -> Result<(), RuntimeError> {
+        let v = eval_expr(e, &self.env)?;
+        match lvl {
+            Expr::Id(var) => self.env.insert(var.clone(), v),
+            _ => panic!("Can only assign to a variable"),
+        };
+        Ok(())
+    }
+
+    // Conditions hold when they are not 0
+    fn cond(&mut self, e: &Expr, node: usize) -> Result<bool, RuntimeError> {
+        self.step()?;
+        let holds = eval_expr(e, &self.env)? != 0;
+        self.record(node);
+        Ok(holds)
+    }
+
+    fn exec_cfg(&mut self, cfg: &ControlFlowGraph) -> Result<Flow, RuntimeError> {
+        let mut cur = if cfg.get_nodes().is_empty() { None } else { Some(0) };
+        while let Some(idx) = cur {
+            let node = cfg.get_node(idx);
+            cur = match node.get_node_kind() {
+                NodeKind::Condition(e) => match self.cond(e, idx)? {
+                    true => node.get_true_succ(),
+                    false => node.get_false_succ(),
+                },
+                NodeKind::Return(e) => {
+                    self.step()?;
+                    let v = eval_expr(e, &self.env)?;
+                    self.record(idx);
+                    return Ok(Flow::Return(v));
+                }
+                kind => {
+                    self.step()?;
+                    if let NodeKind::Assignment(lvl, e) = kind {
+                        self.assign(lvl, e)?;
+                    }
+                    self.record(idx);
+                    node.get_succs().iter().next().cloned()
+                }
+            };
+        }
+        Ok(Flow::Next)
+    }
+
+    // `first` is the CFG node of the first statement
+    fn exec_block(&mut self, stmts: &[Statement], first: usize) -> Result<Flow, RuntimeError> {
+        let mut node = first;
+        for stmt in stmts {
+            if let Flow::Return(v) = self.exec(stmt, node)? {
+                return Ok(Flow::Return(v));
+            }
+            node += node_count(std::slice::from_ref(stmt));
+        }
+        Ok(Flow::Next)
+    }
+
+    fn exec(&mut self, stmt: &Statement, node: usize) -> Result<Flow, RuntimeError> {
+        match stmt {
+            Statement::Assignment(lvl, e) => {
+                self.step()?;
+                self.assign(lvl, e)?;
+                self.record(node);
+                Ok(Flow::Next)
+            }
+            Statement::Return(e) => {
+                self.step()?;
+                let v = eval_expr(e, &self.env)?;
+                self.record(node);
+                Ok(Flow::Return(v))
+            }
+            Statement::If(cond, body) => {
+                if self.cond(cond, node)? {
+                    return self.exec_block(body, node + 1);
+                }
+                Ok(Flow::Next)
+            }
+            Statement::While(cond, body) => {
+                while self.cond(cond, node)? {
+                    if let Flow::Return(v) = self.exec_block(body, node + 1)? {
+                        return Ok(Flow::Return(v));
+                    }
+                }
+                Ok(Flow::Next)
+            }
+            Statement::DoWhile(body, cond) => loop {
+                if let Flow::Return(v) = self.exec_block(body, node)? {
+                    return Ok(Flow::Return(v));
+                }
+                if !self.cond(cond, node + node_count(body))? {
+                    return Ok(Flow::Next);
+                }
+            },
+        }
+    }
+}
@@ -0,0 +1,690 @@
+use crate::ast::*;
+use crate::interference::{Coloring, InterferenceGraph};
+use crate::lexer::{Span, Token};
+use crate::liveness::{ControlFlowGraph, LivenessTrace, NodeKind};
+use std::collections::BTreeSet;
+use std::fmt::Display;
+
+// Bumped whenever a representation below changes incompatibly
+pub const JSON_VERSION: i64 = 1;
+
+// A JSON value. Numbers are integers, as nothing here needs more; objects
+// keep their keys in the order they were written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Str(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+// Malformed JSON, at a byte offset, or JSON that does not describe what was
+// asked for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonError {
+    offset: Option<usize>,
+    msg: String,
+}
+
+impl JsonError {
+    fn new(msg: &str) -> Self {
+        JsonError {
+            offset: None,
+            msg: msg.to_string(),
+        }
+    }
+    pub fn get_offset(&self) -> Option<usize> {
+        self.offset
+    }
+    pub fn get_msg(&self) -> &String {
+        &self.msg
+    }
+}
+
+impl Display for JsonError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.offset {
+            Some(offset) => write!(f, "{} at offset {offset}", self.msg),
+            None => write!(f, "{}", self.msg),
+        }
+    }
+}
+
+impl Json {
+    pub fn str(s: &str) -> Json {
+        Json::Str(s.to_string())
+    }
+    pub fn object(fields: Vec<(&str, Json)>) -> Json {
+        Json::Object(fields.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+    }
+    fn strings<'a>(it: impl Iterator<Item = &'a String>) -> Json {
+        Json::Array(it.map(|s| Json::str(s)).collect())
+    }
+    fn ints(it: impl Iterator<Item = usize>) -> Json {
+        Json::Array(it.map(|i| Json::Int(i as i64)).collect())
+    }
+
+    // The value of a key of an object
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Json::Int(n) => Some(*n),
+            _ => None,
+        }
+    }
+    pub fn as_array(&self) -> Option<&Vec<Json>> {
+        match self {
+            Json::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    // Indented by two spaces, one field or item per line. Arrays of
+    // numbers, strings and such stay on one line.
+    pub fn pretty(&self) -> String {
+        let mut res = String::new();
+        self.write_pretty(&mut res, 0);
+        res
+    }
+
+    fn write_pretty(&self, res: &mut String, depth: usize) {
+        let pad = "  ".repeat(depth + 1);
+        match self {
+            Json::Array(items) if items.iter().all(|i| !matches!(i, Json::Array(_) | Json::Object(_))) => {
+                let items: Vec<String> = items.iter().map(|i| i.to_string()).collect();
+                res.push_str(&format!("[{}]", items.join(", ")));
+            }
+            Json::Array(items) => {
+                res.push_str("[\n");
+                for (i, item) in items.iter().enumerate() {
+                    res.push_str(&pad);
+                    item.write_pretty(res, depth + 1);
+                    res.push_str(if i + 1 < items.len() { ",\n" } else { "\n" });
+                }
+                res.push_str(&"  ".repeat(depth));
+                res.push(']');
+            }
+            Json::Object(fields) if !fields.is_empty() => {
+                res.push_str("{\n");
+                for (i, (k, v)) in fields.iter().enumerate() {
+                    res.push_str(&format!("{pad}{}: ", quote(k)));
+                    v.write_pretty(res, depth + 1);
+                    res.push_str(if i + 1 < fields.len() { ",\n" } else { "\n" });
+                }
+                res.push_str(&"  ".repeat(depth));
+                res.push('}');
+            }
+            other => res.push_str(&other.to_string()),
+        }
+    }
+
+    pub fn parse(src: &str) -> Result<Json, JsonError> {
+        let mut r = Reader { src, pos: 0 };
+        let value = r.value()?;
+        r.skip_ws();
+        if r.pos < src.len() {
+            return Err(r.error("trailing characters"));
+        }
+        Ok(value)
+    }
+}
+
+fn quote(s: &str) -> String {
+    let mut res = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            '\n' => res.push_str("\\n"),
+            '\r' => res.push_str("\\r"),
+            '\t' => res.push_str("\\t"),
+            c if (c as u32) < 0x20 => res.push_str(&format!("\\u{:04x}", c as u32)),
+            c => res.push(c),
+        }
+    }
+    res.push('"');
+    res
+}
+
+// Compact, without any whitespace
+impl Display for Json {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(b) => write!(f, "{b}"),
+            Json::Int(n) => write!(f, "{n}"),
+            Json::Str(s) => write!(f, "{}", quote(s)),
+            Json::Array(items) => {
+                let items: Vec<String> = items.iter().map(|i| i.to_string()).collect();
+                write!(f, "[{}]", items.join(","))
+            }
+            Json::Object(fields) => {
+                let fields: Vec<String> = fields.iter().map(|(k, v)| format!("{}:{v}", quote(k))).collect();
+                write!(f, "{{{}}}", fields.join(","))
+            }
+        }
+    }
+}
+
+struct Reader<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn error(&self, msg: &str) -> JsonError {
+        JsonError {
+            offset: Some(self.pos),
+            msg: msg.to_string(),
+        }
+    }
+    fn peek(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+    fn skip_ws(&mut self) {
+        while let Some(' ' | '\t' | '\n' | '\r') = self.peek() {
+            self.pos += 1;
+        }
+    }
+    fn expect(&mut self, c: char) -> Result<(), JsonError> {
+        self.skip_ws();
+        match self.peek() {
+            Some(found) if found == c => {
+                self.pos += 1;
+                Ok(())
+            }
+            Some(found) => Err(self.error(&format!("expected `{c}`, found `{found}`"))),
+            None => Err(self.error(&format!("expected `{c}`, found end of input"))),
+        }
+    }
+    fn keyword(&mut self, word: &str, value: Json) -> Result<Json, JsonError> {
+        match self.src[self.pos..].starts_with(word) {
+            true => {
+                self.pos += word.len();
+                Ok(value)
+            }
+            false => Err(self.error("expected a value")),
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, JsonError> {
+        self.skip_ws();
+        match self.peek() {
+            Some('n') => self.keyword("null", Json::Null),
+            Some('t') => self.keyword("true", Json::Bool(true)),
+            Some('f') => self.keyword("false", Json::Bool(false)),
+            Some('"') => self.string().map(Json::Str),
+            Some('-' | '0'..='9') => self.int(),
+            Some('[') => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                self.skip_ws();
+                if self.peek() == Some(']') {
+                    self.pos += 1;
+                    return Ok(Json::Array(items));
+                }
+                loop {
+                    items.push(self.value()?);
+                    self.skip_ws();
+                    match self.next() {
+                        Some(',') => continue,
+                        Some(']') => return Ok(Json::Array(items)),
+                        _ => return Err(self.error("expected `,` or `]`")),
+                    }
+                }
+            }
+            Some('{') => {
+                self.pos += 1;
+                let mut fields = Vec::new();
+                self.skip_ws();
+                if self.peek() == Some('}') {
+                    self.pos += 1;
+                    return Ok(Json::Object(fields));
+                }
+                loop {
+                    self.skip_ws();
+                    if self.peek() != Some('"') {
+                        return Err(self.error("expected a key"));
+                    }
+                    let key = self.string()?;
+                    self.expect(':')?;
+                    fields.push((key, self.value()?));
+                    self.skip_ws();
+                    match self.next() {
+                        Some(',') => continue,
+                        Some('}') => return Ok(Json::Object(fields)),
+                        _ => return Err(self.error("expected `,` or `}`")),
+                    }
+                }
+            }
+            Some(_) => Err(self.error("expected a value")),
+            None => Err(self.error("expected a value, found end of input")),
+        }
+    }
+
+    fn int(&mut self) -> Result<Json, JsonError> {
+        let start = self.pos;
+        if self.peek() == Some('-') {
+            self.pos += 1;
+        }
+        while let Some('0'..='9') = self.peek() {
+            self.pos += 1;
+        }
+        if let Some('.' | 'e' | 'E') = self.peek() {
+            return Err(self.error("only integers are supported"));
+        }
+        self.src[start..self.pos].parse().map(Json::Int).map_err(|_| JsonError {
+            offset: Some(start),
+            msg: String::from("invalid integer"),
+        })
+    }
+
+    // After the opening quote is peeked
+    fn string(&mut self) -> Result<String, JsonError> {
+        self.pos += 1;
+        let mut res = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(res),
+                Some('\\') => match self.next() {
+                    Some('"') => res.push('"'),
+                    Some('\\') => res.push('\\'),
+                    Some('/') => res.push('/'),
+                    Some('b') => res.push('\u{8}'),
+                    Some('f') => res.push('\u{c}'),
+                    Some('n') => res.push('\n'),
+                    Some('r') => res.push('\r'),
+                    Some('t') => res.push('\t'),
+                    Some('u') => res.push(self.unicode_escape()?),
+                    _ => return Err(self.error("invalid escape")),
+                },
+                Some(c) if (c as u32) < 0x20 => return Err(self.error("control character in string")),
+                Some(c) => res.push(c),
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+    }
+
+    // After `\u`, joining surrogate pairs
+    fn unicode_escape(&mut self) -> Result<char, JsonError> {
+        let hex = |r: &mut Self| -> Result<u32, JsonError> {
+            let digits = r.src.get(r.pos..r.pos + 4).ok_or_else(|| r.error("invalid \\u escape"))?;
+            let n = u32::from_str_radix(digits, 16).map_err(|_| r.error("invalid \\u escape"))?;
+            r.pos += 4;
+            Ok(n)
+        };
+        let first = hex(self)?;
+        let code = match first {
+            0xd800..=0xdbff => {
+                if !self.src[self.pos..].starts_with("\\u") {
+                    return Err(self.error("unpaired surrogate"));
+                }
+                self.pos += 2;
+                match hex(self)? {
+                    low @ 0xdc00..=0xdfff => 0x10000 + ((first - 0xd800) << 10) + (low - 0xdc00),
+                    _ => return Err(self.error("unpaired surrogate")),
+                }
+            }
+            n => n,
+        };
+        char::from_u32(code).ok_or_else(|| self.error("invalid \\u escape"))
+    }
+}
+
+// {"version": 1, "kind": ..., "data": ...}, what tools are given
+pub fn document(kind: &str, data: Json) -> Json {
+    Json::object(vec![
+        ("version", Json::Int(JSON_VERSION)),
+        ("kind", Json::str(kind)),
+        ("data", data),
+    ])
+}
+
+// The data of a document of the given kind, if its version is this one
+pub fn read_document(src: &str, kind: &str) -> Result<Json, JsonError> {
+    let doc = Json::parse(src)?;
+    match doc.get("version").and_then(|v| v.as_int()) {
+        Some(JSON_VERSION) => (),
+        Some(v) => return Err(JsonError::new(&format!("unsupported version {v}, expected {JSON_VERSION}"))),
+        None => return Err(JsonError::new("missing version")),
+    }
+    match doc.get("kind").and_then(|k| k.as_str()) {
+        Some(k) if k == kind => (),
+        Some(k) => return Err(JsonError::new(&format!("expected a {kind} document, found {k}"))),
+        None => return Err(JsonError::new("missing kind")),
+    }
+    doc.get("data").cloned().ok_or_else(|| JsonError::new("missing data"))
+}
+
+pub trait ToJson {
+    fn to_json(&self) -> Json;
+}
+
+pub trait FromJson: Sized {
+    fn from_json(j: &Json) -> Result<Self, JsonError>;
+}
+
+impl ToJson for Span {
+    fn to_json(&self) -> Json {
+        Json::object(vec![
+            ("start", Json::Int(self.get_start() as i64)),
+            ("end", Json::Int(self.get_end() as i64)),
+        ])
+    }
+}
+
+// {"type": "id", "value": "a"}, punctuation without a value
+impl ToJson for Token {
+    fn to_json(&self) -> Json {
+        let (ty, value) = match self {
+            Token::Id(id) => ("id", Some(Json::str(id))),
+            Token::Int(n) => ("int", Some(Json::Int(*n as i64))),
+            Token::Keyword(kw) => ("keyword", Some(Json::str(kw))),
+            Token::Equals => ("equals", None),
+            Token::LessThan => ("less_than", None),
+            Token::GreaterThan => ("greater_than", None),
+            Token::Plus => ("plus", None),
+            Token::Minus => ("minus", None),
+            Token::Mult => ("mult", None),
+            Token::Div => ("div", None),
+            Token::Mod => ("mod", None),
+            Token::Semicolon => ("semicolon", None),
+            Token::LParen => ("lparen", None),
+            Token::RParen => ("rparen", None),
+            Token::LBrace => ("lbrace", None),
+            Token::RBrace => ("rbrace", None),
+        };
+        let mut fields = vec![("type", Json::str(ty))];
+        fields.extend(value.map(|v| ("value", v)));
+        Json::object(fields)
+    }
+}
+
+// Tokens with their spans, as Lexer::tokenize_with_spans gives them
+pub fn tokens_to_json(tokens: &[(Token, Span)]) -> Json {
+    Json::Array(
+        tokens
+            .iter()
+            .map(|(t, s)| Json::object(vec![("token", t.to_json()), ("span", s.to_json())]))
+            .collect(),
+    )
+}
+
+impl ToJson for Operator {
+    fn to_json(&self) -> Json {
+        Json::str(&self.to_string())
+    }
+}
+
+impl FromJson for Operator {
+    fn from_json(j: &Json) -> Result<Self, JsonError> {
+        match j.as_str() {
+            Some("+") => Ok(Operator::Plus),
+            Some("-") => Ok(Operator::Minus),
+            Some("*") => Ok(Operator::Mult),
+            Some("/") => Ok(Operator::Div),
+            Some("%") => Ok(Operator::Mod),
+            Some("<") => Ok(Operator::LessThan),
+            _ => Err(JsonError::new(&format!("`{j}` is not an operator"))),
+        }
+    }
+}
+
+// {"type": "binop", "op": "+", "left": ..., "right": ...}
+impl ToJson for Expr {
+    fn to_json(&self) -> Json {
+        match self {
+            Expr::Id(id) => Json::object(vec![("type", Json::str("id")), ("name", Json::str(id))]),
+            Expr::Int(n) => Json::object(vec![("type", Json::str("int")), ("value", Json::Int(*n as i64))]),
+            Expr::BinOp(l, op, r) => Json::object(vec![
+                ("type", Json::str("binop")),
+                ("op", op.to_json()),
+                ("left", l.to_json()),
+                ("right", r.to_json()),
+            ]),
+        }
+    }
+}
+
+fn field<'a>(j: &'a Json, key: &str, what: &str) -> Result<&'a Json, JsonError> {
+    j.get(key).ok_or_else(|| JsonError::new(&format!("{what} is missing `{key}`")))
+}
+
+fn string_field(j: &Json, key: &str, what: &str) -> Result<String, JsonError> {
+    let v = field(j, key, what)?;
+    v.as_str()
+        .map(|s| s.to_string())
+        .ok_or_else(|| JsonError::new(&format!("`{key}` of {what} should be a string, found `{v}`")))
+}
+
+impl FromJson for Expr {
+    fn from_json(j: &Json) -> Result<Self, JsonError> {
+        match string_field(j, "type", "an expression")?.as_str() {
+            "id" => Ok(Expr::Id(string_field(j, "name", "an id")?)),
+            "int" => {
+                let v = field(j, "value", "an int")?;
+                match v.as_int().map(i32::try_from) {
+                    Some(Ok(n)) => Ok(Expr::Int(n)),
+                    _ => Err(JsonError::new(&format!("`{v}` is not a 32-bit integer"))),
+                }
+            }
+            "binop" => Ok(Expr::BinOp(
+                Box::new(Expr::from_json(field(j, "left", "a binop")?)?),
+                Operator::from_json(field(j, "op", "a binop")?)?,
+                Box::new(Expr::from_json(field(j, "right", "a binop")?)?),
+            )),
+            t => Err(JsonError::new(&format!("unknown expression type `{t}`"))),
+        }
+    }
+}
+
+fn stmts_to_json(stmts: &[Statement]) -> Json {
+    Json::Array(stmts.iter().map(|s| s.to_json()).collect())
+}
+
+fn stmts_from_json(j: &Json) -> Result<Vec<Statement>, JsonError> {
+    match j.as_array() {
+        Some(items) => items.iter().map(Statement::from_json).collect(),
+        None => Err(JsonError::new(&format!("expected a list of statements, found `{j}`"))),
+    }
+}
+
+// {"type": "while", "cond": ..., "body": [...]}
+impl ToJson for Statement {
+    fn to_json(&self) -> Json {
+        match self {
+            Statement::Assignment(lvl, e) => Json::object(vec![
+                ("type", Json::str("assign")),
+                ("target", lvl.to_json()),
+                ("value", e.to_json()),
+            ]),
+            Statement::Return(e) => Json::object(vec![("type", Json::str("return")), ("value", e.to_json())]),
+            Statement::If(c, body) => Json::object(vec![
+                ("type", Json::str("if")),
+                ("cond", c.to_json()),
+                ("body", stmts_to_json(body)),
+            ]),
+            Statement::While(c, body) => Json::object(vec![
+                ("type", Json::str("while")),
+                ("cond", c.to_json()),
+                ("body", stmts_to_json(body)),
+            ]),
+            Statement::DoWhile(body, c) => Json::object(vec![
+                ("type", Json::str("do_while")),
+                ("body", stmts_to_json(body)),
+                ("cond", c.to_json()),
+            ]),
+        }
+    }
+}
+
+impl FromJson for Statement {
+    fn from_json(j: &Json) -> Result<Self, JsonError> {
+        let expr = |key: &str, what: &str| -> Result<Box<Expr>, JsonError> {
+            Ok(Box::new(Expr::from_json(field(j, key, what)?)?))
+        };
+        match string_field(j, "type", "a statement")?.as_str() {
+            "assign" => {
+                let target = expr("target", "an assignment")?;
+                if !matches!(*target, Expr::Id(_)) {
+                    return Err(JsonError::new("the target of an assignment should be an id"));
+                }
+                Ok(Statement::Assignment(target, expr("value", "an assignment")?))
+            }
+            "return" => Ok(Statement::Return(expr("value", "a return")?)),
+            "if" => Ok(Statement::If(expr("cond", "an if")?, stmts_from_json(field(j, "body", "an if")?)?)),
+            "while" => Ok(Statement::While(
+                expr("cond", "a while")?,
+                stmts_from_json(field(j, "body", "a while")?)?,
+            )),
+            "do_while" => Ok(Statement::DoWhile(
+                stmts_from_json(field(j, "body", "a do-while")?)?,
+                expr("cond", "a do-while")?,
+            )),
+            t => Err(JsonError::new(&format!("unknown statement type `{t}`"))),
+        }
+    }
+}
+
+// {"stmts": [...]}
+impl ToJson for Program {
+    fn to_json(&self) -> Json {
+        Json::object(vec![("stmts", stmts_to_json(&self.stmts))])
+    }
+}
+
+impl FromJson for Program {
+    fn from_json(j: &Json) -> Result<Self, JsonError> {
+        Ok(Program::new(stmts_from_json(field(j, "stmts", "a program")?)?))
+    }
+}
+
+// A program from a document made by document("program", p.to_json())
+pub fn read_program(src: &str) -> Result<Program, JsonError> {
+    Program::from_json(&read_document(src, "program")?)
+}
+
+impl ToJson for NodeKind {
+    fn to_json(&self) -> Json {
+        match self {
+            NodeKind::Assignment(_, _) => Json::str("assignment"),
+            NodeKind::Return(_) => Json::str("return"),
+            NodeKind::Condition(_) => Json::str("condition"),
+            NodeKind::Empty => Json::str("empty"),
+        }
+    }
+}
+
+// Nodes with their use/def and edges, and the live sets if the analysis was
+// performed. Conditions say which successor is taken when true.
+impl ToJson for ControlFlowGraph {
+    fn to_json(&self) -> Json {
+        let analyzed = self.is_analyzed();
+        let mut nodes = Vec::new();
+        let mut edges = Vec::new();
+        for (idx, n) in self.get_nodes().iter().enumerate() {
+            let mut fields = vec![
+                ("id", Json::Int(idx as i64)),
+                ("kind", n.get_node_kind().to_json()),
+                ("statement", Json::str(&n.get_node_kind().to_string())),
+                ("uses", Json::strings(n.get_uses().iter())),
+                ("defs", Json::strings(n.get_defs().iter())),
+                ("preds", Json::ints(n.get_preds().iter().cloned())),
+                ("succs", Json::ints(n.get_succs().iter().cloned())),
+            ];
+            if analyzed {
+                fields.push(("live_in", Json::strings(self.get_live_in(idx).iter())));
+                fields.push(("live_out", Json::strings(self.get_live_out(idx).iter())));
+            }
+            nodes.push(Json::object(fields));
+
+            for s in n.get_succs().iter() {
+                let mut fields = vec![("from", Json::Int(idx as i64)), ("to", Json::Int(*s as i64))];
+                if let NodeKind::Condition(_) = n.get_node_kind() {
+                    fields.push(("branch", Json::Bool(n.get_true_succ() == Some(*s))));
+                }
+                if analyzed {
+                    let live: BTreeSet<&String> = self.get_live_out(idx).intersection(self.get_live_in(*s)).collect();
+                    fields.push(("live", Json::strings(live.into_iter())));
+                }
+                edges.push(Json::object(fields));
+            }
+        }
+        Json::object(vec![
+            ("analyzed", Json::Bool(analyzed)),
+            ("nodes", Json::Array(nodes)),
+            ("edges", Json::Array(edges)),
+        ])
+    }
+}
+
+// Every pass of the fixpoint, live sets indexed by node
+impl ToJson for LivenessTrace {
+    fn to_json(&self) -> Json {
+        let n = self.get_order().len();
+        let iterations = self
+            .get_iterations()
+            .iter()
+            .map(|it| {
+                Json::object(vec![
+                    ("live_in", Json::Array((0..n).map(|i| Json::strings(it.get_live_in(i).iter())).collect())),
+                    ("live_out", Json::Array((0..n).map(|i| Json::strings(it.get_live_out(i).iter())).collect())),
+                    ("changed", Json::ints(it.get_changed_nodes().into_iter())),
+                ])
+            })
+            .collect();
+        Json::object(vec![
+            ("order", Json::ints(self.get_order().iter().cloned())),
+            ("iterations", Json::Array(iterations)),
+        ])
+    }
+}
+
+// {"vars": [...], "edges": [["a", "b"], ...]}
+impl ToJson for InterferenceGraph {
+    fn to_json(&self) -> Json {
+        let edges = self
+            .get_edges()
+            .iter()
+            .map(|(a, b)| Json::Array(vec![Json::str(a), Json::str(b)]))
+            .collect();
+        Json::object(vec![
+            ("vars", Json::strings(self.get_vars().into_iter())),
+            ("edges", Json::Array(edges)),
+        ])
+    }
+}
+
+// {"k": 3, "colors": {"a": 0, ...}, "spilled": [...]}
+impl ToJson for Coloring {
+    fn to_json(&self) -> Json {
+        let colors = self
+            .get_colors()
+            .iter()
+            .map(|(v, c)| (v.clone(), Json::Int(*c as i64)))
+            .collect();
+        Json::object(vec![
+            ("k", Json::Int(self.get_k() as i64)),
+            ("colors", Json::Object(colors)),
+            ("spilled", Json::strings(self.get_spilled().iter())),
+        ])
+    }
+}
@@ -0,0 +1,351 @@
+use crate::ast::*;
+use crate::dataflow::{
+    get_expression_kills, get_expression_order, get_expression_universe, DataflowProblem, Direction, Meet,
+};
+use crate::liveness::{ControlFlowGraph, NodeKind};
+use crate::rewrite::{get_branch_kinds, BranchKind, NameGenerator, ProgramEdits};
+use std::collections::{BTreeSet, HashMap};
+
+// Where lazy code motion places a computation. Edges into nodes with several
+// predecessors get a placement of their own, as if split by an empty node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum LcmPoint {
+    // Right before the program starts
+    Entry,
+    // At the start of a CFG node, on every edge into it
+    Node(usize),
+    // On the edge (from, to) only
+    Edge(usize, usize),
+}
+
+// The lazy code motion analyses over the CFG with split edges. Sets are per
+// point, indexed like get_points.
+#[derive(Debug, Clone)]
+pub struct LazyCodeMotion {
+    points: Vec<LcmPoint>,
+    succs: Vec<Vec<usize>>,
+    anticipated: Vec<BTreeSet<Expr>>,
+    available: Vec<BTreeSet<Expr>>,
+    earliest: Vec<BTreeSet<Expr>>,
+    postponable: Vec<BTreeSet<Expr>>,
+    latest: Vec<BTreeSet<Expr>>,
+    used: Vec<BTreeSet<Expr>>,
+    uses: Vec<BTreeSet<Expr>>,
+}
+
+impl LazyCodeMotion {
+    pub fn get_points(&self) -> &Vec<LcmPoint> {
+        &self.points
+    }
+    // Successors of each point in the split graph
+    pub fn get_succs(&self) -> &Vec<Vec<usize>> {
+        &self.succs
+    }
+    // Anticipated at the start of each point
+    pub fn get_anticipated(&self) -> &Vec<BTreeSet<Expr>> {
+        &self.anticipated
+    }
+    // Available at the start of each point, counting the computations that
+    // could be placed as early as possible
+    pub fn get_available(&self) -> &Vec<BTreeSet<Expr>> {
+        &self.available
+    }
+    pub fn get_earliest(&self) -> &Vec<BTreeSet<Expr>> {
+        &self.earliest
+    }
+    // Postponable at the start of each point
+    pub fn get_postponable(&self) -> &Vec<BTreeSet<Expr>> {
+        &self.postponable
+    }
+    pub fn get_latest(&self) -> &Vec<BTreeSet<Expr>> {
+        &self.latest
+    }
+    // Used after each point
+    pub fn get_used(&self) -> &Vec<BTreeSet<Expr>> {
+        &self.used
+    }
+
+    // Points where `t = e` goes
+    pub fn get_insertions(&self, e: &Expr) -> Vec<LcmPoint> {
+        (0..self.points.len())
+            .filter(|b| self.latest[*b].contains(e) && self.used[*b].contains(e))
+            .map(|b| self.points[b])
+            .collect()
+    }
+
+    // CFG nodes whose computation of `e` reads `t` instead
+    pub fn get_replacements(&self, e: &Expr) -> Vec<usize> {
+        (0..self.points.len())
+            .filter(|b| {
+                self.uses[*b].contains(e) && (!self.latest[*b].contains(e) || self.used[*b].contains(e))
+            })
+            .filter_map(|b| match self.points[b] {
+                LcmPoint::Node(n) => Some(n),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl ControlFlowGraph {
+    pub fn lazy_code_motion(&self) -> LazyCodeMotion {
+        let nodes = self.get_nodes();
+        let n = nodes.len();
+
+        // Node i of the CFG is point i, the entry is point n and split edges
+        // come after
+        let mut preds: Vec<Vec<usize>> = vec![Vec::new(); n];
+        for (idx, node) in nodes.iter().enumerate() {
+            for s in node.get_succs() {
+                preds[*s].push(idx);
+            }
+        }
+        if n > 0 {
+            preds[0].push(n);
+        }
+        let mut points: Vec<LcmPoint> = (0..n).map(LcmPoint::Node).collect();
+        points.push(LcmPoint::Entry);
+        let mut succs: Vec<Vec<usize>> = vec![Vec::new(); n + 1];
+        if n > 0 {
+            succs[n].push(0);
+        }
+        for (idx, node) in nodes.iter().enumerate() {
+            for s in node.get_succs().iter().cloned() {
+                if preds[s].len() > 1 {
+                    points.push(LcmPoint::Edge(idx, s));
+                    succs.push(vec![s]);
+                    succs[idx].push(points.len() - 1);
+                } else {
+                    succs[idx].push(s);
+                }
+            }
+        }
+        let size = points.len();
+        // Split edges never end the program, so only CFG nodes can
+        let exits: Vec<usize> = (0..n).filter(|idx| nodes[*idx].is_exit()).collect();
+
+        let universe = get_expression_universe(self);
+        let mut uses: Vec<BTreeSet<Expr>> = nodes
+            .iter()
+            .map(|n| n.get_node_kind().get_binops().into_iter().cloned().collect())
+            .collect();
+        let mut kill = get_expression_kills(self, &universe);
+        uses.resize(size, BTreeSet::new());
+        kill.resize(size, BTreeSet::new());
+
+        let solve = |direction, meet, gen: Vec<BTreeSet<Expr>>, kill: Vec<BTreeSet<Expr>>| {
+            DataflowProblem {
+                direction,
+                meet,
+                gen,
+                kill,
+                boundary: BTreeSet::new(),
+                universe: universe.clone(),
+            }
+            .solve_graph(&succs, n, &exits)
+        };
+
+        // in = uses U (out - kill)
+        let anticipated = solve(Direction::Backward, Meet::Intersection, uses.clone(), kill.clone())
+            .get_ins()
+            .clone();
+
+        // out = (anticipated U in) - kill
+        let gen = (0..size).map(|b| anticipated[b].difference(&kill[b]).cloned().collect()).collect();
+        let available = solve(Direction::Forward, Meet::Intersection, gen, kill.clone())
+            .get_ins()
+            .clone();
+
+        let earliest: Vec<BTreeSet<Expr>> = (0..size)
+            .map(|b| anticipated[b].difference(&available[b]).cloned().collect())
+            .collect();
+
+        // out = (earliest U in) - uses
+        let gen = (0..size).map(|b| earliest[b].difference(&uses[b]).cloned().collect()).collect();
+        let postponable = solve(Direction::Forward, Meet::Intersection, gen, uses.clone())
+            .get_ins()
+            .clone();
+
+        // Placed at b if it can go no further: b uses it, or some successor
+        // cannot take it
+        let movable = |b: usize| -> BTreeSet<Expr> { earliest[b].union(&postponable[b]).cloned().collect() };
+        let latest: Vec<BTreeSet<Expr>> = (0..size)
+            .map(|b| {
+                movable(b)
+                    .into_iter()
+                    .filter(|e| uses[b].contains(e) || succs[b].iter().any(|s| !movable(*s).contains(e)))
+                    .collect()
+            })
+            .collect();
+
+        // in = (uses U out) - latest
+        let gen = (0..size).map(|b| uses[b].difference(&latest[b]).cloned().collect()).collect();
+        let used = solve(Direction::Backward, Meet::Union, gen, latest.clone())
+            .get_outs()
+            .clone();
+
+        LazyCodeMotion {
+            points,
+            succs,
+            anticipated,
+            available,
+            earliest,
+            postponable,
+            latest,
+            used,
+            uses,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LcmResult {
+    program: Program,
+    temps: Vec<(Expr, String)>,
+    inserted: Vec<(LcmPoint, Expr)>,
+    deleted: Vec<(usize, Expr)>,
+    skipped: Vec<Expr>,
+}
+
+impl LcmResult {
+    pub fn get_program(&self) -> &Program {
+        &self.program
+    }
+    // The temporary holding each moved expression
+    pub fn get_temps(&self) -> &Vec<(Expr, String)> {
+        &self.temps
+    }
+    // Computations added, by where they were placed
+    pub fn get_inserted(&self) -> &Vec<(LcmPoint, Expr)> {
+        &self.inserted
+    }
+    // Computations replaced by their temporary, by CFG node
+    pub fn get_deleted(&self) -> &Vec<(usize, Expr)> {
+        &self.deleted
+    }
+    pub fn get_inserted_at(&self, point: LcmPoint) -> Vec<&Expr> {
+        self.inserted.iter().filter(|(p, _)| *p == point).map(|(_, e)| e).collect()
+    }
+    pub fn get_deleted_at(&self, n: usize) -> Vec<&Expr> {
+        self.deleted.iter().filter(|(m, _)| *m == n).map(|(_, e)| e).collect()
+    }
+    // Expressions that would need a computation on an edge the program has
+    // no place for, like the false branch of an `if`, and were left alone
+    pub fn get_skipped(&self) -> &Vec<Expr> {
+        &self.skipped
+    }
+}
+
+// Partial redundancy elimination by lazy code motion.
+//
+// Each expression is moved on its own: it is computed into a fresh temporary
+// at its latest placements, and the computations made redundant by them read
+// the temporary instead. Expressions whose placement cannot be written back
+// into the program are skipped.
+pub fn lazy_code_motion(p: &Program) -> LcmResult {
+    let cfg = ControlFlowGraph::from(p);
+    let lcm = cfg.lazy_code_motion();
+    let kinds = get_branch_kinds(p);
+    let mut names = NameGenerator::new("t", p);
+
+    let mut temps: Vec<(Expr, String)> = Vec::new();
+    let mut inserted = Vec::new();
+    let mut deleted = Vec::new();
+    let mut skipped = Vec::new();
+    let mut edits = ProgramEdits::new();
+
+    for e in get_expression_order(&cfg) {
+        let replacements = lcm.get_replacements(&e);
+        if replacements.is_empty() {
+            continue;
+        }
+        let insertions = lcm.get_insertions(&e);
+        let spots: Option<Vec<Spot>> = insertions.iter().map(|p| get_spot(&cfg, &kinds, *p)).collect();
+        let Some(spots) = spots else {
+            skipped.push(e);
+            continue;
+        };
+
+        let t = names.fresh();
+        let stmt = Statement::Assignment(Box::new(Expr::Id(t.clone())), Box::new(e.clone()));
+        for spot in spots {
+            match spot {
+                Spot::Before(n) => edits.insert_before(n, stmt.clone()),
+                Spot::After(n) => edits.insert_after(n, stmt.clone()),
+                Spot::BodyStart(n) => edits.insert_body_start(n, stmt.clone()),
+                Spot::Preheader(n) => edits.insert_preheader(n, stmt.clone()),
+            }
+        }
+        inserted.extend(insertions.into_iter().map(|p| (p, e.clone())));
+        deleted.extend(replacements.into_iter().map(|n| (n, e.clone())));
+        temps.push((e, t));
+    }
+
+    for (idx, node) in cfg.get_nodes().iter().enumerate() {
+        let here: Vec<&(Expr, String)> = temps
+            .iter()
+            .filter(|(e, _)| deleted.contains(&(idx, e.clone())))
+            .collect();
+        if here.is_empty() {
+            continue;
+        }
+        let kind = node.get_node_kind();
+        let e = kind.get_expr().expect("a node computing an expression");
+        edits.replace(idx, kind.with_expr(replace_with_temps(e, &here)));
+    }
+
+    inserted.sort_by_key(|(p, _)| *p);
+    deleted.sort_by_key(|(n, _)| *n);
+    LcmResult {
+        program: edits.apply(p),
+        temps,
+        inserted,
+        deleted,
+        skipped,
+    }
+}
+
+enum Spot {
+    Before(usize),
+    After(usize),
+    BodyStart(usize),
+    Preheader(usize),
+}
+
+// How a placement is written into the program, if it can be
+fn get_spot(cfg: &ControlFlowGraph, kinds: &HashMap<usize, BranchKind>, p: LcmPoint) -> Option<Spot> {
+    match p {
+        LcmPoint::Entry if cfg.get_node(0).get_preds().is_empty() => Some(Spot::Before(0)),
+        // the first node is a loop header
+        LcmPoint::Entry => Some(Spot::Preheader(0)),
+        LcmPoint::Node(n) => Some(Spot::Before(n)),
+        LcmPoint::Edge(u, v) => {
+            let node = cfg.get_node(u);
+            if !matches!(node.get_node_kind(), NodeKind::Condition(_)) {
+                return Some(Spot::After(u));
+            }
+            let on_true = node.get_true_succ() == Some(v);
+            let on_false = node.get_false_succ() == Some(v);
+            match (kinds.get(&u)?, on_true, on_false) {
+                (_, true, true) => None,
+                (BranchKind::If | BranchKind::While, true, _) => Some(Spot::BodyStart(u)),
+                (BranchKind::While | BranchKind::DoWhile, _, true) => Some(Spot::After(u)),
+                _ => None,
+            }
+        }
+    }
+}
+
+fn replace_with_temps(e: &Expr, temps: &[&(Expr, String)]) -> Expr {
+    if let Some((_, t)) = temps.iter().find(|(te, _)| te == e) {
+        return Expr::Id(t.clone());
+    }
+    match e {
+        Expr::BinOp(l, op, r) => Expr::BinOp(
+            Box::new(replace_with_temps(l, temps)),
+            *op,
+            Box::new(replace_with_temps(r, temps)),
+        ),
+        _ => e.clone(),
+    }
+}
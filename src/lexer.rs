@@ -1,3 +1,4 @@
+use std::fmt::Display;
 use std::str::Chars;
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -21,6 +22,78 @@ pub enum Token {
     // Colon,
 }
 
+impl Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Id(id) => write!(f, "`{id}`"),
+            Token::Int(n) => write!(f, "`{n}`"),
+            Token::Keyword(kw) => write!(f, "`{kw}`"),
+            Token::Equals => write!(f, "`=`"),
+            Token::LessThan => write!(f, "`<`"),
+            Token::GreaterThan => write!(f, "`>`"),
+            Token::Plus => write!(f, "`+`"),
+            Token::Minus => write!(f, "`-`"),
+            Token::Mult => write!(f, "`*`"),
+            Token::Div => write!(f, "`/`"),
+            Token::Mod => write!(f, "`%`"),
+            Token::Semicolon => write!(f, "`;`"),
+            Token::LParen => write!(f, "`(`"),
+            Token::RParen => write!(f, "`)`"),
+            Token::LBrace => write!(f, "`{{`"),
+            Token::RBrace => write!(f, "`}}`"),
+        }
+    }
+}
+
+// A range of the source, in bytes, end exclusive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct Span {
+    start: usize,
+    end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Span { start, end }
+    }
+    pub fn get_start(&self) -> usize {
+        self.start
+    }
+    pub fn get_end(&self) -> usize {
+        self.end
+    }
+    // From the start of self to the end of other
+    pub fn to(&self, other: Span) -> Span {
+        Span::new(self.start, other.end.max(self.end))
+    }
+}
+
+// Where tokenizing stopped, the text that is not a token
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LexError {
+    span: Span,
+    msg: String,
+}
+
+impl LexError {
+    // Byte offset into the input
+    pub fn get_offset(&self) -> usize {
+        self.span.start
+    }
+    pub fn get_span(&self) -> Span {
+        self.span
+    }
+    pub fn get_msg(&self) -> &String {
+        &self.msg
+    }
+}
+
+impl Display for LexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at offset {}", self.msg, self.span.start)
+    }
+}
+
 fn char_to_token(c: char) -> Option<Token> {
     match c {
         ';' => Some(Token::Semicolon),
@@ -53,6 +126,8 @@ fn string_to_token(s: &str) -> Option<Token> {
 pub struct Lexer<'a> {
     input: Chars<'a>,
     current: Option<char>,
+    // byte offset of current
+    offset: usize,
 }
 
 impl<'a> Lexer<'a> {
@@ -60,11 +135,15 @@ impl<'a> Lexer<'a> {
         let mut lexer = Lexer {
             input: input.chars(),
             current: None,
+            offset: 0,
         };
-        lexer.advance();
+        lexer.current = lexer.input.next();
         lexer
     }
     pub fn advance(&mut self) {
+        if let Some(c) = self.current {
+            self.offset += c.len_utf8();
+        }
         self.current = self.input.next();
     }
 
@@ -75,34 +154,52 @@ impl<'a> Lexer<'a> {
     // Returns the next token if possible
     // Whitespace are non-tokens
     fn next_token(&mut self) -> Option<Token> {
+        self.lex_token().ok().flatten().map(|(t, _)| t)
+    }
+
+    // Ok(None) at the end of the input
+    fn lex_token(&mut self) -> Result<Option<(Token, Span)>, LexError> {
         while let Some(c) = self.current {
-            match c {
+            let start = self.offset;
+            let tok = match c {
                 c if c.is_whitespace() => {
                     self.advance();
-                    // we only return None for errors
                     continue;
                 }
                 // Handle identifiers and keywords
                 // - Should ensure that keyword
                 //   starts with a letter
-                c if c.is_alphabetic() => return self.consume_keyword_or_id(),
-                c if c.is_numeric() => return self.consume_numeric(),
+                c if c.is_alphabetic() => self.consume_keyword_or_id(),
+                // only ASCII digits, `²` and the like are numeric but no i32
+                c if c.is_ascii_digit() => self.consume_numeric(),
                 // del @ (';' | '=' | '+' | '(' | ')' | '{' | '}') => {
                 del if !c.is_alphanumeric() => {
                     self.advance();
-                    return char_to_token(del);
+                    char_to_token(del)
                 }
-                _ => return None,
-            }
+                _ => None,
+            };
+            let span = Span::new(start, self.offset);
+            return match tok {
+                Some(t) => Ok(Some((t, span))),
+                None if c.is_ascii_digit() => Err(LexError {
+                    span,
+                    msg: String::from("integer literal too large"),
+                }),
+                None => Err(LexError {
+                    span,
+                    msg: format!("unexpected character `{c}`"),
+                }),
+            };
         }
-        None
+        Ok(None)
     }
 
     fn consume_numeric(&mut self) -> Option<Token> {
         let mut n = String::new();
         // collect chars until non alphanumeric
         while let Some(c) = self.current {
-            if c.is_numeric() {
+            if c.is_ascii_digit() {
                 // identifier.push(c);
                 n.push(c);
                 self.advance();
@@ -111,11 +208,7 @@ impl<'a> Lexer<'a> {
                 break;
             }
         }
-        if let Some(i) = n.parse().ok() {
-            Some(Token::Int(i))
-        } else {
-            None
-        }
+        n.parse().ok().map(Token::Int)
     }
 
     fn consume_keyword_or_id(&mut self) -> Option<Token> {
@@ -132,11 +225,23 @@ impl<'a> Lexer<'a> {
             }
         }
 
-        match identifier.as_str() {
-            s => return string_to_token(s),
-            // keyword @ ("return" | "if") => return string_to_token(keyword),
-            // id => return string_to_token(id),
-        };
+        string_to_token(identifier.as_str())
+    }
+
+    // Like tokenize, but input that is not a token is an error instead of
+    // where the tokens end
+    pub fn try_tokenize(self) -> Result<Vec<Token>, LexError> {
+        let tokens = self.tokenize_with_spans()?;
+        Ok(tokens.into_iter().map(|(t, _)| t).collect())
+    }
+
+    // Every token with where it is in the input
+    pub fn tokenize_with_spans(mut self) -> Result<Vec<(Token, Span)>, LexError> {
+        let mut res = Vec::new();
+        while let Some(t) = self.lex_token()? {
+            res.push(t);
+        }
+        Ok(res)
     }
 
     pub fn tokenize(mut self) -> Vec<Token> {
@@ -1,5 +1,31 @@
 pub mod ast;
+pub mod cli;
+pub mod constprop;
+pub mod copyprop;
+pub mod cse;
+pub mod dataflow;
+pub mod deadcode;
+pub mod diagnostics;
+pub mod dominance;
+pub mod dot;
+pub mod export;
+pub mod generate;
+pub mod gvn;
+pub mod hoist;
+pub mod interference;
+pub mod interpreter;
+pub mod json;
+pub mod lcm;
 pub mod lexer;
+pub mod licm;
 pub mod liveness;
+pub mod loops;
+pub mod normalize;
+pub mod oracle;
 pub mod parser;
 pub mod pretty_print;
+pub mod repl;
+pub mod rewrite;
+pub mod ssa;
+pub mod valuenum;
+pub mod watch;
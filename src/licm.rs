@@ -0,0 +1,154 @@
+use crate::ast::*;
+use crate::dataflow::{get_def_var, DataflowResult};
+use crate::deadcode::may_fail;
+use crate::dominance::DominatorTree;
+use crate::liveness::{ControlFlowGraph, NodeKind};
+use crate::loops::LoopForest;
+use crate::rewrite::{node_kind_to_statement, ProgramEdits};
+use std::cmp::Reverse;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LicmResult {
+    program: Program,
+    hoisted: Vec<Statement>,
+}
+
+impl LicmResult {
+    pub fn get_program(&self) -> &Program {
+        &self.program
+    }
+    // Hoisted statements, in the order they were moved
+    pub fn get_hoisted(&self) -> &Vec<Statement> {
+        &self.hoisted
+    }
+}
+
+// Loop-invariant code motion.
+//
+// An assignment `x = e` in a loop is moved to the loop's preheader when:
+// - every operand of `e` is only defined outside the loop, or has a single
+//   reaching definition that is itself hoisted and dominates the assignment
+// - it is the only definition of `x` in the loop
+// - `x` is not live into the loop header, so no use sees an older value
+// - it dominates every loop exit where `x` is live afterwards
+// - it dominates every way out of the loop if `e` may divide by zero, so
+//   hoisting cannot add a failing division on a path that never ran the
+//   loop body. Returns and branches to the end of the program count too.
+//
+// Loops are processed innermost first, and the program is re-flattened after
+// each hoist so statements can travel through several loop levels.
+pub fn hoist_loop_invariants(p: &Program) -> LicmResult {
+    let mut program = p.clone();
+    let mut hoisted = Vec::new();
+
+    loop {
+        let mut cfg = ControlFlowGraph::from(&program);
+        match hoist_from_one_loop(&mut cfg) {
+            Some((edits, stmts)) => {
+                program = edits.apply(&program);
+                hoisted.extend(stmts);
+            }
+            None => break,
+        }
+    }
+
+    LicmResult { program, hoisted }
+}
+
+fn hoist_from_one_loop(cfg: &mut ControlFlowGraph) -> Option<(ProgramEdits, Vec<Statement>)> {
+    cfg.fast_perform_liveness_analysis();
+    let dom = cfg.dominator_tree();
+    let forest = cfg.loop_forest();
+    let reaching = cfg.reaching_definitions();
+
+    let mut order: Vec<usize> = (0..forest.get_loops().len()).collect();
+    order.sort_by_key(|l| Reverse(forest.get_loop(*l).get_depth()));
+
+    for l in order {
+        let hoistable = find_hoistable(cfg, &dom, &forest, &reaching, l);
+        if hoistable.is_empty() {
+            continue;
+        }
+        let header = forest.get_loop(l).get_header();
+        let mut edits = ProgramEdits::new();
+        let mut stmts = Vec::new();
+        for n in hoistable {
+            let stmt = node_kind_to_statement(cfg.get_node(n).get_node_kind());
+            edits.remove(n);
+            edits.insert_preheader(header, stmt.clone());
+            stmts.push(stmt);
+        }
+        return Some((edits, stmts));
+    }
+    None
+}
+
+// Hoistable assignments of loop `l`, in program order
+fn find_hoistable(
+    cfg: &ControlFlowGraph,
+    dom: &DominatorTree,
+    forest: &LoopForest,
+    reaching: &DataflowResult<usize>,
+    l: usize,
+) -> Vec<usize> {
+    let lp = forest.get_loop(l);
+    let exits = lp.get_exit_edges(cfg);
+    let exiting = lp.get_exiting_nodes(cfg);
+    let body = lp.get_body();
+
+    let mut hoistable: Vec<usize> = Vec::new();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for n in body.iter().cloned() {
+            if hoistable.contains(&n) || forest.get_innermost_loop(n) != Some(l) {
+                continue;
+            }
+            let (var, e) = match cfg.get_node(n).get_node_kind() {
+                NodeKind::Assignment(lvl, e) => match lvl.as_ref() {
+                    Expr::Id(var) => (var, e),
+                    _ => continue,
+                },
+                _ => continue,
+            };
+
+            let invariant = e.clone().iter().all(|v| {
+                let defs = cfg.reaching_defs_of(reaching, n, &v);
+                defs.iter().all(|d| !lp.contains(*d))
+                    || (defs.len() == 1
+                        && defs
+                            .iter()
+                            .all(|d| hoistable.contains(d) && dom.dominates(*d, n)))
+            });
+            if !invariant {
+                continue;
+            }
+
+            let single_def = body
+                .iter()
+                .filter(|m| get_def_var(cfg.get_node(**m).get_node_kind()) == Some(var))
+                .count()
+                == 1;
+            if !single_def || cfg.get_live_in(lp.get_header()).contains(var) {
+                continue;
+            }
+
+            let dominates_live_exits = exits
+                .iter()
+                .filter(|(_, s)| cfg.get_live_in(*s).contains(var))
+                .all(|(m, _)| dom.dominates(n, *m));
+            if !dominates_live_exits {
+                continue;
+            }
+
+            if may_fail(e) && !exiting.iter().all(|m| dom.dominates(n, *m)) {
+                continue;
+            }
+
+            hoistable.push(n);
+            changed = true;
+        }
+    }
+    hoistable.sort();
+    hoistable
+}
@@ -1,11 +1,12 @@
 use crate::ast::*;
-use std::{collections::HashSet, fmt::format};
+use crate::dot::DotOptions;
+use std::collections::BTreeSet;
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct ControlFlowGraph {
     nodes: Vec<Node>,
-    live_in: Vec<HashSet<String>>,
-    live_out: Vec<HashSet<String>>,
+    live_in: Vec<BTreeSet<String>>,
+    live_out: Vec<BTreeSet<String>>,
     live_ranges_found: bool,
 }
 
@@ -35,9 +36,34 @@ impl ControlFlowGraph {
             panic!("That node could not be found")
         }
     }
+    pub(crate) fn get_node_mut(&mut self, n: usize) -> &mut Node {
+        &mut self.nodes[n]
+    }
+
+    // Appends a node without edges and returns its index
+    pub(crate) fn push_node(&mut self, kind: NodeKind) -> usize {
+        let idx = self.nodes.len();
+        self.nodes.push(Node::new(idx, kind));
+        idx
+    }
+
+    // Inserts a node without edges at index 0, moving all others up by one
+    pub(crate) fn push_front_node(&mut self, kind: NodeKind) {
+        for n in self.nodes.iter_mut() {
+            n.shift(1);
+        }
+        self.nodes.insert(0, Node::new(0, kind));
+    }
+
+    // Redoes liveness after the graph changed, if it was done before
+    pub(crate) fn refresh_liveness(&mut self) {
+        if self.live_ranges_found {
+            self.fast_perform_liveness_analysis();
+        }
+    }
 
     // in[n] = use[n] U (out[n] - def[n])
-    fn calc_in(&self, node: &Node) -> HashSet<String> {
+    fn calc_in(&self, node: &Node) -> BTreeSet<String> {
         let out_diff_def = self
             .calc_out(node)
             .difference(node.get_defs())
@@ -48,9 +74,9 @@ impl ControlFlowGraph {
     }
 
     // out[n] = U in[s], where s = succ[n]
-    fn calc_out(&self, node: &Node) -> HashSet<String> {
+    fn calc_out(&self, node: &Node) -> BTreeSet<String> {
         let succs = node.get_succs();
-        let mut outs = HashSet::new();
+        let mut outs = BTreeSet::new();
 
         for s in succs {
             outs.extend(self.get_live_in(*s).iter().cloned());
@@ -60,79 +86,82 @@ impl ControlFlowGraph {
     }
 
     pub fn perform_liveness_analysis(&mut self) {
-        // init
-        for _ in 0..self.nodes.len() {
-            self.live_in.push(HashSet::new());
-            self.live_out.push(HashSet::new());
-        }
+        self.perform_liveness_analysis_with(&mut ());
+    }
 
-        let mut og_in = None;
-        let mut og_out = None;
+    pub fn fast_perform_liveness_analysis(&mut self) {
+        self.fast_perform_liveness_analysis_with(&mut ());
+    }
 
-        let mut it= 0;
+    // The analysis telling obs what it does, e.g. a LivenessStats
+    pub fn perform_liveness_analysis_with(&mut self, obs: &mut dyn LivenessObserver) {
+        let order: Vec<usize> = (0..self.nodes.len()).collect();
+        self.solve_liveness(order, obs);
+    }
 
-        loop {
-            for (idx, n) in self.nodes.iter().enumerate() {
-                self.live_in[idx] = self.calc_in(n);
-                self.live_out[idx] = self.calc_out(n);
-            }
-            it += 1;
-            if og_out == Some(self.live_out.clone()) && og_in == Some(self.live_in.clone()) {
-                break;
-            }
-            og_in = Some(self.live_in.clone());
-            og_out = Some(self.live_out.clone());
-        }
-        println!("Iterations: {it}");
-        self.live_ranges_found = true;
+    pub fn fast_perform_liveness_analysis_with(&mut self, obs: &mut dyn LivenessObserver) {
+        let order: Vec<usize> = (0..self.nodes.len()).rev().collect();
+        self.solve_liveness(order, obs);
     }
 
-    pub fn fast_perform_liveness_analysis(&mut self) {
-        // init
-        for _ in 0..self.nodes.len() {
-            self.live_in.push(HashSet::new());
-            self.live_out.push(HashSet::new());
-        }
+    // The analysis visiting nodes in order, with the sets after every pass
+    pub fn trace_liveness_analysis(&mut self) -> LivenessTrace {
+        let order: Vec<usize> = (0..self.nodes.len()).collect();
+        self.solve_liveness(order, &mut ())
+    }
 
-        let mut og_in = None;
-        let mut og_out = None;
+    // The analysis visiting nodes backwards, which needs fewer passes
+    pub fn fast_trace_liveness_analysis(&mut self) -> LivenessTrace {
+        let order: Vec<usize> = (0..self.nodes.len()).rev().collect();
+        self.solve_liveness(order, &mut ())
+    }
 
-        let mut it= 0;
+    // Passes over the nodes in order until one changes nothing
+    fn solve_liveness(&mut self, order: Vec<usize>, obs: &mut dyn LivenessObserver) -> LivenessTrace {
+        // init
+        self.live_in = vec![BTreeSet::new(); self.nodes.len()];
+        self.live_out = vec![BTreeSet::new(); self.nodes.len()];
 
+        let mut trace = LivenessTrace {
+            order,
+            iterations: Vec::new(),
+        };
         loop {
-            for i in (0..self.nodes.len()).rev() {
-                self.live_in[i] = self.calc_in(self.get_node(i));
-                self.live_out[i] = self.calc_out(self.get_node(i));
+            for &i in trace.order.iter() {
+                let live_in = self.calc_in(self.get_node(i));
+                let live_out = self.calc_out(self.get_node(i));
+                obs.visit(i, live_in != self.live_in[i], live_out != self.live_out[i]);
+                self.live_in[i] = live_in;
+                self.live_out[i] = live_out;
             }
-            //for (idx, n) in self.nodes.iter().enumerate() {
-            //    self.live_in[idx] = self.calc_in(n);
-            //    self.live_out[idx] = self.calc_out(n);
-            //}
-            it += 1;
-            if og_out == Some(self.live_out.clone()) && og_in == Some(self.live_in.clone()) {
+            let changed = trace.push(self.live_in.clone(), self.live_out.clone());
+            obs.iteration(trace.get_iteration_count(), changed);
+            if !changed {
                 break;
             }
-            og_in = Some(self.live_in.clone());
-            og_out = Some(self.live_out.clone());
         }
-        println!("Iterations: {it}");
         self.live_ranges_found = true;
+        trace
     }
 
-    pub fn get_live_in(&self,idx:usize) -> &HashSet<String> {
+    pub fn get_live_in(&self,idx:usize) -> &BTreeSet<String> {
         self.live_in.get(idx).unwrap()
     }
 
-    pub fn get_live_out(&self,idx:usize) -> &HashSet<String> {
+    pub fn get_live_out(&self,idx:usize) -> &BTreeSet<String> {
         self.live_out.get(idx).unwrap()
     }
 
-    pub fn get_live_sets(&self) -> (&Vec<HashSet<String>>,&Vec<HashSet<String>>) {
+    // Whether a liveness analysis has been performed, so live sets are there
+    pub fn is_analyzed(&self) -> bool {
+        self.live_ranges_found
+    }
+
+    pub fn get_live_sets(&self) -> (&Vec<BTreeSet<String>>,&Vec<BTreeSet<String>>) {
         (&self.live_in, &self.live_out)
     }
 
     pub fn get_live_range(&self, var: String) -> Vec<(usize,usize)> {
-        println!("Live range for {var}");
         let mut res = Vec::new();
         for (idx, n) in self.nodes.iter().enumerate() {
             let succ = n.get_succs();
@@ -146,42 +175,7 @@ impl ControlFlowGraph {
     }
 
     pub fn generate_dot(&self) -> String {
-        let mut sb = String::from(
-            "digraph CFG {\n\tnode [shape=rectangle];\n\n");
-
-        let mut blocks = String::new();
-        let mut edges= String::new();
-
-        for node in self.nodes.iter() {
-            let exp = format!("{}",node.node_kind);
-            let idx = node.idx;
-            let s = format!("\tblock{idx} [label=\"{exp}\"];\n");
-            blocks.push_str(s.as_str());
-
-            for s in node.get_succs() {
-                //if self.live_out.len() < node.idx {continue;}
-                //if self.live_in.len() < *s {continue;}
-                let mut live = String::from("");
-                if self.live_ranges_found {
-                    live = self.live_out[node.idx]
-                        .intersection(&self.live_in[*s])
-                        .map(|l| l.to_string())
-                        .collect::<Vec<_>>()
-                        .join(", ");
-                } 
-
-                edges.push_str(format!("\tblock{idx} -> block{s} [label=\"{live}\"];\n").as_str());
-            }
-
-        }
-        sb.push_str(&blocks);
-        sb.push('\n');
-
-        sb.push_str(&edges);
-
-        sb.push('}');
-
-        sb
+        self.generate_dot_with(&DotOptions::new())
     }
 
 }
@@ -217,9 +211,12 @@ impl FlattenerState {
             }
         }
 
+        // Body states start at an offset, so the global index of a succ
+        // has to be shifted to find it in this state's nodes
+        let base = cur_off - self.nodes.len();
         for idx in node.get_succs() {
-            if self.nodes.get(*idx).is_some() {
-                self.nodes[*idx].add_pred(cur_off);
+            if let Some(n) = idx.checked_sub(base).and_then(|i| self.nodes.get_mut(i)) {
+                n.add_pred(cur_off);
             }
         }
 
@@ -230,13 +227,17 @@ impl FlattenerState {
                 node.add_pred(n.get_node_idx());
             }
         }
+        // The cond of a while with an empty body loops to itself
+        if node.get_succs().contains(&cur_off) {
+            node.add_pred(cur_off);
+        }
 
         // TODO: Extract this to a "handle_use()"-function
         if let NodeKind::Condition(e) = node.get_node_kind() {
-            node.use_extend(e.clone().iter().collect::<HashSet<String>>());
+            node.use_extend(e.clone().iter().collect::<BTreeSet<String>>());
         }
         if let NodeKind::Return(e) = node.get_node_kind() {
-            node.use_extend(e.clone().iter().collect::<HashSet<String>>());
+            node.use_extend(e.clone().iter().collect::<BTreeSet<String>>());
         }
 
         self.nodes.push(node);
@@ -251,13 +252,148 @@ impl FlattenerState {
     }
 }
 
+// Gets told what the liveness analysis does, as it does it
+pub trait LivenessObserver {
+    // After computing in and out of a node, whether they differ from before
+    fn visit(&mut self, _node: usize, _in_changed: bool, _out_changed: bool) {}
+    // After every pass, counting from 1, whether it changed anything
+    fn iteration(&mut self, _iteration: usize, _changed: bool) {}
+}
+
+// Nobody is watching
+impl LivenessObserver for () {}
+
+// How much work the analysis took
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LivenessStats {
+    iterations: usize,
+    nodes_visited: usize,
+    set_changes: usize,
+}
+
+impl LivenessStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    // Passes over the nodes, including the last one that changed nothing
+    pub fn get_iterations(&self) -> usize {
+        self.iterations
+    }
+    pub fn get_nodes_visited(&self) -> usize {
+        self.nodes_visited
+    }
+    // Updates of an in or out set that changed it
+    pub fn get_set_changes(&self) -> usize {
+        self.set_changes
+    }
+}
+
+impl LivenessObserver for LivenessStats {
+    fn visit(&mut self, _node: usize, in_changed: bool, out_changed: bool) {
+        self.nodes_visited += 1;
+        self.set_changes += in_changed as usize + out_changed as usize;
+    }
+    fn iteration(&mut self, iteration: usize, _changed: bool) {
+        self.iterations = iteration;
+    }
+}
+
+impl std::fmt::Display for LivenessStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} iterations, {} nodes visited, {} set changes",
+            self.iterations, self.nodes_visited, self.set_changes
+        )
+    }
+}
+
+// in and out of every node after one pass of the analysis, and which of
+// them the pass changed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LivenessIteration {
+    live_in: Vec<BTreeSet<String>>,
+    live_out: Vec<BTreeSet<String>>,
+    in_changed: Vec<bool>,
+    out_changed: Vec<bool>,
+}
+
+impl LivenessIteration {
+    pub fn get_live_in(&self, idx: usize) -> &BTreeSet<String> {
+        &self.live_in[idx]
+    }
+    pub fn get_live_out(&self, idx: usize) -> &BTreeSet<String> {
+        &self.live_out[idx]
+    }
+    pub fn in_changed(&self, idx: usize) -> bool {
+        self.in_changed[idx]
+    }
+    pub fn out_changed(&self, idx: usize) -> bool {
+        self.out_changed[idx]
+    }
+    // Nodes whose in or out changed
+    pub fn get_changed_nodes(&self) -> Vec<usize> {
+        (0..self.live_in.len())
+            .filter(|i| self.in_changed[*i] || self.out_changed[*i])
+            .collect()
+    }
+}
+
+// Every pass of the liveness fixpoint, the last one changing nothing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LivenessTrace {
+    order: Vec<usize>,
+    iterations: Vec<LivenessIteration>,
+}
+
+impl LivenessTrace {
+    // The order nodes are visited in during a pass
+    pub fn get_order(&self) -> &Vec<usize> {
+        &self.order
+    }
+    pub fn get_iterations(&self) -> &Vec<LivenessIteration> {
+        &self.iterations
+    }
+    pub fn get_iteration_count(&self) -> usize {
+        self.iterations.len()
+    }
+
+    // Records a pass and returns whether it changed anything. The sets start
+    // out empty.
+    fn push(&mut self, live_in: Vec<BTreeSet<String>>, live_out: Vec<BTreeSet<String>>) -> bool {
+        let empty = BTreeSet::new();
+        let changed = |sets: &Vec<BTreeSet<String>>, prev: Option<&Vec<BTreeSet<String>>>| -> Vec<bool> {
+            sets.iter()
+                .enumerate()
+                .map(|(i, s)| *s != *prev.map_or(&empty, |p| &p[i]))
+                .collect()
+        };
+        let last = self.iterations.last();
+        let in_changed = changed(&live_in, last.map(|it| &it.live_in));
+        let out_changed = changed(&live_out, last.map(|it| &it.live_out));
+        let any = in_changed.iter().chain(out_changed.iter()).any(|c| *c);
+        self.iterations.push(LivenessIteration {
+            live_in,
+            live_out,
+            in_changed,
+            out_changed,
+        });
+        any
+    }
+}
+
 fn flatten_program(p: &Program) -> Vec<Node> {
     let mut flat_state = FlattenerState::new();
 
     flatten_statements(&mut flat_state, p.stmts.iter().collect());
 
-    if let Some(last_node) = flat_state.nodes.last_mut() {
-        last_node.clear_succ();
+    // Edges falling off the end of the program (the last statement, or the
+    // false branch of a trailing if/loop) lead to the exit, not to a node
+    let len = flat_state.nodes.len();
+    for node in flat_state.nodes.iter_mut() {
+        node.succ.retain(|s| *s < len);
+        node.true_succ = node.true_succ.filter(|s| *s < len);
+        node.false_succ = node.false_succ.filter(|s| *s < len);
     }
 
     flat_state.nodes
@@ -286,8 +422,7 @@ fn flatten_statements(state: &mut FlattenerState, stmts: Vec<&Statement>) {
                 flatten_statements(&mut body_flat_state, body.iter().collect());
                 let body_end = body_flat_state.nodes.len();
 
-                cond_node.add_succ(body_start);
-                cond_node.add_succ(body_start + body_end);
+                cond_node.add_branches(body_start, body_start + body_end);
 
                 state.add_node(cond_node);
 
@@ -310,10 +445,9 @@ fn flatten_statements(state: &mut FlattenerState, stmts: Vec<&Statement>) {
 
                 let mut cond_node = Node::new(body_end,NodeKind::Condition(cond.clone()));
 
-                // Cond node goes to start of loop if true
-                cond_node.add_succ(body_start);
-                // Cond node next node if false
-                cond_node.add_succ(body_end + 1);
+                // Cond node goes to start of loop if true,
+                // and to the next node if false
+                cond_node.add_branches(body_start, body_end + 1);
 
                 state.add_node(cond_node);
             }
@@ -325,11 +459,21 @@ fn flatten_statements(state: &mut FlattenerState, stmts: Vec<&Statement>) {
 
                 flatten_statements(&mut body_flat_state, body.iter().collect());
                 let body_len = body_flat_state.nodes.len();
+                let cond_idx = state.get_offset();
+                let body_end = body_start + body_len;
+
+                // Leaving the body jumps back to the cond (the back edge)
+                for bn in body_flat_state.nodes.iter_mut() {
+                    bn.redirect_succ(body_end, cond_idx);
+                }
 
                 // init cond node and modify
-                let mut cond_node = Node::new(state.get_offset(), NodeKind::Condition(cond.clone()));
-                cond_node.add_succ(body_start);
-                cond_node.add_succ(body_start + body_len);
+                let mut cond_node = Node::new(cond_idx, NodeKind::Condition(cond.clone()));
+                if body_len == 0 {
+                    cond_node.add_branches(cond_idx, body_end);
+                } else {
+                    cond_node.add_branches(body_start, body_end);
+                }
 
                 state.add_node(cond_node);
 
@@ -338,8 +482,6 @@ fn flatten_statements(state: &mut FlattenerState, stmts: Vec<&Statement>) {
                     state.add_node(bn);
                 }
             }
-            // this is merely keep error recovery when additions are made
-            _ => panic!("Not implemented yet"),
         }
     }
 }
@@ -356,7 +498,7 @@ fn handle_assignment(idx: usize,stmt: &Statement) -> Node {
                 node.insert_def(id);
             }
 
-            node.use_extend(e.clone().iter().collect::<HashSet<String>>());
+            node.use_extend(e.clone().iter().collect::<BTreeSet<String>>());
             node
         }
         _ => panic!("Death"),
@@ -365,7 +507,7 @@ fn handle_assignment(idx: usize,stmt: &Statement) -> Node {
 fn handle_return(idx: usize, stmt: &Statement) -> Node {
     match stmt {
         Statement::Return(e) => {
-            Node::new(idx,NodeKind::from(e.clone()))
+            Node::new(idx,NodeKind::Return(e.clone()))
         }
         _ => panic!("Death"),
     }
@@ -376,6 +518,32 @@ pub enum NodeKind {
     Assignment(Box<Expr>, Box<Expr>),
     Condition(Box<Expr>),
     Return(Box<Expr>),
+    // Does nothing, added when normalizing the CFG (split edges, preheaders,
+    // unique entry/exit). Never produced from a Program.
+    Empty,
+}
+
+impl NodeKind {
+    // The expression evaluated by the node, Empty nodes evaluate none
+    pub fn get_expr(&self) -> Option<&Expr> {
+        match self {
+            NodeKind::Assignment(_, e) | NodeKind::Condition(e) | NodeKind::Return(e) => Some(e),
+            NodeKind::Empty => None,
+        }
+    }
+    // Every BinOp the node evaluates, outermost first
+    pub fn get_binops(&self) -> Vec<&Expr> {
+        self.get_expr().map_or(Vec::new(), |e| e.get_binops())
+    }
+    // The same node evaluating another expression
+    pub fn with_expr(&self, e: Expr) -> NodeKind {
+        match self {
+            NodeKind::Assignment(lvl, _) => NodeKind::Assignment(lvl.clone(), Box::new(e)),
+            NodeKind::Condition(_) => NodeKind::Condition(Box::new(e)),
+            NodeKind::Return(_) => NodeKind::Return(Box::new(e)),
+            NodeKind::Empty => panic!("An empty node has no expression to replace"),
+        }
+    }
 }
 
 pub fn get_ids_from_expr(e: Box<Expr>) -> Vec<String> {
@@ -386,11 +554,14 @@ pub fn get_ids_from_expr(e: Box<Expr>) -> Vec<String> {
 pub struct Node {
     idx : usize,
     node_kind: NodeKind,
-    use_set: HashSet<String>,
-    def_set: HashSet<String>,
-    in_set: HashSet<String>,
-    pred: HashSet<usize>,
-    succ: HashSet<usize>,
+    use_set: BTreeSet<String>,
+    def_set: BTreeSet<String>,
+    in_set: BTreeSet<String>,
+    pred: BTreeSet<usize>,
+    succ: BTreeSet<usize>,
+    // Where a condition node goes when it holds or not, None being the exit
+    true_succ: Option<usize>,
+    false_succ: Option<usize>,
 }
 
 impl Node {
@@ -400,11 +571,13 @@ impl Node {
             node_kind,
             // According to the compiler book, the sets below
             // should stored in the CFG to improve modulatity
-            use_set: HashSet::new(),
-            def_set: HashSet::new(),
-            pred: HashSet::new(),
-            succ: HashSet::new(),
-            in_set: HashSet::new(),
+            use_set: BTreeSet::new(),
+            def_set: BTreeSet::new(),
+            pred: BTreeSet::new(),
+            succ: BTreeSet::new(),
+            in_set: BTreeSet::new(),
+            true_succ: None,
+            false_succ: None,
         }
     }
 
@@ -416,7 +589,7 @@ impl Node {
         &self.node_kind
     }
 
-    pub fn use_extend(&mut self, vars: HashSet<String>) {
+    pub fn use_extend(&mut self, vars: BTreeSet<String>) {
         self.use_set.extend(vars);
     }
     pub fn insert_use(&mut self, var: String) {
@@ -426,10 +599,10 @@ impl Node {
         self.def_set.insert(var);
     }
 
-    pub fn get_defs(&self) -> &HashSet<String> {
+    pub fn get_defs(&self) -> &BTreeSet<String> {
         &self.def_set
     }
-    pub fn get_uses(&self) -> &HashSet<String> {
+    pub fn get_uses(&self) -> &BTreeSet<String> {
         &self.use_set
     }
 
@@ -449,11 +622,14 @@ impl Node {
 
     pub fn set_pred(&mut self, nv: Vec<usize>) {
         self.pred.clear();
-        let _ = nv.iter().map(|p| self.add_pred(*p));
+        self.pred.extend(nv);
     }
     pub fn set_succ(&mut self, nv: Vec<usize>) {
         self.succ.clear();
-        let _ = nv.iter().map(|p| self.add_succ(*p));
+        self.succ.extend(nv);
+    }
+    pub fn remove_pred(&mut self, n: usize) -> bool {
+        self.pred.remove(&n)
     }
     pub fn clear_pred(&mut self) {
         self.pred.clear();
@@ -462,10 +638,73 @@ impl Node {
         self.succ.clear();
     }
 
-    pub fn get_preds(&self) -> &HashSet<usize> {
+    pub fn add_branches(&mut self, t: usize, f: usize) {
+        self.true_succ = Some(t);
+        self.false_succ = Some(f);
+        self.add_succ(t);
+        self.add_succ(f);
+    }
+    pub fn get_true_succ(&self) -> Option<usize> {
+        self.true_succ
+    }
+    pub fn get_false_succ(&self) -> Option<usize> {
+        self.false_succ
+    }
+    // Whether the program can end right after the node: it has no succs, or
+    // it is a condition with a branch to the exit. Such a branch is not in
+    // the succ set, so a node can have succs and still be an exit.
+    pub fn is_exit(&self) -> bool {
+        match self.node_kind {
+            NodeKind::Condition(_) => self.true_succ.is_none() || self.false_succ.is_none(),
+            _ => self.succ.is_empty(),
+        }
+    }
+    // Moves the edge to `from` over to `to`, returns whether there was one
+    pub fn redirect_succ(&mut self, from: usize, to: usize) -> bool {
+        if !self.succ.remove(&from) {
+            return false;
+        }
+        self.add_succ(to);
+        if self.true_succ == Some(from) {
+            self.true_succ = Some(to);
+        }
+        if self.false_succ == Some(from) {
+            self.false_succ = Some(to);
+        }
+        true
+    }
+
+    // Sets where the condition goes when it holds (true) or not (false),
+    // keeping the succ set in line
+    pub fn set_branch(&mut self, branch: bool, to: usize) {
+        let old = if branch { self.true_succ } else { self.false_succ };
+        let other = if branch { self.false_succ } else { self.true_succ };
+        if let Some(old) = old {
+            if other != Some(old) {
+                self.succ.remove(&old);
+            }
+        }
+        if branch {
+            self.true_succ = Some(to);
+        } else {
+            self.false_succ = Some(to);
+        }
+        self.add_succ(to);
+    }
+
+    // Moves every index the node holds up by `by`, for nodes inserted in front
+    pub(crate) fn shift(&mut self, by: usize) {
+        self.idx += by;
+        self.pred = self.pred.iter().map(|p| p + by).collect();
+        self.succ = self.succ.iter().map(|s| s + by).collect();
+        self.true_succ = self.true_succ.map(|s| s + by);
+        self.false_succ = self.false_succ.map(|s| s + by);
+    }
+
+    pub fn get_preds(&self) -> &BTreeSet<usize> {
         &self.pred
     }
-    pub fn get_succs(&self) -> &HashSet<usize> {
+    pub fn get_succs(&self) -> &BTreeSet<usize> {
         &self.succ
     }
 }
@@ -0,0 +1,232 @@
+use crate::liveness::ControlFlowGraph;
+use std::collections::BTreeSet;
+
+// A natural loop. Back edges sharing a header are merged into one loop.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Loop {
+    header: usize,
+    latches: Vec<usize>,
+    body: BTreeSet<usize>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    depth: usize,
+}
+
+impl Loop {
+    pub fn get_header(&self) -> usize {
+        self.header
+    }
+    // Sources of the back edges into the header
+    pub fn get_latches(&self) -> &Vec<usize> {
+        &self.latches
+    }
+    // Every node of the loop, including the header and nested loops
+    pub fn get_body(&self) -> &BTreeSet<usize> {
+        &self.body
+    }
+    pub fn contains(&self, n: usize) -> bool {
+        self.body.contains(&n)
+    }
+    pub fn get_parent(&self) -> Option<usize> {
+        self.parent
+    }
+    pub fn get_children(&self) -> &Vec<usize> {
+        &self.children
+    }
+    // Outermost loops have depth 1
+    pub fn get_depth(&self) -> usize {
+        self.depth
+    }
+
+    // Edges (from, to) leaving the loop
+    pub fn get_exit_edges(&self, cfg: &ControlFlowGraph) -> Vec<(usize, usize)> {
+        let mut res = Vec::new();
+        for n in self.body.iter() {
+            for s in cfg.get_node(*n).get_succs() {
+                if !self.body.contains(s) {
+                    res.push((*n, *s));
+                }
+            }
+        }
+        res
+    }
+
+    // Nodes after which control can leave the loop, through an exit edge or
+    // because the program ends there (a return, or a branch to the exit)
+    pub fn get_exiting_nodes(&self, cfg: &ControlFlowGraph) -> Vec<usize> {
+        self.body
+            .iter()
+            .filter(|n| {
+                let node = cfg.get_node(**n);
+                node.is_exit() || node.get_succs().iter().any(|s| !self.body.contains(s))
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+// All natural loops of a CFG, ordered by header, together with the
+// innermost loop of every node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoopForest {
+    loops: Vec<Loop>,
+    innermost: Vec<Option<usize>>,
+}
+
+impl LoopForest {
+    pub fn get_loops(&self) -> &Vec<Loop> {
+        &self.loops
+    }
+    pub fn get_loop(&self, l: usize) -> &Loop {
+        &self.loops[l]
+    }
+    pub fn get_roots(&self) -> Vec<usize> {
+        (0..self.loops.len())
+            .filter(|l| self.loops[*l].parent.is_none())
+            .collect()
+    }
+    pub fn get_innermost_loop(&self, n: usize) -> Option<usize> {
+        self.innermost[n]
+    }
+    // Number of loops containing the node, 0 outside of loops
+    pub fn get_loop_depth(&self, n: usize) -> usize {
+        self.innermost[n].map_or(0, |l| self.loops[l].depth)
+    }
+    pub fn get_loop_depths(&self) -> Vec<usize> {
+        (0..self.innermost.len())
+            .map(|n| self.get_loop_depth(n))
+            .collect()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.loops.is_empty()
+    }
+
+    // Nested `subgraph cluster_*` blocks for generate_dot. `block_lines`
+    // holds the declaration of every node, nodes outside loops are skipped.
+    pub fn dot_clusters(&self, block_lines: &[String]) -> Vec<(usize, String)> {
+        self.get_roots()
+            .into_iter()
+            .map(|l| {
+                let first = *self.loops[l].body.iter().min().unwrap();
+                (first, self.dot_cluster(l, block_lines, 1))
+            })
+            .collect()
+    }
+
+    fn dot_cluster(&self, l: usize, block_lines: &[String], indent: usize) -> String {
+        let lp = &self.loops[l];
+        let tabs = "\t".repeat(indent);
+        let mut sb = format!("{tabs}subgraph cluster_loop{l} {{\n");
+        sb.push_str(&format!(
+            "{tabs}\tlabel=\"loop {l} (header {}, depth {})\";\n{tabs}\tstyle=dashed;\n",
+            lp.header, lp.depth
+        ));
+
+        let mut done = BTreeSet::new();
+        for n in lp.body.iter().cloned() {
+            match self.innermost[n] {
+                Some(inner) if inner == l => {
+                    sb.push_str(&format!("{tabs}{}", block_lines[n]));
+                }
+                _ => {
+                    // Emit the child loop containing n, at its first node
+                    let child = lp
+                        .children
+                        .iter()
+                        .find(|c| self.loops[**c].contains(n))
+                        .unwrap();
+                    if done.insert(*child) {
+                        sb.push_str(&self.dot_cluster(*child, block_lines, indent + 1));
+                    }
+                }
+            }
+        }
+        sb.push_str(&format!("{tabs}}}\n"));
+        sb
+    }
+}
+
+impl ControlFlowGraph {
+    // Edges n -> h where h dominates n
+    pub fn back_edges(&self) -> Vec<(usize, usize)> {
+        let dom = self.dominator_tree();
+        let mut res = Vec::new();
+        for (idx, n) in self.get_nodes().iter().enumerate() {
+            for s in n.get_succs() {
+                if dom.dominates(*s, idx) {
+                    res.push((idx, *s));
+                }
+            }
+        }
+        res
+    }
+
+    pub fn loop_forest(&self) -> LoopForest {
+        let n = self.get_nodes().len();
+        let dom = self.dominator_tree();
+
+        // Natural loop of n -> h: h plus everything that reaches n without
+        // going through h
+        let mut loops: Vec<Loop> = Vec::new();
+        for (latch, header) in self.back_edges() {
+            let idx = match loops.iter().position(|l| l.header == header) {
+                Some(idx) => idx,
+                None => {
+                    loops.push(Loop {
+                        header,
+                        latches: Vec::new(),
+                        body: BTreeSet::from([header]),
+                        parent: None,
+                        children: Vec::new(),
+                        depth: 0,
+                    });
+                    loops.len() - 1
+                }
+            };
+            let lp = &mut loops[idx];
+            lp.latches.push(latch);
+            let mut stack = vec![latch];
+            while let Some(m) = stack.pop() {
+                if dom.is_reachable(m) && lp.body.insert(m) {
+                    stack.extend(self.get_node(m).get_preds().iter());
+                }
+            }
+        }
+        loops.sort_by_key(|l| l.header);
+
+        // The parent of a loop is the smallest other loop containing its header
+        for l in 0..loops.len() {
+            let header = loops[l].header;
+            loops[l].parent = (0..loops.len())
+                .filter(|m| *m != l && loops[*m].contains(header))
+                .min_by_key(|m| loops[*m].body.len());
+        }
+        for l in 0..loops.len() {
+            if let Some(p) = loops[l].parent {
+                loops[p].children.push(l);
+            }
+        }
+        for l in 0..loops.len() {
+            let mut depth = 1;
+            let mut runner = loops[l].parent;
+            while let Some(p) = runner {
+                depth += 1;
+                runner = loops[p].parent;
+            }
+            loops[l].depth = depth;
+        }
+
+        let mut innermost = vec![None; n];
+        for (node, inner) in innermost.iter_mut().enumerate() {
+            *inner = (0..loops.len())
+                .filter(|l| loops[*l].contains(node))
+                .min_by_key(|l| loops[*l].body.len());
+        }
+
+        LoopForest { loops, innermost }
+    }
+
+    pub fn get_loop_depths(&self) -> Vec<usize> {
+        self.loop_forest().get_loop_depths()
+    }
+}
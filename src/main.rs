@@ -1,22 +1,8 @@
-use live_crab::lexer::Lexer;
-use live_crab::liveness::*;
-use live_crab::parser::Parser;
+use live_crab::cli;
 use std::io;
 
-pub fn get_str_from_path(path: &str) -> Option<String> {
-    std::fs::read_to_string(path).ok()
-}
-
-fn main() -> io::Result<()> {
-    let s = get_str_from_path("examples/book_ex").unwrap();
-    let lexer = Lexer::new(&s);
-    let tokens = lexer.tokenize();
-    let mut parser = Parser::new(tokens);
-    let prog = parser.parse();
-    let mut cfg = ControlFlowGraph::from(&prog);
-    cfg.fast_perform_liveness_analysis();
-
-    println!("Dot:\n{}",cfg.generate_dot());
-
-    Ok(())
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let code = cli::run(&args, &mut io::stdin().lock(), &mut io::stdout().lock(), &mut io::stderr().lock());
+    std::process::exit(code);
 }
@@ -0,0 +1,186 @@
+use crate::liveness::{ControlFlowGraph, NodeKind};
+use std::collections::HashSet;
+
+// Utilities bringing the CFG into the shapes many passes expect. New nodes
+// are NodeKind::Empty and appended after the existing ones, except for a new
+// entry, which has to be node 0. Preds, succs, the true/false branches and
+// (if it was performed) liveness are kept up to date.
+impl ControlFlowGraph {
+    // Puts an empty node on the edge from -> to and returns it
+    pub fn split_edge(&mut self, from: usize, to: usize) -> usize {
+        assert!(
+            self.get_node(from).get_succs().contains(&to),
+            "There is no edge {from} -> {to}"
+        );
+        let new = self.push_node(NodeKind::Empty);
+        self.get_node_mut(from).redirect_succ(to, new);
+        self.get_node_mut(new).add_pred(from);
+        self.get_node_mut(new).add_succ(to);
+        let target = self.get_node_mut(to);
+        target.remove_pred(from);
+        target.add_pred(new);
+        self.refresh_liveness();
+        new
+    }
+
+    // Edges from a node with several successors to a node with several
+    // predecessors, sorted. A condition branching to the exit has the exit
+    // as one of its successors.
+    pub fn critical_edges(&self) -> Vec<(usize, usize)> {
+        let nodes = self.get_nodes();
+        let mut res = Vec::new();
+        for (idx, n) in nodes.iter().enumerate() {
+            let exit = matches!(n.get_node_kind(), NodeKind::Condition(_)) && n.is_exit();
+            if n.get_succs().len() + usize::from(exit) < 2 {
+                continue;
+            }
+            for s in n.get_succs() {
+                if nodes[*s].get_preds().len() > 1 {
+                    res.push((idx, *s));
+                }
+            }
+        }
+        res.sort();
+        res
+    }
+
+    // Splits every critical edge, returns the new nodes
+    pub fn split_critical_edges(&mut self) -> Vec<usize> {
+        self.critical_edges()
+            .into_iter()
+            .map(|(from, to)| self.split_edge(from, to))
+            .collect()
+    }
+
+    // Makes sure the entry, node 0, has no predecessors. If it is a loop
+    // header, an empty node is put in front of it and all nodes move up by
+    // one. Returns whether a node was added.
+    pub fn ensure_unique_entry(&mut self) -> bool {
+        let n = self.get_nodes().len();
+        if n > 0 && self.get_node(0).get_preds().is_empty() {
+            return false;
+        }
+        self.push_front_node(NodeKind::Empty);
+        if n > 0 {
+            self.get_node_mut(0).add_succ(1);
+            self.get_node_mut(1).add_pred(0);
+        }
+        self.refresh_liveness();
+        true
+    }
+
+    // Makes every path that ends lead to a single node without successors.
+    // If there are several ways out (returns, the last statement, branches
+    // falling off the end), they are sent to a new empty node. Returns the
+    // exit, or None if the program never ends.
+    pub fn ensure_unique_exit(&mut self) -> Option<usize> {
+        let nodes = self.get_nodes();
+        // Nodes leaving the program, and whether they do so through a branch
+        let mut leaving: Vec<(usize, Option<bool>)> = Vec::new();
+        for (idx, n) in nodes.iter().enumerate() {
+            match n.get_node_kind() {
+                NodeKind::Condition(_) => {
+                    if n.get_true_succ().is_none() {
+                        leaving.push((idx, Some(true)));
+                    }
+                    if n.get_false_succ().is_none() {
+                        leaving.push((idx, Some(false)));
+                    }
+                }
+                _ if n.get_succs().is_empty() => leaving.push((idx, None)),
+                _ => {}
+            }
+        }
+        if nodes.is_empty() {
+            leaving.push((0, None));
+        }
+
+        match leaving.as_slice() {
+            [] => return None,
+            [(idx, None)] if !nodes.is_empty() => return Some(*idx),
+            _ => {}
+        }
+        let exit = self.push_node(NodeKind::Empty);
+        for (idx, branch) in leaving {
+            if idx == exit {
+                continue;
+            }
+            match branch {
+                Some(b) => self.get_node_mut(idx).set_branch(b, exit),
+                None => self.get_node_mut(idx).add_succ(exit),
+            }
+            self.get_node_mut(exit).add_pred(idx);
+        }
+        self.refresh_liveness();
+        Some(exit)
+    }
+
+    // Gives every loop a preheader: a node outside the loop whose only
+    // successor is the header, through which every edge entering the loop
+    // goes. A lone predecessor that already fits is kept, otherwise an empty
+    // node is added. A loop entered at node 0 gets a new entry first. Returns
+    // (header, preheader) pairs, sorted by header.
+    pub fn add_loop_preheaders(&mut self) -> Vec<(usize, usize)> {
+        let forest = self.loop_forest();
+        if forest.get_loops().iter().any(|l| l.get_header() == 0) && self.ensure_unique_entry() {
+            return self.add_loop_preheaders();
+        }
+
+        let mut res = Vec::new();
+        for lp in forest.get_loops() {
+            let header = lp.get_header();
+            let mut outside: Vec<usize> = self
+                .get_node(header)
+                .get_preds()
+                .iter()
+                .filter(|p| !lp.contains(**p))
+                .cloned()
+                .collect();
+            outside.sort();
+
+            if let [p] = outside.as_slice() {
+                let p_node = self.get_node(*p);
+                if p_node.get_succs().len() == 1 && !p_node.is_exit() {
+                    res.push((header, *p));
+                    continue;
+                }
+            }
+            let pre = self.push_node(NodeKind::Empty);
+            for p in outside.iter() {
+                self.get_node_mut(*p).redirect_succ(header, pre);
+                self.get_node_mut(pre).add_pred(*p);
+                self.get_node_mut(header).remove_pred(*p);
+            }
+            self.get_node_mut(pre).add_succ(header);
+            self.get_node_mut(header).add_pred(pre);
+            res.push((header, pre));
+        }
+        self.refresh_liveness();
+        res.sort();
+        res
+    }
+
+    // Whether preds and succs mirror each other and branches are succs
+    pub fn is_consistent(&self) -> bool {
+        let nodes = self.get_nodes();
+        let edges: HashSet<(usize, usize)> = nodes
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, n)| n.get_succs().iter().map(move |s| (idx, *s)))
+            .collect();
+        let back: HashSet<(usize, usize)> = nodes
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, n)| n.get_preds().iter().map(move |p| (*p, idx)))
+            .collect();
+        edges == back
+            && nodes.iter().enumerate().all(|(idx, n)| {
+                n.get_node_idx() == idx
+                    && n.get_succs().iter().all(|s| *s < nodes.len())
+                    && [n.get_true_succ(), n.get_false_succ()]
+                        .iter()
+                        .flatten()
+                        .all(|s| n.get_succs().contains(s))
+            })
+    }
+}
@@ -0,0 +1,159 @@
+use crate::ast::*;
+use crate::interpreter::{Interpreter, Trace};
+use crate::liveness::{ControlFlowGraph, NodeKind};
+use std::collections::BTreeSet;
+use std::fmt::Display;
+
+// Variables read by a node, and the variable it writes. Taken from the node
+// itself, not from the use/def sets the static analysis works on.
+fn reads(kind: &NodeKind) -> BTreeSet<String> {
+    match kind.get_expr() {
+        Some(e) => Box::new(e.clone()).iter().collect(),
+        None => BTreeSet::new(),
+    }
+}
+fn writes(kind: &NodeKind) -> Option<&String> {
+    match kind {
+        NodeKind::Assignment(lvl, _) => match lvl.as_ref() {
+            Expr::Id(var) => Some(var),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// For each step of the trace, the variables that were really read later in
+// the run (at that step or after) before being written again. These are the
+// variables live on entry to the step's node, as seen in this one run.
+pub fn dynamic_live_in(cfg: &ControlFlowGraph, trace: &Trace) -> Vec<BTreeSet<String>> {
+    let mut live = BTreeSet::new();
+    let mut res = Vec::new();
+    for step in trace.get_steps().iter().rev() {
+        let kind = cfg.get_node(step.get_node()).get_node_kind();
+        if let Some(var) = writes(kind) {
+            live.remove(var);
+        }
+        live.extend(reads(kind));
+        res.push(live.clone());
+    }
+    res.reverse();
+    res
+}
+
+// A step where a variable was read later but the static analysis did not
+// have it live on entry to the node
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unsoundness {
+    step: usize,
+    node: usize,
+    missing: BTreeSet<String>,
+}
+
+impl Unsoundness {
+    pub fn get_step(&self) -> usize {
+        self.step
+    }
+    pub fn get_node(&self) -> usize {
+        self.node
+    }
+    // Dynamically live variables missing from the static live_in
+    pub fn get_missing(&self) -> &BTreeSet<String> {
+        &self.missing
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LivenessCheck {
+    cfg: ControlFlowGraph,
+    live_in: Vec<BTreeSet<String>>,
+    trace: Trace,
+    dynamic: Vec<BTreeSet<String>>,
+    violations: Vec<Unsoundness>,
+}
+
+impl LivenessCheck {
+    pub fn get_cfg(&self) -> &ControlFlowGraph {
+        &self.cfg
+    }
+    // The static live_in that was checked, by node
+    pub fn get_static_live_in(&self) -> &Vec<BTreeSet<String>> {
+        &self.live_in
+    }
+    pub fn get_trace(&self) -> &Trace {
+        &self.trace
+    }
+    // Dynamic live_in, one set per step of the trace
+    pub fn get_dynamic_live_in(&self) -> &Vec<BTreeSet<String>> {
+        &self.dynamic
+    }
+    // Steps where the static result missed a variable, in trace order
+    pub fn get_violations(&self) -> &Vec<Unsoundness> {
+        &self.violations
+    }
+    pub fn is_sound(&self) -> bool {
+        self.violations.is_empty()
+    }
+    // Panics with the CFG, the trace and what went wrong if unsound
+    pub fn assert_sound(&self) {
+        assert!(self.is_sound(), "{self}");
+    }
+}
+
+impl Display for LivenessCheck {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_sound() {
+            return writeln!(f, "liveness sound over {} steps", self.dynamic.len());
+        }
+        writeln!(f, "liveness unsound")?;
+        write!(f, "{}", self.cfg)?;
+        write!(f, "{}", self.trace)?;
+        for v in self.violations.iter() {
+            let missing: Vec<&str> = v.missing.iter().map(|s| s.as_str()).collect();
+            writeln!(
+                f,
+                "step {}: live_in({}) is missing {}",
+                v.step,
+                v.node,
+                missing.join(", ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// Runs the program and checks the static live_in of every node it passes
+// through holds every variable that is really read later
+pub fn check_liveness(p: &Program, interp: &Interpreter) -> LivenessCheck {
+    check_cfg_liveness(&ControlFlowGraph::from(p), interp)
+}
+
+// The same on a CFG, which may have been normalized
+pub fn check_cfg_liveness(cfg: &ControlFlowGraph, interp: &Interpreter) -> LivenessCheck {
+    let mut analyzed = cfg.clone();
+    analyzed.perform_liveness_analysis();
+    let live_in = analyzed.get_live_sets().0.clone();
+    check_trace(cfg, &live_in, interp.trace(cfg))
+}
+
+// Checks a trace of the CFG against any static live_in, one set per node
+pub fn check_trace(cfg: &ControlFlowGraph, live_in: &[BTreeSet<String>], trace: Trace) -> LivenessCheck {
+    let dynamic = dynamic_live_in(cfg, &trace);
+    let violations = trace
+        .get_steps()
+        .iter()
+        .zip(dynamic.iter())
+        .enumerate()
+        .filter_map(|(step, (s, live))| {
+            let node = s.get_node();
+            let missing: BTreeSet<String> = live.iter().filter(|v| !live_in[node].contains(*v)).cloned().collect();
+            (!missing.is_empty()).then_some(Unsoundness { step, node, missing })
+        })
+        .collect();
+    LivenessCheck {
+        cfg: cfg.clone(),
+        live_in: live_in.to_vec(),
+        trace,
+        dynamic,
+        violations,
+    }
+}
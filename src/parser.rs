@@ -1,14 +1,85 @@
 use crate::ast::*;
-use crate::lexer::Token;
+use crate::lexer::{Span, Token};
+use std::fmt::Display;
+
+// What went wrong, at the index of the offending token. An index past the
+// last token means the input ended too early.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    token: usize,
+    span: Option<Span>,
+    msg: String,
+}
+
+impl ParseError {
+    pub fn get_token_idx(&self) -> usize {
+        self.token
+    }
+    // Where the offending token is, if the parser was given spans. At the end
+    // of the input it is the empty span after the last token.
+    pub fn get_span(&self) -> Option<Span> {
+        self.span
+    }
+    pub fn get_msg(&self) -> &String {
+        &self.msg
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (token {})", self.msg, self.token)
+    }
+}
 
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize, // for peeking
+    spans: Vec<Span>,
+    node_spans: Vec<Span>,
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
-        Parser { tokens, current: 0 }
+        Parser {
+            tokens,
+            current: 0,
+            spans: Vec::new(),
+            node_spans: Vec::new(),
+        }
+    }
+
+    // Tokens with where they are in the source, from
+    // Lexer::tokenize_with_spans. Errors and node spans then point into it.
+    pub fn with_spans(tokens: Vec<(Token, Span)>) -> Self {
+        let (tokens, spans) = tokens.into_iter().unzip();
+        Parser {
+            tokens,
+            current: 0,
+            spans,
+            node_spans: Vec::new(),
+        }
+    }
+
+    // The source of each statement and condition parsed, indexed like the
+    // nodes of the CFG built from the program. Empty without spans.
+    pub fn get_node_spans(&self) -> &Vec<Span> {
+        &self.node_spans
+    }
+
+    pub fn get_token_span(&self, idx: usize) -> Option<Span> {
+        match self.spans.get(idx) {
+            Some(s) => Some(*s),
+            None => self.spans.last().map(|s| Span::new(s.get_end(), s.get_end())),
+        }
+    }
+
+    // Records the node made of the tokens from first up to the current one.
+    // Conditions of if and while are recorded before their body, that of a
+    // do-while after, which is the order the CFG numbers them in.
+    fn record_node(&mut self, first: usize) {
+        if let (Some(first), Some(last)) = (self.spans.get(first), self.spans.get(self.current - 1)) {
+            self.node_spans.push(first.to(*last));
+        }
     }
 
     // utility
@@ -16,98 +87,132 @@ impl Parser {
         self.tokens.get(self.current)
     }
 
+    fn error(&self, expected: &str) -> ParseError {
+        let msg = match self.tokens.get(self.current) {
+            Some(t) => format!("expected {expected}, found {t}"),
+            None => format!("expected {expected}, found end of input"),
+        };
+        ParseError {
+            token: self.current,
+            span: self.get_token_span(self.current),
+            msg,
+        }
+    }
+
+    // Parses statements up to the first token that does not start one and
+    // returns them, ignoring the rest. Panics on a malformed statement. See
+    // try_parse for a parse of the whole input that reports errors.
     pub fn parse(&mut self) -> Program {
-        let stmts = self.get_statements();
-        Program::new(stmts)
+        match self.get_statements() {
+            Ok(stmts) => Program::new(stmts),
+            Err(e) => panic!("{e}"),
+        }
+    }
+
+    // Every token has to be part of a statement
+    pub fn try_parse(&mut self) -> Result<Program, ParseError> {
+        let stmts = self.get_statements()?;
+        if self.peek().is_some() {
+            return Err(self.error("a statement"));
+        }
+        Ok(Program::new(stmts))
     }
 
-    fn get_statements(&mut self) -> Vec<Statement> {
+    fn get_statements(&mut self) -> Result<Vec<Statement>, ParseError> {
         let mut stmts = Vec::new();
-        while let Some(e) = self.next_statement() {
+        while let Some(e) = self.try_next_statement()? {
             stmts.push(e);
         }
-        stmts
+        Ok(stmts)
     }
     pub fn next_statement(&mut self) -> Option<Statement> {
+        self.try_next_statement().ok().flatten()
+    }
+
+    // Ok(None) if the next token does not start a statement
+    fn try_next_statement(&mut self) -> Result<Option<Statement>, ParseError> {
         match self.peek() {
-            // Some(t) => {
-            //     println!("Next_statment starts with token: {:?}", t);
-            //     return self.eat_assignment();
-            // }
-            Some(Token::Id(_)) => return self.eat_assignment(),
+            Some(Token::Id(_)) => self.eat_assignment().map(Some),
             Some(Token::Keyword(s)) => {
                 let s = s.clone();
-                return self.eat_keyword(s);
+                self.eat_keyword(s)
             }
-            _ => None,
+            _ => Ok(None),
         }
     }
 
-    fn eat_keyword(&mut self, kw: String) -> Option<Statement> {
-        self.consume(Token::Keyword(kw.to_string()));
-        match kw.as_str() {
+    fn eat_keyword(&mut self, kw: String) -> Result<Option<Statement>, ParseError> {
+        let first = self.current;
+        let stmt = match kw.as_str() {
             "return" => {
-                let e = self.eat_expr();
-                self.consume(Token::Semicolon);
-                Some(Statement::Return(Box::new(e)))
+                self.consume(Token::Keyword(kw.to_string()))?;
+                let e = self.eat_expr()?;
+                self.consume(Token::Semicolon)?;
+                self.record_node(first);
+                Statement::Return(Box::new(e))
             }
             "if" => {
-                self.consume(Token::LParen);
-                let e = self.eat_expr();
-                self.consume(Token::RParen);
-                self.consume(Token::LBrace);
-                let res = Some(Statement::If(Box::new(e), self.get_statements()));
-                self.consume(Token::RBrace);
-                res
+                self.consume(Token::Keyword(kw.to_string()))?;
+                self.consume(Token::LParen)?;
+                let e = self.eat_expr()?;
+                self.consume(Token::RParen)?;
+                self.record_node(first);
+                self.consume(Token::LBrace)?;
+                let body = self.get_statements()?;
+                self.consume(Token::RBrace)?;
+                Statement::If(Box::new(e), body)
             }
             "while" => {
-                self.consume(Token::LParen);
-                let cond = self.eat_expr();
-                self.consume(Token::RParen);
-                self.consume(Token::LBrace);
-                let res = Some(Statement::While(Box::new(cond), self.get_statements()));
-                self.consume(Token::RBrace);
-                res
+                self.consume(Token::Keyword(kw.to_string()))?;
+                self.consume(Token::LParen)?;
+                let cond = self.eat_expr()?;
+                self.consume(Token::RParen)?;
+                self.record_node(first);
+                self.consume(Token::LBrace)?;
+                let body = self.get_statements()?;
+                self.consume(Token::RBrace)?;
+                Statement::While(Box::new(cond), body)
             }
             "do" => {
-                self.consume(Token::LBrace);
-                let body = self.get_statements();
-                self.consume(Token::RBrace);
-                self.consume(Token::Keyword(String::from("while")));
-                self.consume(Token::LParen);
-                let cond = self.eat_expr();
-                self.consume(Token::RParen);
-                self.consume(Token::Semicolon);
-                Some(Statement::DoWhile(body, Box::new(cond)))
+                self.consume(Token::Keyword(kw.to_string()))?;
+                self.consume(Token::LBrace)?;
+                let body = self.get_statements()?;
+                self.consume(Token::RBrace)?;
+                // the condition node is the trailing `while (...);`
+                let cond_first = self.current;
+                self.consume(Token::Keyword(String::from("while")))?;
+                self.consume(Token::LParen)?;
+                let cond = self.eat_expr()?;
+                self.consume(Token::RParen)?;
+                self.consume(Token::Semicolon)?;
+                self.record_node(cond_first);
+                Statement::DoWhile(body, Box::new(cond))
             }
-            _ => None,
-        }
+            // break is a keyword without a statement, so it ends the
+            // statements like any token that cannot start one
+            _ => return Ok(None),
+        };
+        Ok(Some(stmt))
     }
 
-    fn eat_assignment(&mut self) -> Option<Statement> {
+    fn eat_assignment(&mut self) -> Result<Statement, ParseError> {
+        let first = self.current;
         let id = match self.peek() {
-            Some(Token::Id(id)) => {
-                id.clone()
-            }
-            e => {
-                 println!("got: {:?}", e);
-                panic!("Was not an id")
-            }
+            Some(Token::Id(id)) => id.clone(),
+            _ => return Err(self.error("an identifier")),
         };
 
-        self.consume(Token::Id(id.clone()));
-        self.consume(Token::Equals);
-        let e = self.eat_expr();
-        self.consume(Token::Semicolon);
+        self.consume(Token::Id(id.clone()))?;
+        self.consume(Token::Equals)?;
+        let e = self.eat_expr()?;
+        self.consume(Token::Semicolon)?;
+        self.record_node(first);
 
-        Some(Statement::Assignment(
-            Box::new(Expr::Id(id.clone())),
-            Box::new(e),
-        ))
+        Ok(Statement::Assignment(Box::new(Expr::Id(id.clone())), Box::new(e)))
     }
 
-    fn eat_expr(&mut self) -> Expr {
-        let mut left = self.left_exp();
+    fn eat_expr(&mut self) -> Result<Expr, ParseError> {
+        let mut left = self.left_exp()?;
 
         while let Some(op) = self.peek() {
             match op.clone() {
@@ -117,43 +222,53 @@ impl Parser {
                 | Token::Minus
                 | Token::LessThan
                 | Token::Mult) => {
-                    self.consume(t_op.clone());
-                    let right = self.left_exp();
+                    self.consume(t_op.clone())?;
+                    let right = self.left_exp()?;
                     left = Expr::BinOp(Box::new(left), token_to_operator(&t_op), Box::new(right));
                 }
                 _ => break, // No more binary operators, break out of loop
             }
         }
 
-        left
+        Ok(left)
     }
-    fn left_exp(&mut self) -> Expr {
+    fn left_exp(&mut self) -> Result<Expr, ParseError> {
         let t = self.peek();
 
         match t {
             Some(Token::Int(n)) => {
                 // Consume the integer token
-                let n = n.clone(); // to not borrow too much
-                self.consume(Token::Int(n));
-                Expr::Int(n)
+                let n = *n; // to not borrow too much
+                self.consume(Token::Int(n))?;
+                Ok(Expr::Int(n))
             }
             Some(Token::Id(id)) => {
                 let id = id.clone();
-                self.consume(Token::Id(id.clone()));
-                Expr::Id(id)
+                self.consume(Token::Id(id.clone()))?;
+                Ok(Expr::Id(id))
             }
-            _ => panic!("Unexpected token in left expression"),
+            // Negative literal, as printed for folded constants
+            Some(Token::Minus) => {
+                self.consume(Token::Minus)?;
+                match self.peek() {
+                    Some(Token::Int(n)) => {
+                        let n = *n;
+                        self.consume(Token::Int(n))?;
+                        Ok(Expr::Int(-n))
+                    }
+                    _ => Err(self.error("a literal after unary minus")),
+                }
+            }
+            _ => Err(self.error("an expression")),
         }
     }
 
-    fn consume(&mut self, tk: Token) {
+    fn consume(&mut self, tk: Token) -> Result<(), ParseError> {
         if Some(&tk) == self.peek() {
             self.current += 1;
+            Ok(())
         } else {
-            for (idx, t) in self.tokens.iter().enumerate() {
-                println!("Token {idx}: {:?}", t);
-            }
-            panic!("Could not eat {:?}", tk)
+            Err(self.error(&tk.to_string()))
         }
     }
 }
@@ -1,6 +1,5 @@
 use crate::ast::*;
 use crate::liveness::*;
-use std::fmt::format;
 use std::fmt::Display;
 
 impl Program {
@@ -98,6 +97,9 @@ impl Display for ControlFlowGraph {
                 (_,NodeKind::Condition(e)) => {
                     res.push_str(&format!("{idx}: if {}\n", e));
                 }
+                (_,NodeKind::Empty) => {
+                    res.push_str(&format!("{idx}: empty\n"));
+                }
             }
             res.push_str(
                 &format!("\tdef: {:?}, use: {:?}\n",
@@ -144,6 +146,12 @@ fn pretty_print_operator(op: &Operator) -> String {
     }
 }
 
+impl Display for Operator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", pretty_print_operator(self))
+    }
+}
+
 impl Display for NodeKind {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mut res = String::new();
@@ -157,6 +165,9 @@ impl Display for NodeKind {
             NodeKind::Condition(e) => {
                 res.push_str(&format!("if {}", e));
             }
+            NodeKind::Empty => {
+                res.push_str("empty");
+            }
         }
         write!(f, "{}", res)
     }
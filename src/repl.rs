@@ -0,0 +1,196 @@
+use crate::ast::*;
+use crate::cli::{analyzed_cfg, live_table, range_line, Solver};
+use crate::diagnostics::{render, Diagnostic, SourceFile};
+use crate::lexer::Lexer;
+use crate::liveness::ControlFlowGraph;
+use crate::parser::Parser;
+use std::io::{BufRead, Write};
+
+pub const HELP: &str = "\
+Type statements to add them to the program, they may span several lines.
+
+commands:
+  :program      the program so far
+  :cfg          its control flow graph
+  :live         live-in and live-out of every node
+  :dot          the CFG in DOT, edges labelled with live variables
+  :range <x>    the edges x is live on
+  :undo         take back the last input (or drop an unfinished one)
+  :load <file>  replace the program with the file's
+  :clear        start over
+  :help         this text
+  :quit         leave, as does end of input
+";
+
+// An incremental session: every complete input is parsed on its own and its
+// statements appended to the program
+pub struct Repl {
+    stmts: Vec<Statement>,
+    // the program before each input, for :undo
+    history: Vec<Vec<Statement>>,
+    // lines of a statement that is not complete yet
+    pending: String,
+    solver: Solver,
+    done: bool,
+}
+
+impl Repl {
+    pub fn new() -> Self {
+        Repl {
+            stmts: Vec::new(),
+            history: Vec::new(),
+            pending: String::new(),
+            solver: Solver::Fast,
+            done: false,
+        }
+    }
+
+    pub fn with_solver(mut self, solver: Solver) -> Self {
+        self.solver = solver;
+        self
+    }
+
+    pub fn get_program(&self) -> Program {
+        Program::new(self.stmts.clone())
+    }
+    // Whether :quit was given
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+    // Whether the last lines did not finish a statement
+    pub fn is_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+    pub fn get_prompt(&self) -> &'static str {
+        match self.is_pending() {
+            true => "... ",
+            false => "> ",
+        }
+    }
+
+    // Handles one line of input and returns what to print
+    pub fn handle_line(&mut self, line: &str) -> String {
+        let trimmed = line.trim();
+        if let Some(cmd) = trimmed.strip_prefix(':') {
+            return self.command(cmd);
+        }
+        if trimmed.is_empty() && !self.is_pending() {
+            return String::new();
+        }
+        self.pending.push_str(line);
+        self.pending.push('\n');
+        self.add_pending()
+    }
+
+    fn add_pending(&mut self) -> String {
+        let src = self.pending.clone();
+        let file = SourceFile::new("<input>", &src);
+        let tokens = match Lexer::new(&src).tokenize_with_spans() {
+            Ok(t) => t,
+            Err(e) => {
+                self.pending.clear();
+                return render(&Diagnostic::from_lex_error(&e), &file, false);
+            }
+        };
+        let n = tokens.len();
+        match Parser::with_spans(tokens).try_parse() {
+            Ok(p) => {
+                self.pending.clear();
+                self.history.push(self.stmts.clone());
+                self.stmts.extend(p.stmts);
+                String::new()
+            }
+            // wait for the rest of the statement
+            Err(e) if e.get_token_idx() >= n => String::new(),
+            Err(e) => {
+                self.pending.clear();
+                render(&Diagnostic::from_parse_error(&e), &file, false)
+            }
+        }
+    }
+
+    fn command(&mut self, cmd: &str) -> String {
+        let (name, arg) = match cmd.split_once(char::is_whitespace) {
+            Some((name, arg)) => (name, arg.trim()),
+            None => (cmd, ""),
+        };
+        let p = self.get_program();
+        match (name, arg) {
+            // without the header, so it can be pasted back
+            ("program", "") => p.stmts.iter().map(Program::pretty_print_statement).collect(),
+            ("cfg", "") => ControlFlowGraph::from(&p).to_string(),
+            ("live", "") => live_table(&analyzed_cfg(&p, self.solver)),
+            ("dot", "") => analyzed_cfg(&p, self.solver).generate_dot() + "\n",
+            ("range", x) if !x.is_empty() && !x.contains(char::is_whitespace) => {
+                range_line(&analyzed_cfg(&p, self.solver), x)
+            }
+            ("undo", "") if self.is_pending() => {
+                self.pending.clear();
+                String::from("dropped the unfinished input\n")
+            }
+            ("undo", "") => match self.history.pop() {
+                Some(stmts) => {
+                    self.stmts = stmts;
+                    String::new()
+                }
+                None => String::from("nothing to undo\n"),
+            },
+            ("load", path) if !path.is_empty() => self.load(path),
+            ("clear", "") => {
+                self.history.push(std::mem::take(&mut self.stmts));
+                self.pending.clear();
+                String::new()
+            }
+            ("help", "") => HELP.to_string(),
+            ("quit" | "q", "") => {
+                self.done = true;
+                String::new()
+            }
+            ("range", _) => String::from("usage: :range <x>\n"),
+            ("load", _) => String::from("usage: :load <file>\n"),
+            _ => format!("unknown command `:{cmd}`, see :help\n"),
+        }
+    }
+
+    fn load(&mut self, path: &str) -> String {
+        let src = match std::fs::read_to_string(path) {
+            Ok(s) => s,
+            Err(e) => return format!("cannot read {path}: {e}\n"),
+        };
+        let file = SourceFile::new(path, &src);
+        let tokens = match Lexer::new(&src).tokenize_with_spans() {
+            Ok(t) => t,
+            Err(e) => return render(&Diagnostic::from_lex_error(&e), &file, false),
+        };
+        match Parser::with_spans(tokens).try_parse() {
+            Ok(p) => {
+                self.history.push(std::mem::replace(&mut self.stmts, p.stmts));
+                self.pending.clear();
+                String::new()
+            }
+            Err(e) => render(&Diagnostic::from_parse_error(&e), &file, false),
+        }
+    }
+
+    // Reads lines until :quit or the end of the input, prompting before each
+    pub fn run(&mut self, input: &mut dyn BufRead, out: &mut dyn Write) -> std::io::Result<()> {
+        let mut line = String::new();
+        while !self.done {
+            write!(out, "{}", self.get_prompt())?;
+            out.flush()?;
+            line.clear();
+            if input.read_line(&mut line)? == 0 {
+                writeln!(out)?;
+                break;
+            }
+            write!(out, "{}", self.handle_line(line.trim_end_matches(['\n', '\r'])))?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for Repl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,257 @@
+use crate::ast::*;
+use crate::liveness::NodeKind;
+use std::collections::{HashMap, HashSet};
+
+// Edits to a Program, addressed by the index of the CFG node each statement
+// (or condition) is flattened to by ControlFlowGraph::from. This lets passes
+// run their analysis on the CFG and still produce a structured Program.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProgramEdits {
+    replaced: HashMap<usize, NodeKind>,
+    removed: HashSet<usize>,
+    before: HashMap<usize, Vec<Statement>>,
+    after: HashMap<usize, Vec<Statement>>,
+    body_start: HashMap<usize, Vec<Statement>>,
+    preheaders: HashMap<usize, Vec<Statement>>,
+}
+
+impl ProgramEdits {
+    pub fn new() -> Self {
+        ProgramEdits::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.replaced.is_empty()
+            && self.removed.is_empty()
+            && self.before.is_empty()
+            && self.after.is_empty()
+            && self.body_start.is_empty()
+            && self.preheaders.is_empty()
+    }
+
+    // Replace the statement of an assignment/return node, or the expression
+    // of a condition node
+    pub fn replace(&mut self, idx: usize, kind: NodeKind) {
+        self.replaced.insert(idx, kind);
+    }
+
+    // Remove an assignment or return node
+    pub fn remove(&mut self, idx: usize) {
+        self.removed.insert(idx);
+    }
+
+    // Run `stmt` right before node `idx`, on every edge into it. For the
+    // condition of a while loop this is both before the loop and at the end
+    // of its body.
+    pub fn insert_before(&mut self, idx: usize, stmt: Statement) {
+        self.before.entry(idx).or_default().push(stmt);
+    }
+
+    // Run `stmt` right after the statement node `idx` belongs to. For an
+    // assignment this is the edge to its successor, for a loop condition it is
+    // the edge leaving the loop.
+    pub fn insert_after(&mut self, idx: usize, stmt: Statement) {
+        self.after.entry(idx).or_default().push(stmt);
+    }
+
+    // Run `stmt` first in the body of the if/while/do-while with condition
+    // `cond`. For if and while this is the edge taken when the condition holds.
+    pub fn insert_body_start(&mut self, cond: usize, stmt: Statement) {
+        self.body_start.entry(cond).or_default().push(stmt);
+    }
+
+    // Run `stmt` once before entering the loop whose header is `header`.
+    // The header of a while loop is its condition, the header of a do-while
+    // loop is the first node of its body.
+    pub fn insert_preheader(&mut self, header: usize, stmt: Statement) {
+        self.preheaders.entry(header).or_default().push(stmt);
+    }
+
+    pub fn apply(&self, p: &Program) -> Program {
+        let mut idx = 0;
+        let mut emitted = HashSet::new();
+        Program::new(self.apply_statements(&p.stmts, &mut idx, &mut emitted))
+    }
+
+    // Walks the statements in the same order as flatten_statements.
+    // `emitted` holds the headers whose preheader is already out: a do-while
+    // shares its header with a loop its body starts with, and the preheader
+    // goes before the outermost of them.
+    fn apply_statements(&self, stmts: &[Statement], idx: &mut usize, emitted: &mut HashSet<usize>) -> Vec<Statement> {
+        let mut res = Vec::new();
+        for stmt in stmts {
+            match stmt {
+                Statement::Assignment(_, _) | Statement::Return(_) => {
+                    let i = *idx;
+                    *idx += 1;
+                    res.extend(self.get_before(i));
+                    if !self.removed.contains(&i) {
+                        match self.replaced.get(&i) {
+                            Some(kind) => res.push(node_kind_to_statement(kind)),
+                            None => res.push(stmt.clone()),
+                        }
+                    }
+                    res.extend(self.get_after(i));
+                }
+                Statement::If(cond, body) => {
+                    let i = *idx;
+                    *idx += 1;
+                    res.extend(self.get_before(i));
+                    let cond = self.get_cond(i, cond);
+                    let mut new_body = self.get_body_start(i);
+                    new_body.extend(self.apply_statements(body, idx, emitted));
+                    res.push(Statement::If(cond, new_body));
+                    res.extend(self.get_after(i));
+                }
+                Statement::While(cond, body) => {
+                    let i = *idx;
+                    *idx += 1;
+                    res.extend(self.get_preheader(i, emitted));
+                    res.extend(self.get_before(i));
+                    let cond = self.get_cond(i, cond);
+                    let mut new_body = self.get_body_start(i);
+                    new_body.extend(self.apply_statements(body, idx, emitted));
+                    new_body.extend(self.get_before(i));
+                    res.push(Statement::While(cond, new_body));
+                    res.extend(self.get_after(i));
+                }
+                Statement::DoWhile(body, cond) => {
+                    res.extend(self.get_preheader(*idx, emitted));
+                    let mut new_body = self.apply_statements(body, idx, emitted);
+                    let i = *idx;
+                    *idx += 1;
+                    new_body.extend(self.get_before(i));
+                    let cond = self.get_cond(i, cond);
+                    let mut body = self.get_body_start(i);
+                    body.extend(new_body);
+                    res.push(Statement::DoWhile(body, cond));
+                    res.extend(self.get_after(i));
+                }
+            }
+        }
+        res
+    }
+
+    fn get_before(&self, idx: usize) -> Vec<Statement> {
+        self.before.get(&idx).cloned().unwrap_or_default()
+    }
+
+    fn get_after(&self, idx: usize) -> Vec<Statement> {
+        self.after.get(&idx).cloned().unwrap_or_default()
+    }
+
+    fn get_body_start(&self, cond: usize) -> Vec<Statement> {
+        self.body_start.get(&cond).cloned().unwrap_or_default()
+    }
+
+    fn get_preheader(&self, header: usize, emitted: &mut HashSet<usize>) -> Vec<Statement> {
+        if !emitted.insert(header) {
+            return Vec::new();
+        }
+        self.preheaders.get(&header).cloned().unwrap_or_default()
+    }
+
+    fn get_cond(&self, idx: usize, cond: &Expr) -> Box<Expr> {
+        match self.replaced.get(&idx) {
+            Some(NodeKind::Condition(e)) => e.clone(),
+            _ => Box::new(cond.clone()),
+        }
+    }
+}
+
+pub fn node_kind_to_statement(kind: &NodeKind) -> Statement {
+    match kind {
+        NodeKind::Assignment(lvl, e) => Statement::Assignment(lvl.clone(), e.clone()),
+        NodeKind::Return(e) => Statement::Return(e.clone()),
+        NodeKind::Condition(_) => panic!("A condition is not a statement on its own"),
+        NodeKind::Empty => panic!("Empty nodes do not come from statements"),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BranchKind {
+    If,
+    While,
+    DoWhile,
+}
+
+// The kind of statement each condition node of ControlFlowGraph::from(p)
+// belongs to, which the CFG itself does not record
+pub fn get_branch_kinds(p: &Program) -> HashMap<usize, BranchKind> {
+    fn walk(stmts: &[Statement], idx: &mut usize, kinds: &mut HashMap<usize, BranchKind>) {
+        for stmt in stmts {
+            match stmt {
+                Statement::Assignment(_, _) | Statement::Return(_) => *idx += 1,
+                Statement::If(_, body) => {
+                    kinds.insert(*idx, BranchKind::If);
+                    *idx += 1;
+                    walk(body, idx, kinds);
+                }
+                Statement::While(_, body) => {
+                    kinds.insert(*idx, BranchKind::While);
+                    *idx += 1;
+                    walk(body, idx, kinds);
+                }
+                Statement::DoWhile(body, _) => {
+                    walk(body, idx, kinds);
+                    kinds.insert(*idx, BranchKind::DoWhile);
+                    *idx += 1;
+                }
+            }
+        }
+    }
+    let mut kinds = HashMap::new();
+    walk(&p.stmts, &mut 0, &mut kinds);
+    kinds
+}
+
+// Every variable named in the program
+pub fn get_program_vars(p: &Program) -> HashSet<String> {
+    fn walk(stmts: &[Statement], vars: &mut HashSet<String>) {
+        for stmt in stmts {
+            match stmt {
+                Statement::Assignment(lvl, e) => {
+                    vars.extend(lvl.clone().iter());
+                    vars.extend(e.clone().iter());
+                }
+                Statement::Return(e) => vars.extend(e.clone().iter()),
+                Statement::If(c, body) | Statement::While(c, body) | Statement::DoWhile(body, c) => {
+                    vars.extend(c.clone().iter());
+                    walk(body, vars);
+                }
+            }
+        }
+    }
+    let mut vars = HashSet::new();
+    walk(&p.stmts, &mut vars);
+    vars
+}
+
+// Hands out variable names `<prefix>0`, `<prefix>1`, ... that do not clash
+// with the program's own. The prefix should be letters only, so the lexer
+// reads the names back as identifiers.
+pub struct NameGenerator {
+    prefix: String,
+    used: HashSet<String>,
+    next: usize,
+}
+
+impl NameGenerator {
+    pub fn new(prefix: &str, p: &Program) -> Self {
+        NameGenerator {
+            prefix: prefix.to_string(),
+            used: get_program_vars(p),
+            next: 0,
+        }
+    }
+
+    pub fn fresh(&mut self) -> String {
+        loop {
+            let name = format!("{}{}", self.prefix, self.next);
+            self.next += 1;
+            if self.used.insert(name.clone()) {
+                return name;
+            }
+        }
+    }
+}
@@ -0,0 +1,307 @@
+use crate::ast::*;
+use crate::dataflow::{DataflowProblem, Direction, Meet};
+use crate::dominance::DominatorTree;
+use crate::liveness::{ControlFlowGraph, NodeKind};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Display;
+
+// Name of version `version` of `var`. Version 0 is the value the variable
+// has when the program starts. Identifiers cannot contain `_`, so these never
+// clash with the program's own names.
+pub fn ssa_name(var: &str, version: usize) -> String {
+    format!("{var}_{version}")
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Phi {
+    var: String,
+    dest: String,
+    args: Vec<(Option<usize>, String)>,
+}
+
+impl Phi {
+    // The program variable this phi merges versions of
+    pub fn get_var(&self) -> &String {
+        &self.var
+    }
+    pub fn get_dest(&self) -> &String {
+        &self.dest
+    }
+    // One argument per incoming edge, by predecessor. None is the edge from
+    // the program entry into node 0.
+    pub fn get_args(&self) -> &Vec<(Option<usize>, String)> {
+        &self.args
+    }
+    pub fn get_arg(&self, pred: Option<usize>) -> Option<&String> {
+        self.args.iter().find(|(p, _)| *p == pred).map(|(_, a)| a)
+    }
+    pub(crate) fn set_arg(&mut self, pred: Option<usize>, name: String) {
+        match self.args.iter_mut().find(|(p, _)| *p == pred) {
+            Some(arg) => arg.1 = name,
+            None => {
+                self.args.push((pred, name));
+                self.args.sort();
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SsaNode {
+    phis: Vec<Phi>,
+    kind: NodeKind,
+    preds: Vec<usize>,
+    succs: Vec<usize>,
+    reachable: bool,
+}
+
+impl SsaNode {
+    // Phis run at the start of the node, all at once
+    pub fn get_phis(&self) -> &Vec<Phi> {
+        &self.phis
+    }
+    // The statement or condition with every variable replaced by its version
+    pub fn get_node_kind(&self) -> &NodeKind {
+        &self.kind
+    }
+    pub fn get_preds(&self) -> &Vec<usize> {
+        &self.preds
+    }
+    pub fn get_succs(&self) -> &Vec<usize> {
+        &self.succs
+    }
+    // Unreachable nodes are kept as they were, without versions
+    pub fn is_reachable(&self) -> bool {
+        self.reachable
+    }
+    pub(crate) fn get_phis_mut(&mut self) -> &mut Vec<Phi> {
+        &mut self.phis
+    }
+    pub(crate) fn set_node_kind(&mut self, kind: NodeKind) {
+        self.kind = kind;
+    }
+}
+
+// A CFG in static single assignment form: the same nodes and edges, with
+// every definition creating a new version of its variable and phis merging
+// versions where control flow joins.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SsaForm {
+    nodes: Vec<SsaNode>,
+    dom: DominatorTree,
+    entry_names: BTreeSet<String>,
+}
+
+impl SsaForm {
+    pub fn get_nodes(&self) -> &Vec<SsaNode> {
+        &self.nodes
+    }
+    pub fn get_node(&self, n: usize) -> &SsaNode {
+        &self.nodes[n]
+    }
+    pub fn get_dominator_tree(&self) -> &DominatorTree {
+        &self.dom
+    }
+    // Versions 0 read somewhere, the values the program starts with
+    pub fn get_entry_names(&self) -> &BTreeSet<String> {
+        &self.entry_names
+    }
+    // The node defining a version, by a phi or an assignment
+    pub fn get_def(&self, name: &str) -> Option<usize> {
+        self.nodes.iter().position(|n| {
+            n.phis.iter().any(|phi| phi.dest == name)
+                || matches!(&n.kind, NodeKind::Assignment(lvl, _) if **lvl == Expr::Id(name.to_string()))
+        })
+    }
+    pub(crate) fn get_node_mut(&mut self, n: usize) -> &mut SsaNode {
+        &mut self.nodes[n]
+    }
+}
+
+impl Display for SsaForm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (idx, n) in self.nodes.iter().enumerate() {
+            for phi in n.phis.iter() {
+                let args: Vec<String> = phi
+                    .args
+                    .iter()
+                    .map(|(p, a)| match p {
+                        Some(p) => format!("{a} [{p}]"),
+                        None => format!("{a} [entry]"),
+                    })
+                    .collect();
+                writeln!(f, "{idx}: {} = phi({});", phi.dest, args.join(", "))?;
+            }
+            writeln!(f, "{idx}: {}", n.kind)?;
+        }
+        Ok(())
+    }
+}
+
+impl ControlFlowGraph {
+    // Pruned SSA: phis are placed on the iterated dominance frontiers of each
+    // variable's definitions (Cytron et al.), but only where the variable is
+    // live, then variables are renamed in a preorder walk of the dominator
+    // tree.
+    pub fn ssa_form(&self) -> SsaForm {
+        let nodes = self.get_nodes();
+        let dom = self.dominator_tree();
+
+        let live_in = DataflowProblem {
+            direction: Direction::Backward,
+            meet: Meet::Union,
+            gen: nodes.iter().map(|n| n.get_uses().iter().cloned().collect()).collect(),
+            kill: nodes.iter().map(|n| n.get_defs().iter().cloned().collect()).collect(),
+            boundary: BTreeSet::new(),
+            universe: BTreeSet::new(),
+        }
+        .solve(self);
+
+        let mut def_sites: BTreeMap<&String, Vec<usize>> = BTreeMap::new();
+        for (idx, n) in nodes.iter().enumerate() {
+            if dom.is_reachable(idx) {
+                for d in n.get_defs() {
+                    def_sites.entry(d).or_default().push(idx);
+                }
+            }
+        }
+
+        let mut ssa_nodes: Vec<SsaNode> = nodes
+            .iter()
+            .enumerate()
+            .map(|(idx, n)| {
+                let preds: Vec<usize> = n.get_preds().iter().filter(|p| dom.is_reachable(**p)).cloned().collect();
+                SsaNode {
+                    phis: Vec::new(),
+                    kind: n.get_node_kind().clone(),
+                    preds,
+                    succs: n.get_succs().iter().cloned().collect(),
+                    reachable: dom.is_reachable(idx),
+                }
+            })
+            .collect();
+
+        // Vars in name order, so each node's phis are sorted by variable
+        for (var, sites) in def_sites.iter() {
+            let mut has_phi: BTreeSet<usize> = BTreeSet::new();
+            let mut work = sites.clone();
+            while let Some(x) = work.pop() {
+                for y in dom.get_frontier(x).iter().cloned() {
+                    if has_phi.contains(&y) || !live_in.get_in(y).contains(*var) {
+                        continue;
+                    }
+                    has_phi.insert(y);
+                    ssa_nodes[y].phis.push(Phi {
+                        var: var.to_string(),
+                        dest: String::new(),
+                        args: Vec::new(),
+                    });
+                    if !sites.contains(&y) {
+                        work.push(y);
+                    }
+                }
+            }
+        }
+        for node in ssa_nodes.iter_mut() {
+            node.phis.sort_by(|a, b| a.var.cmp(&b.var));
+        }
+
+        let mut renamer = Renamer {
+            nodes: ssa_nodes,
+            stacks: HashMap::new(),
+            versions: HashMap::new(),
+            entry_names: BTreeSet::new(),
+        };
+        for root in dom.get_roots().iter().filter(|r| dom.is_reachable(**r)) {
+            // the entry edge into the root brings in the starting values
+            for i in 0..renamer.nodes[*root].phis.len() {
+                let name = renamer.current(&renamer.nodes[*root].phis[i].var.clone());
+                renamer.nodes[*root].phis[i].set_arg(None, name);
+            }
+            renamer.rename(*root, &dom);
+        }
+
+        SsaForm {
+            nodes: renamer.nodes,
+            dom,
+            entry_names: renamer.entry_names,
+        }
+    }
+}
+
+struct Renamer {
+    nodes: Vec<SsaNode>,
+    stacks: HashMap<String, Vec<String>>,
+    versions: HashMap<String, usize>,
+    entry_names: BTreeSet<String>,
+}
+
+impl Renamer {
+    fn current(&mut self, var: &str) -> String {
+        match self.stacks.get(var).and_then(|s| s.last()) {
+            Some(name) => name.clone(),
+            None => {
+                let name = ssa_name(var, 0);
+                self.entry_names.insert(name.clone());
+                name
+            }
+        }
+    }
+
+    fn define(&mut self, var: &str) -> String {
+        let v = self.versions.entry(var.to_string()).or_insert(0);
+        *v += 1;
+        let name = ssa_name(var, *v);
+        self.stacks.entry(var.to_string()).or_default().push(name.clone());
+        name
+    }
+
+    fn rename_expr(&mut self, e: &Expr) -> Expr {
+        match e {
+            Expr::Id(var) => Expr::Id(self.current(var)),
+            Expr::Int(n) => Expr::Int(*n),
+            Expr::BinOp(l, op, r) => Expr::BinOp(Box::new(self.rename_expr(l)), *op, Box::new(self.rename_expr(r))),
+        }
+    }
+
+    fn rename(&mut self, n: usize, dom: &DominatorTree) {
+        let mut defined: Vec<String> = Vec::new();
+
+        for i in 0..self.nodes[n].phis.len() {
+            let var = self.nodes[n].phis[i].var.clone();
+            self.nodes[n].phis[i].dest = self.define(&var);
+            defined.push(var);
+        }
+
+        let kind = self.nodes[n].kind.clone();
+        if let Some(e) = kind.get_expr() {
+            let e = self.rename_expr(e);
+            self.nodes[n].kind = match &kind {
+                NodeKind::Assignment(lvl, _) => match lvl.as_ref() {
+                    Expr::Id(var) => {
+                        let name = self.define(var);
+                        defined.push(var.clone());
+                        NodeKind::Assignment(Box::new(Expr::Id(name)), Box::new(e))
+                    }
+                    _ => kind.with_expr(e),
+                },
+                _ => kind.with_expr(e),
+            };
+        }
+
+        for s in self.nodes[n].succs.clone() {
+            for i in 0..self.nodes[s].phis.len() {
+                let name = self.current(&self.nodes[s].phis[i].var.clone());
+                self.nodes[s].phis[i].set_arg(Some(n), name);
+            }
+        }
+
+        for c in dom.get_children(n) {
+            self.rename(*c, dom);
+        }
+
+        for var in defined {
+            self.stacks.get_mut(&var).unwrap().pop();
+        }
+    }
+}
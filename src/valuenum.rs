@@ -0,0 +1,266 @@
+use crate::ast::*;
+use crate::liveness::{ControlFlowGraph, NodeKind};
+use crate::rewrite::ProgramEdits;
+use std::collections::HashMap;
+use std::fmt::Display;
+
+impl ControlFlowGraph {
+    // Maximal straight-line runs of nodes: only the first node may have
+    // several (or no) predecessors, and only the last several successors.
+    // Ordered by their first node.
+    pub fn basic_blocks(&self) -> Vec<Vec<usize>> {
+        let nodes = self.get_nodes();
+        // A branch to the exit counts as a successor too
+        let falls_through = |idx: usize| nodes[idx].get_succs().len() == 1 && !nodes[idx].is_exit();
+        let is_leader = |idx: usize| {
+            let preds = nodes[idx].get_preds();
+            idx == 0 || preds.len() != 1 || preds.iter().any(|p| !falls_through(*p))
+        };
+
+        let mut blocks = Vec::new();
+        for leader in (0..nodes.len()).filter(|idx| is_leader(*idx)) {
+            let mut block = vec![leader];
+            let mut cur = leader;
+            while falls_through(cur) {
+                let next = *nodes[cur].get_succs().iter().next().unwrap();
+                if is_leader(next) {
+                    break;
+                }
+                block.push(next);
+                cur = next;
+            }
+            blocks.push(block);
+        }
+        blocks
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Value {
+    Const(i32),
+    // What the variable held when the block was entered
+    Initial(String),
+    // Operands are value numbers, ordered for commutative operators
+    Op(Operator, usize, usize),
+}
+
+// The value numbers of one basic block after numbering all of it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValueTable {
+    block: Vec<usize>,
+    values: Vec<Value>,
+    vars: Vec<(String, usize)>,
+}
+
+impl ValueTable {
+    pub fn get_block(&self) -> &Vec<usize> {
+        &self.block
+    }
+    // Value number n is get_values()[n]
+    pub fn get_values(&self) -> &Vec<Value> {
+        &self.values
+    }
+    // The value number each variable holds at the end of the block
+    pub fn get_vars(&self) -> &Vec<(String, usize)> {
+        &self.vars
+    }
+    pub fn get_value_number(&self, var: &str) -> Option<usize> {
+        self.vars.iter().find(|(v, _)| v == var).map(|(_, vn)| *vn)
+    }
+
+    fn holder(&self, vn: usize) -> Option<&String> {
+        self.vars.iter().find(|(_, n)| *n == vn).map(|(v, _)| v)
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Const(n) => write!(f, "{n}"),
+            // identifiers cannot contain @, so this is no variable like x0
+            Value::Initial(var) => write!(f, "{var}@entry"),
+            Value::Op(op, l, r) => write!(f, "#{l} {op} #{r}"),
+        }
+    }
+}
+
+// One row per value number, with the variables holding it at the end
+impl Display for ValueTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let block: Vec<String> = self.block.iter().map(|n| n.to_string()).collect();
+        writeln!(f, "block {}", block.join(" "))?;
+        writeln!(f, "vn\tvalue\tvars")?;
+        for (vn, value) in self.values.iter().enumerate() {
+            let vars: Vec<&str> = self
+                .vars
+                .iter()
+                .filter(|(_, n)| *n == vn)
+                .map(|(v, _)| v.as_str())
+                .collect();
+            writeln!(f, "#{vn}\t{value}\t{}", vars.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+// Builds the table of one block while its nodes are rewritten
+struct Numbering {
+    table: ValueTable,
+    lookup: HashMap<Value, usize>,
+}
+
+impl Numbering {
+    fn new(block: &[usize]) -> Self {
+        Numbering {
+            table: ValueTable {
+                block: block.to_vec(),
+                values: Vec::new(),
+                vars: Vec::new(),
+            },
+            lookup: HashMap::new(),
+        }
+    }
+
+    fn number(&mut self, v: Value) -> usize {
+        if let Some(vn) = self.lookup.get(&v) {
+            return *vn;
+        }
+        let vn = self.table.values.len();
+        self.table.values.push(v.clone());
+        self.lookup.insert(v, vn);
+        vn
+    }
+
+    fn var(&mut self, var: &str) -> usize {
+        match self.table.get_value_number(var) {
+            Some(vn) => vn,
+            None => {
+                let vn = self.number(Value::Initial(var.to_string()));
+                self.table.vars.push((var.to_string(), vn));
+                vn
+            }
+        }
+    }
+
+    fn constant(&self, vn: usize) -> Option<i32> {
+        match self.table.values[vn] {
+            Value::Const(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    fn expr(&mut self, e: &Expr) -> usize {
+        match e {
+            Expr::Id(var) => self.var(var),
+            Expr::Int(n) => self.number(Value::Const(*n)),
+            Expr::BinOp(l, op, r) => {
+                let l = self.expr(l);
+                let r = self.expr(r);
+                match (op, self.constant(l), self.constant(r)) {
+                    (_, Some(a), Some(b)) if op.apply(a, b).is_some() => {
+                        self.number(Value::Const(op.apply(a, b).unwrap()))
+                    }
+                    (Operator::Plus, _, Some(0))
+                    | (Operator::Minus, _, Some(0))
+                    | (Operator::Mult, _, Some(1))
+                    | (Operator::Div, _, Some(1)) => l,
+                    (Operator::Plus, Some(0), _) | (Operator::Mult, Some(1), _) => r,
+                    _ if op.is_commutative() && r < l => self.number(Value::Op(*op, r, l)),
+                    _ => self.number(Value::Op(*op, l, r)),
+                }
+            }
+        }
+    }
+
+    // The expression with every part whose value is already held by a
+    // variable, or is a constant, replaced by it
+    fn rewrite(&mut self, e: &Expr) -> Expr {
+        let vn = self.expr(e);
+        // -2147483648 has no literal, so i32::MIN keeps the expression
+        // computing it
+        if let Some(n) = self.constant(vn).filter(|n| *n != i32::MIN) {
+            return Expr::Int(n);
+        }
+        if let Some(var) = self.table.holder(vn) {
+            return Expr::Id(var.clone());
+        }
+        match e {
+            // an identity, only one side is needed
+            Expr::BinOp(l, _, _) if self.expr(l) == vn => self.rewrite(l),
+            Expr::BinOp(_, _, r) if self.expr(r) == vn => self.rewrite(r),
+            Expr::BinOp(l, op, r) => Expr::BinOp(Box::new(self.rewrite(l)), *op, Box::new(self.rewrite(r))),
+            _ => e.clone(),
+        }
+    }
+
+    fn assign(&mut self, var: &str, vn: usize) {
+        self.table.vars.retain(|(v, _)| v != var);
+        self.table.vars.push((var.to_string(), vn));
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValueNumberingResult {
+    program: Program,
+    tables: Vec<ValueTable>,
+    rewritten: Vec<usize>,
+}
+
+impl ValueNumberingResult {
+    pub fn get_program(&self) -> &Program {
+        &self.program
+    }
+    // One table per basic block, in block order
+    pub fn get_tables(&self) -> &Vec<ValueTable> {
+        &self.tables
+    }
+    // CFG nodes whose statement or condition changed
+    pub fn get_rewritten(&self) -> &Vec<usize> {
+        &self.rewritten
+    }
+}
+
+// Local value numbering.
+//
+// Each basic block is numbered on its own, starting from nothing known.
+// Expressions with the same operator and operand numbers get the same
+// number, with the operands of `+` and `*` in either order. Constants are
+// folded and `x + 0`, `0 + x`, `x - 0`, `x * 1`, `1 * x` and `x / 1` get the
+// number of `x`. Every (sub)expression whose number is a constant or already
+// held by a variable is replaced by it, and each variable use by the first
+// variable still holding its value.
+pub fn number_values(p: &Program) -> ValueNumberingResult {
+    let cfg = ControlFlowGraph::from(p);
+    let mut edits = ProgramEdits::new();
+    let mut tables = Vec::new();
+    let mut rewritten = Vec::new();
+
+    for block in cfg.basic_blocks() {
+        let mut numbering = Numbering::new(&block);
+        for idx in block.iter().cloned() {
+            let kind = cfg.get_node(idx).get_node_kind();
+            let Some(e) = kind.get_expr() else {
+                continue;
+            };
+            let new = numbering.rewrite(e);
+            if let NodeKind::Assignment(lvl, _) = kind {
+                if let Expr::Id(var) = lvl.as_ref() {
+                    let vn = numbering.expr(e);
+                    numbering.assign(var, vn);
+                }
+            }
+            let new_kind = kind.with_expr(new);
+            if new_kind != *kind {
+                edits.replace(idx, new_kind);
+                rewritten.push(idx);
+            }
+        }
+        tables.push(numbering.table);
+    }
+
+    ValueNumberingResult {
+        program: edits.apply(p),
+        tables,
+        rewritten,
+    }
+}
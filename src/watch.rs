@@ -0,0 +1,80 @@
+use crate::cli::{render, CliError, Options};
+use crate::diagnostics::{check_source, render_all, Severity, SourceFile};
+use std::io::Write;
+use std::time::Duration;
+
+// Reruns a command whenever its input file changes. Polls the contents
+// rather than the modification time, which is too coarse on some systems.
+pub struct Watcher {
+    opts: Options,
+    path: String,
+    interval: Duration,
+    // the contents last seen, or why the file could not be read
+    last: Option<Result<String, String>>,
+}
+
+impl Watcher {
+    // The options of a watch command line, with an input file
+    pub fn new(opts: &Options) -> Self {
+        Watcher {
+            opts: opts.watched_options(),
+            path: opts.get_input().cloned().unwrap_or_default(),
+            interval: Duration::from_millis(opts.get_interval()),
+            last: None,
+        }
+    }
+
+    pub fn get_path(&self) -> &String {
+        &self.path
+    }
+
+    // Looks at the file once and rebuilds if it changed, printing the
+    // diagnostics and what happened. Ok(false) if nothing changed.
+    pub fn poll(&mut self, out: &mut dyn Write) -> Result<bool, CliError> {
+        let current = std::fs::read_to_string(&self.path).map_err(|e| e.to_string());
+        if self.last.as_ref() == Some(&current) {
+            return Ok(false);
+        }
+        let report = match &current {
+            Ok(src) => self.rebuild(src),
+            Err(e) => format!("cannot read {}: {e}, waiting for it\n", self.path),
+        };
+        self.last = Some(current);
+        out.write_all(report.as_bytes())
+            .and_then(|_| out.flush())
+            .map_err(|e| CliError::Io(format!("cannot write output: {e}")))?;
+        Ok(true)
+    }
+
+    fn rebuild(&self, src: &str) -> String {
+        let ds = check_source(src);
+        let mut res = render_all(&ds, &SourceFile::new(&self.path, src), self.opts.get_color());
+        if ds.iter().any(|d| d.get_severity() == Severity::Error) {
+            res.push_str(&format!("{}: not rebuilt\n", self.path));
+            return res;
+        }
+        // only lex and parse errors are left and check found none
+        let output = match render(&self.opts, src) {
+            Ok(output) => output,
+            Err(e) => return res + &format!("{e}\n"),
+        };
+        match self.opts.get_output() {
+            Some(path) => match std::fs::write(path, output) {
+                Ok(()) => res.push_str(&format!("{}: wrote {path}\n", self.path)),
+                Err(e) => res.push_str(&format!("cannot write {path}: {e}\n")),
+            },
+            None => res.push_str(&output),
+        }
+        res
+    }
+
+    // Polls until killed
+    pub fn run(&mut self, out: &mut dyn Write) -> Result<(), CliError> {
+        writeln!(out, "watching {}, ctrl-c to stop", self.path)
+            .map_err(|e| CliError::Io(format!("cannot write output: {e}")))?;
+        loop {
+            self.poll(out)?;
+            std::thread::sleep(self.interval);
+        }
+    }
+}
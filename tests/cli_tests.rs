@@ -0,0 +1,198 @@
+use live_crab::cli::*;
+
+mod test_utils;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // (exit code, stdout, stderr)
+    fn run_cli(args: &[&str], stdin: &str) -> (i32, String, String) {
+        let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let code = run(&args, &mut stdin.as_bytes(), &mut out, &mut err);
+        (code, String::from_utf8(out).unwrap(), String::from_utf8(err).unwrap())
+    }
+
+    fn args(a: &[&str]) -> Vec<String> {
+        a.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_arguments() {
+        let opts = parse_args(&args(&["regalloc", "-k", "2", "--solver", "naive", "examples/s1"]))
+            .unwrap()
+            .unwrap();
+        assert_eq!(opts.get_command(), Command::Regalloc);
+        assert_eq!(opts.get_registers(), 2);
+        assert_eq!(opts.get_solver(), Solver::Naive);
+        assert_eq!(opts.get_format(), Format::Text);
+        assert_eq!(opts.get_input().map(|s| s.as_str()), Some("examples/s1"));
+
+        let opts = parse_args(&args(&["dot", "-", "-o", "out.dot"])).unwrap().unwrap();
+        assert_eq!(opts.get_input(), None);
+        assert_eq!(opts.get_format(), Format::Dot);
+        assert_eq!(opts.get_output().map(|s| s.as_str()), Some("out.dot"));
+
+        assert_eq!(parse_args(&args(&["live", "--help"])), Ok(None));
+    }
+    #[test]
+    fn usage_errors() {
+        for bad in [
+            vec![],
+            vec!["frobnicate"],
+            vec!["live", "--solver", "magic"],
+            vec!["live", "-k"],
+            vec!["regalloc", "-k", "0"],
+            vec!["ast", "--format", "dot"],
+            vec!["live", "a", "b"],
+            vec!["live", "--nope"],
+            vec!["repl", "-o", "out.txt"],
+            vec!["cfg", "-f", "csv"],
+            vec!["live", "-f", "html"],
+        ] {
+            let (code, out, err) = run_cli(&bad, "");
+            assert_eq!(code, EXIT_USAGE, "{bad:?}");
+            assert!(out.is_empty());
+            assert!(err.starts_with("error: ") && err.contains("usage:"), "{err}");
+        }
+        let (code, out, _) = run_cli(&["-h"], "");
+        assert_eq!(code, EXIT_OK);
+        assert_eq!(out, USAGE);
+    }
+    #[test]
+    fn input_errors() {
+        let (code, out, err) = run_cli(&["ast"], "a = 1 : 2;");
+        assert_eq!(code, EXIT_INPUT);
+        assert!(out.is_empty());
+        let want = "error: unexpected character `:`\n --> <stdin>:1:7\n  |\n1 | a = 1 : 2;\n  |       ^\n\n";
+        assert_eq!(err, want);
+        let (code, _, err) = run_cli(&["cfg"], "a = 1;\n}");
+        assert_eq!(code, EXIT_INPUT);
+        assert!(err.starts_with("error: expected a statement, found `}`\n --> <stdin>:2:1\n"), "{err}");
+        let (code, _, err) = run_cli(&["tokens"], "a = 1 : 2;");
+        assert_eq!(code, EXIT_INPUT);
+        assert!(err.starts_with("error: unexpected character `:`"));
+        let (code, _, err) = run_cli(&["cfg", "examples/does_not_exist"], "");
+        assert_eq!(code, EXIT_IO);
+        assert!(err.starts_with("error: cannot read examples/does_not_exist"));
+    }
+    #[test]
+    fn check() {
+        let (code, out, err) = run_cli(&["check"], "a = b;\nreturn 1;\nc = 2;\n");
+        assert_eq!(code, EXIT_OK);
+        assert!(err.is_empty());
+        // sorted by where they start
+        assert!(out.starts_with("warning: value assigned to `a` is never read\n --> <stdin>:1:1\n"), "{out}");
+        assert!(out.contains("warning: `b` is used before it is assigned\n --> <stdin>:1:5\n"));
+        assert!(out.contains("warning: unreachable code\n --> <stdin>:3:1\n"));
+        let (code, out, _) = run_cli(&["check", "examples/loop1"], "");
+        assert_eq!(code, EXIT_OK);
+        assert!(out.is_empty(), "{out}");
+        let (code, out, err) = run_cli(&["check", "--color"], "a = ;");
+        assert_eq!(code, EXIT_INPUT);
+        assert!(out.is_empty());
+        assert!(err.starts_with("\x1b[1;31merror\x1b[0m"), "{err:?}");
+    }
+    #[test]
+    fn tokens_and_ast() {
+        let (code, out, _) = run_cli(&["tokens"], "a = 1;");
+        assert_eq!(code, EXIT_OK);
+        assert_eq!(out, "Id(\"a\")\nEquals\nInt(1)\nSemicolon\n");
+        let (_, out, _) = run_cli(&["ast", "-"], "a = 1; return a;");
+        assert_eq!(out, "Program :\na = 1;\nreturn a;\n");
+    }
+    #[test]
+    fn live_from_file() {
+        for solver in ["naive", "fast"] {
+            let (code, out, _) = run_cli(&["live", "-s", solver, "examples/book_ex"], "");
+            assert_eq!(code, EXIT_OK);
+            assert!(out.contains("node\tstatement\tin\tout\n"));
+            assert!(out.contains("0\ta = 0;\t{c}\t{a, c}\n"), "{out}");
+            assert!(out.contains("5\treturn c;\t{c}\t{}\n"), "{out}");
+        }
+    }
+    #[test]
+    fn dot_and_ranges() {
+        let (_, out, _) = run_cli(&["dot"], "a = 1; return a;");
+        assert!(out.contains("digraph CFG {"));
+        assert!(out.contains("block0 -> block1 [label=\"a\"];"));
+        let (_, out, _) = run_cli(&["cfg", "-f", "dot"], "a = 1; return a;");
+        assert!(out.contains("block0 -> block1 [label=\"\"];"));
+        let (_, out, _) = run_cli(&["ranges", "--var", "a"], "a = 1; b = a; return b;");
+        assert!(out.ends_with("a: 0 -> 1\n"), "{out}");
+    }
+    #[test]
+    fn fixpoint_table() {
+        let (code, out, _) = run_cli(&["fixpoint", "-s", "naive"], "a = 1; return a;");
+        assert_eq!(code, EXIT_OK);
+        assert_eq!(
+            out,
+            "                1          2          3
+node  use  def  in    out  in   out   in   out
+0     {}   {a}  {}    {}   {}   {a}*  {}   {a}
+1     {a}  {}   {a}*  {}   {a}  {}    {a}  {}
+3 iterations visiting the nodes in order, the last one changes nothing
+"
+        );
+    }
+    #[test]
+    fn table_formats() {
+        let (code, out, _) = run_cli(&["live", "-f", "md"], "a = 1; return a;");
+        assert_eq!(code, EXIT_OK);
+        assert!(out.starts_with("| node | statement |"), "{out}");
+        let (_, out, _) = run_cli(&["live", "--format", "csv"], "a = 1; return a;");
+        assert!(out.ends_with("1,return a;,a,,,a,\n"), "{out}");
+        let (_, out, _) = run_cli(&["live", "-f", "latex"], "a = 1; return a;");
+        assert!(out.starts_with("\\begin{tabular}"), "{out}");
+    }
+    #[test]
+    fn json_output() {
+        for (cmd, kind) in [
+            ("tokens", "tokens"),
+            ("ast", "program"),
+            ("cfg", "cfg"),
+            ("live", "cfg"),
+            ("fixpoint", "liveness_trace"),
+            ("regalloc", "regalloc"),
+        ] {
+            let (code, out, _) = run_cli(&[cmd, "-f", "json"], "a = 1; return a;");
+            assert_eq!(code, EXIT_OK);
+            let doc = live_crab::json::Json::parse(&out).unwrap();
+            assert_eq!(doc.get("version"), Some(&live_crab::json::Json::Int(1)));
+            assert_eq!(doc.get("kind").and_then(|k| k.as_str()), Some(kind), "{cmd}");
+        }
+        let (_, out, _) = run_cli(&["ast", "-f", "json"], "a = 1; return a;");
+        assert_eq!(live_crab::json::read_program(&out).unwrap().stmts.len(), 2);
+    }
+    #[test]
+    fn dot_options() {
+        let (code, out, _) = run_cli(&["dot", "--html", "--highlight", "a", "--no-clusters"], "a = 1; return a;");
+        assert_eq!(code, EXIT_OK);
+        assert!(out.contains("block1 [shape=plaintext, label=<<TABLE "), "{out}");
+        assert!(out.contains("CELLSPACING=\"0\" BGCOLOR=\"lightblue\">"), "{out}");
+        let args = ["live", "-f", "dot", "--highlight", "a", "--highlight-color", "red"];
+        let (_, out, _) = run_cli(&args, "a = 1; return a;");
+        assert!(out.contains("block0 -> block1 [label=\"a\", color=\"red\", penwidth=2];"), "{out}");
+    }
+    #[test]
+    fn regalloc() {
+        let (code, out, _) = run_cli(&["regalloc", "-k", "2"], "a = 1; b = 2; c = 3; return a + b + c;");
+        assert_eq!(code, EXIT_OK);
+        assert!(out.contains("var\treg\tinterferes\n"));
+        assert_eq!(out.matches("spill").count(), 1, "{out}");
+        let (_, out, _) = run_cli(&["regalloc", "-f", "dot"], "a = 1; b = 2; return a + b;");
+        assert!(out.contains("\"a\" -- \"b\";"));
+    }
+    #[test]
+    fn output_file() {
+        let path = std::env::temp_dir().join("live_crab_cli_output.dot");
+        let path = path.to_str().unwrap();
+        let (code, out, _) = run_cli(&["dot", "-o", path], "a = 1; return a;");
+        assert_eq!(code, EXIT_OK);
+        assert!(out.is_empty());
+        assert!(std::fs::read_to_string(path).unwrap().starts_with("digraph CFG {"));
+        let _ = std::fs::remove_file(path);
+    }
+}
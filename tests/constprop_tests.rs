@@ -0,0 +1,96 @@
+use live_crab::constprop::*;
+use live_crab::liveness::ControlFlowGraph;
+
+mod test_utils;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_utils::*;
+
+    #[test]
+    fn lattice_meet() {
+        use ConstValue::*;
+        assert_eq!(Unknown.meet(Constant(3)), Constant(3));
+        assert_eq!(Constant(3).meet(Constant(3)), Constant(3));
+        assert_eq!(Constant(3).meet(Constant(4)), Overdefined);
+        assert_eq!(Overdefined.meet(Unknown), Overdefined);
+    }
+    #[test]
+    fn analysis_straight_line() {
+        let s = "a = 2; b = a * 3; c = b + x; return c;";
+        let cfg = ControlFlowGraph::from(&prog_from_str(s));
+        let got = cfg.constant_propagation();
+        assert_eq!(got.get_value(2, "a"), ConstValue::Constant(2));
+        assert_eq!(got.get_value(2, "b"), ConstValue::Constant(6));
+        assert_eq!(got.get_value(3, "c"), ConstValue::Overdefined);
+        assert_eq!(got.get_value(0, "x"), ConstValue::Overdefined);
+    }
+    #[test]
+    fn analysis_loop_is_overdefined() {
+        let s = get_str_from_path("examples/loop1").unwrap();
+        let cfg = ControlFlowGraph::from(&prog_from_str(&s));
+        let got = cfg.constant_propagation();
+        // i is 0 on entry and i + 1 around the back edge
+        assert_eq!(got.get_value(1, "i"), ConstValue::Overdefined);
+        assert!(got.is_executable(2));
+    }
+    #[test]
+    fn analysis_skips_dead_branch() {
+        let s = "a = 1; if ( a < 0 ) { a = 5; } return a;";
+        let cfg = ControlFlowGraph::from(&prog_from_str(s));
+        let got = cfg.constant_propagation();
+        assert!(!got.is_executable(2));
+        assert_eq!(got.get_value(3, "a"), ConstValue::Constant(1));
+    }
+    #[test]
+    fn fold_straight_line() {
+        let s = "a = 2; b = a * 3; c = b + x; return c - b;";
+        let got = propagate_constants(&prog_from_str(s));
+        let want = prog_from_str("a = 2; b = 6; c = 6 + x; return c - 6;");
+        assert_eq!(got.get_program(), &want, "Got:\n{}", got.get_program());
+        // a and b are no longer live after their definitions
+        assert!(got.get_before().get_live_out(1).contains("b"));
+        assert!(!got.get_after().get_live_out(1).contains("b"));
+        assert!(!got.get_after().get_live_out(0).contains("a"));
+    }
+    #[test]
+    fn fold_constant_if() {
+        let s = "a = 1; if ( a < 2 ) { b = a + 1; } if ( 3 < a ) { b = 9; } return b;";
+        let got = propagate_constants(&prog_from_str(s));
+        let want = prog_from_str("a = 1; b = 2; return 2;");
+        assert_eq!(got.get_program(), &want, "Got:\n{}", got.get_program());
+    }
+    #[test]
+    fn fold_constant_loops() {
+        let s = "n = 0; while ( n < 0 ) { n = n + 1; } do { m = n + 4; } while ( m < 2 ); return m;";
+        let got = propagate_constants(&prog_from_str(s));
+        let want = prog_from_str("n = 0; m = 4; return 4;");
+        assert_eq!(got.get_program(), &want, "Got:\n{}", got.get_program());
+    }
+    #[test]
+    fn no_fold_division_by_zero() {
+        let s = "a = 0; b = 7 / a; return b;";
+        let got = propagate_constants(&prog_from_str(s));
+        let want = prog_from_str("a = 0; b = 7 / 0; return b;");
+        assert_eq!(got.get_program(), &want, "Got:\n{}", got.get_program());
+    }
+    #[test]
+    fn fold_negative_result() {
+        let s = "a = 2; b = a - 5; return b;";
+        let got = propagate_constants(&prog_from_str(s));
+        // folded negatives still print into something the parser accepts
+        let printed = format!("{}", got.get_program()).replace("Program :", "");
+        assert_eq!(prog_from_str(&printed), prog_from_str("a = 2; b = -3; return -3;"));
+    }
+    #[test]
+    fn no_fold_to_min() {
+        // -2147483648 would not parse, its literal is out of range
+        let s = "a = 0 - 2147483647; b = a - 1; return b;";
+        let got = propagate_constants(&prog_from_str(s));
+        let want = prog_from_str("a = -2147483647; b = -2147483647 - 1; return b;");
+        assert_eq!(got.get_program(), &want, "Got:\n{}", got.get_program());
+        let printed = format!("{}", got.get_program()).replace("Program :", "");
+        assert_eq!(&prog_from_str(&printed), got.get_program());
+    }
+}
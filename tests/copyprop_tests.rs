@@ -0,0 +1,98 @@
+use live_crab::copyprop::propagate_copies;
+use live_crab::deadcode::eliminate_dead_assignments;
+use live_crab::interpreter::{Interpreter, RuntimeError};
+use live_crab::liveness::ControlFlowGraph;
+
+mod test_utils;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_utils::*;
+
+    #[test]
+    fn available_copies_killed() {
+        let s = "a = b; c = a + 1; b = 2; return a;";
+        let cfg = ControlFlowGraph::from(&prog_from_str(s));
+        let got = cfg.available_copies();
+        let copy = (String::from("a"), String::from("b"));
+        assert!(got.get_in(1).contains(&copy));
+        assert!(got.get_in(2).contains(&copy));
+        assert!(!got.get_in(3).contains(&copy), "b = 2 did not kill a = b");
+    }
+    #[test]
+    fn available_copies_must_hold_on_all_paths() {
+        let s = "a = b; if ( x < 1 ) { a = c; } return a;";
+        let cfg = ControlFlowGraph::from(&prog_from_str(s));
+        let got = cfg.available_copies();
+        assert!(got.get_in(3).is_empty(), "Got: {:?}", got.get_in(3));
+    }
+    #[test]
+    fn propagate_straight_line() {
+        let s = "a = b; c = a + 1; d = c; return d * a;";
+        let got = propagate_copies(&prog_from_str(s));
+        let want = prog_from_str("c = b + 1; return c * b;");
+        assert_eq!(got.get_program(), &want, "Got:\n{}", got.get_program());
+        assert_eq!(got.get_replaced(), 3);
+        assert_eq!(got.get_removed().len(), 2);
+    }
+    #[test]
+    fn propagate_chain() {
+        let s = "a = x; b = a; c = b; return c;";
+        let got = propagate_copies(&prog_from_str(s));
+        assert_eq!(got.get_program(), &prog_from_str("return x;"));
+    }
+    #[test]
+    fn no_propagate_after_redefinition() {
+        let s = "i = 0; do { j = i; i = i + 1; k = j + 1; } while ( i < 9 ); return k;";
+        let prog = prog_from_str(s);
+        let got = propagate_copies(&prog);
+        assert_eq!(got.get_program(), &prog, "Got:\n{}", got.get_program());
+    }
+    #[test]
+    fn interference_shrinks() {
+        let s = "a = b; x = 1; c = a + x; return c + b;";
+        let got = propagate_copies(&prog_from_str(s));
+        let before = got.get_interference_before();
+        let after = got.get_interference_after();
+        // a is live across x = 1 before, and gone after
+        assert!(before.interferes("a", "x"));
+        assert!(!after.get_vars().iter().any(|v| *v == "a"));
+        assert!(after.interferes("b", "x"));
+        assert!(after.interferes("b", "c"));
+    }
+    #[test]
+    fn interference_excludes_copy_source() {
+        let s = "a = b; return a + b;";
+        let mut cfg = ControlFlowGraph::from(&prog_from_str(s));
+        cfg.fast_perform_liveness_analysis();
+        let got = cfg.interference_graph();
+        assert!(!got.interferes("a", "b"));
+        let s = "a = b + 0; return a + b;";
+        let mut cfg = ControlFlowGraph::from(&prog_from_str(s));
+        cfg.fast_perform_liveness_analysis();
+        let got = cfg.interference_graph();
+        assert!(got.interferes("a", "b"));
+        assert!(got.generate_dot().contains("\t\"a\" -- \"b\";"));
+    }
+    #[test]
+    fn dead_code_keeps_division() {
+        let s = "a = 1; b = a / c; d = 2 / 2; return a;";
+        let (got, removed) = eliminate_dead_assignments(&prog_from_str(s));
+        assert_eq!(got, prog_from_str("a = 1; b = a / c; return a;"));
+        assert_eq!(removed.len(), 1);
+    }
+    #[test]
+    fn dead_code_drops_reads_of_inputs() {
+        // y is an input, the program only fails when it is not given
+        let prog = prog_from_str("x = y; return 1;");
+        let (got, removed) = eliminate_dead_assignments(&prog);
+        assert_eq!(got, prog_from_str("return 1;"));
+        assert_eq!(removed.len(), 1);
+        let run = Interpreter::new().with_input("y", 5);
+        assert_eq!(run.run(&got).unwrap().get_returned(), run.run(&prog).unwrap().get_returned());
+        let run = Interpreter::new();
+        assert_eq!(run.run(&prog), Err(RuntimeError::Uninitialized(String::from("y"))));
+        assert_eq!(run.run(&got).unwrap().get_returned(), Some(1));
+    }
+}
@@ -0,0 +1,99 @@
+use live_crab::ast::*;
+use live_crab::cse::eliminate_common_subexpressions;
+use live_crab::liveness::ControlFlowGraph;
+
+mod test_utils;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_utils::*;
+
+    fn a_plus_b() -> Expr {
+        Expr::BinOp(
+            Box::new(Expr::Id(String::from("a"))),
+            Operator::Plus,
+            Box::new(Expr::Id(String::from("b"))),
+        )
+    }
+
+    #[test]
+    fn available_expressions_killed() {
+        let s = "x = a + b; y = x * 2; a = 1; return a + b;";
+        let cfg = ControlFlowGraph::from(&prog_from_str(s));
+        let got = cfg.available_expressions();
+        assert!(got.get_in(0).is_empty());
+        assert!(got.get_in(1).contains(&a_plus_b()));
+        assert!(got.get_in(2).contains(&a_plus_b()));
+        assert!(!got.get_in(3).contains(&a_plus_b()), "a = 1 did not kill a + b");
+    }
+    #[test]
+    fn available_expressions_must_hold_on_all_paths() {
+        let s = "if ( c < 1 ) { x = a + b; } y = a + b; return y;";
+        let cfg = ControlFlowGraph::from(&prog_from_str(s));
+        let got = cfg.available_expressions();
+        assert!(!got.get_in(2).contains(&a_plus_b()), "Got: {:?}", got.get_in(2));
+
+        let s = "x = a + b; if ( c < 1 ) { x = 1; } y = a + b; return y;";
+        let cfg = ControlFlowGraph::from(&prog_from_str(s));
+        let got = cfg.available_expressions();
+        assert!(got.get_in(3).contains(&a_plus_b()), "Got: {:?}", got.get_in(3));
+    }
+    #[test]
+    fn cse_straight_line() {
+        let s = "x = a + b; y = a + b; return x + y;";
+        let got = eliminate_common_subexpressions(&prog_from_str(s));
+        let want = prog_from_str("t0 = a + b; x = t0; y = t0; return x + y;");
+        assert_eq!(got.get_program(), &want, "Got:\n{}", got.get_program());
+        assert_eq!(got.get_temps(), &vec![(a_plus_b(), String::from("t0"))]);
+        assert_eq!(got.get_eliminated(), 1);
+    }
+    #[test]
+    fn cse_nested_reuses_outermost() {
+        let s = "x = a + b * c; y = a + b * c; return x + y;";
+        let got = eliminate_common_subexpressions(&prog_from_str(s));
+        let want = prog_from_str("t0 = a + b * c; x = t0; y = t0; return x + y;");
+        assert_eq!(got.get_program(), &want, "Got:\n{}", got.get_program());
+        assert_eq!(got.get_temps().len(), 1);
+    }
+    #[test]
+    fn cse_fresh_temp_name() {
+        let s = "t0 = a + b; y = a + b; return t0 + y;";
+        let got = eliminate_common_subexpressions(&prog_from_str(s));
+        let want = prog_from_str("t1 = a + b; t0 = t1; y = t1; return t0 + y;");
+        assert_eq!(got.get_program(), &want, "Got:\n{}", got.get_program());
+    }
+    #[test]
+    fn cse_across_if() {
+        let s = "x = a + b; if ( c < 1 ) { x = 1; } y = a + b; return x + y;";
+        let got = eliminate_common_subexpressions(&prog_from_str(s));
+        let want = prog_from_str("t0 = a + b; x = t0; if ( c < 1 ) { x = 1; } y = t0; return x + y;");
+        assert_eq!(got.get_program(), &want, "Got:\n{}", got.get_program());
+    }
+    #[test]
+    fn cse_not_on_one_path() {
+        let s = "if ( c < 1 ) { x = a + b; } y = a + b; return x + y;";
+        let prog = prog_from_str(s);
+        let got = eliminate_common_subexpressions(&prog);
+        assert_eq!(got.get_program(), &prog, "Got:\n{}", got.get_program());
+        assert!(got.get_temps().is_empty());
+    }
+    #[test]
+    fn cse_while_condition() {
+        let s = "while ( a + b < i ) { a = a + 1; } return a + b;";
+        let got = eliminate_common_subexpressions(&prog_from_str(s));
+        let want = prog_from_str("t0 = a + b; while ( t0 < i ) { a = a + 1; t0 = a + b; } return t0;");
+        assert_eq!(got.get_program(), &want, "Got:\n{}", got.get_program());
+    }
+    #[test]
+    fn cse_lengthens_live_ranges() {
+        let s = "x = a + b; c = x * 2; d = c + 1; y = a + b; return y + d;";
+        let got = eliminate_common_subexpressions(&prog_from_str(s));
+        let before = got.get_before();
+        let after = got.get_after();
+        assert_eq!(before.get_live_range(String::from("x")).len(), 1);
+        assert_eq!(before.get_live_range(String::from("y")).len(), 1);
+        // t0 now carries a + b from the first computation to the reuse
+        assert_eq!(after.get_live_range(String::from("t0")), vec![(0, 1), (1, 2), (2, 3), (3, 4)]);
+    }
+}
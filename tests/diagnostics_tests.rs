@@ -0,0 +1,110 @@
+use live_crab::diagnostics::*;
+use live_crab::lexer::{Lexer, Span};
+use live_crab::liveness::ControlFlowGraph;
+use live_crab::parser::Parser;
+
+mod test_utils;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(src: &str) -> Vec<String> {
+        check_source(src).iter().map(|d| d.get_message().clone()).collect()
+    }
+
+    #[test]
+    fn token_spans() {
+        let got = Lexer::new("ab = 12;\n  é = 1;").tokenize_with_spans().unwrap();
+        let spans: Vec<(usize, usize)> = got.iter().map(|(_, s)| (s.get_start(), s.get_end())).collect();
+        // é is two bytes
+        assert_eq!(spans, vec![(0, 2), (3, 4), (5, 7), (7, 8), (11, 13), (14, 15), (16, 17), (17, 18)]);
+    }
+    #[test]
+    fn node_spans_follow_cfg_order() {
+        let src = "i = 0;\ndo {\n  if (i) { j = i; }\n} while (i < 3);\nwhile (j) { j = j - 1; }\nreturn i;";
+        let mut parser = Parser::with_spans(Lexer::new(src).tokenize_with_spans().unwrap());
+        let p = parser.try_parse().unwrap();
+        let spans = parser.get_node_spans();
+        assert_eq!(spans.len(), ControlFlowGraph::from(&p).get_nodes().len());
+        let text: Vec<&str> = spans.iter().map(|s| &src[s.get_start()..s.get_end()]).collect();
+        assert_eq!(
+            text,
+            vec!["i = 0;", "if (i)", "j = i;", "while (i < 3);", "while (j)", "j = j - 1;", "return i;"]
+        );
+    }
+    #[test]
+    fn line_col() {
+        let f = SourceFile::new("f", "ab\ncd\n\nxé y");
+        assert_eq!(f.line_col(0), (1, 1));
+        assert_eq!(f.line_col(3), (2, 1));
+        assert_eq!(f.line_col(6), (3, 1));
+        assert_eq!(f.line_col(10), (4, 3));
+        assert_eq!(f.line_col(11), (4, 4));
+        assert_eq!(f.get_line(4), "xé y");
+        assert_eq!(f.get_line(3), "");
+    }
+    #[test]
+    fn render_plain() {
+        let f = SourceFile::new("ex.lc", "a = 1;\nb = a + c;\n");
+        let d = Diagnostic::warning("`c` is odd", Span::new(15, 16))
+            .with_label("here")
+            .with_note("first")
+            .with_note("second");
+        let want = "warning: `c` is odd\n --> ex.lc:2:9\n  |\n2 | b = a + c;\n  |         ^ here\n  |\n  = note: first\n  = note: second\n";
+        assert_eq!(render(&d, &f, false), want);
+    }
+    #[test]
+    fn render_wide_gutter_and_multiline() {
+        let src = "a = 1;\n".repeat(9) + "while (a <\n 3) { a = a + 1; }";
+        let f = SourceFile::new("ex.lc", &src);
+        let d = Diagnostic::error("bad", Span::new(63, 75));
+        let got = render(&d, &f, false);
+        assert!(got.starts_with("error: bad\n  --> ex.lc:10:1\n   |\n10 | while (a <\n   | ^^^^^^^^^^\n"), "{got}");
+    }
+    #[test]
+    fn render_color() {
+        let f = SourceFile::new("ex.lc", "a = ;");
+        let got = render(&Diagnostic::error("oops", Span::new(4, 5)), &f, true);
+        assert!(got.starts_with("\x1b[1;31merror\x1b[0m\x1b[1m: oops\x1b[0m\n"));
+        assert!(got.contains("\x1b[1;34m-->\x1b[0m ex.lc:1:5"));
+        assert!(got.contains("\x1b[1;31m^\x1b[0m"));
+    }
+    #[test]
+    fn lex_and_parse_errors() {
+        let got = check_source("a = 1;\nb = 2 @ 3;");
+        assert_eq!(got.len(), 1);
+        assert_eq!(got[0].get_severity(), Severity::Error);
+        assert_eq!(got[0].get_span(), Span::new(13, 14));
+        let got = check_source("a = 1;\nif (a) {\n b = 2;\n");
+        assert_eq!(got[0].get_message(), "expected `}`, found end of input");
+        assert_eq!(got[0].get_span(), Span::new(23, 23));
+    }
+    #[test]
+    fn uninitialized() {
+        assert_eq!(messages("a = b; return a;"), vec!["`b` is used before it is assigned"]);
+        assert_eq!(
+            messages("if (c) { a = 1; } return a;"),
+            vec!["`c` is used before it is assigned", "`a` may be used before it is assigned"]
+        );
+        assert!(messages("a = 1; while (a < 3) { a = a + 1; } return a;").is_empty());
+        let d = &check_source("a = 1; b = a + c;\nreturn b;")[0];
+        assert_eq!(d.get_span(), Span::new(15, 16));
+    }
+    #[test]
+    fn dead_stores() {
+        assert_eq!(messages("a = 1; a = 2; return a;"), vec!["value assigned to `a` is never read"]);
+        assert_eq!(check_source("a = 1; a = 2; return a;")[0].get_span(), Span::new(0, 6));
+        // read in the next iteration
+        assert!(messages("i = 0; do { i = i + 1; } while (i < 3); return i;").is_empty());
+    }
+    #[test]
+    fn unreachable() {
+        let src = "a = 1; return a; b = 2; c = b;";
+        let got = check_source(src);
+        assert_eq!(got.len(), 1);
+        assert_eq!(got[0].get_message(), "unreachable code");
+        assert_eq!(got[0].get_span(), Span::new(17, 30));
+        assert_eq!(got[0].get_notes(), &vec![String::from("it follows a return")]);
+    }
+}
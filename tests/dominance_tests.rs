@@ -0,0 +1,142 @@
+use live_crab::lexer::Lexer;
+use live_crab::liveness::ControlFlowGraph;
+use live_crab::parser::Parser;
+
+mod test_utils;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_utils::*;
+
+    fn cfg_from_str(s: &str) -> ControlFlowGraph {
+        let lexer = Lexer::new(s);
+        let tokens = lexer.tokenize();
+        let mut parser = Parser::new(tokens);
+        let prog = parser.parse();
+        ControlFlowGraph::from(&prog)
+    }
+
+    #[test]
+    fn idom_book_ex() {
+        let s = get_str_from_path("examples/book_ex").unwrap();
+        let cfg = cfg_from_str(&s);
+        //0: a = 0;
+        //1: b = a + 1;
+        //2: c = c + 1;
+        //3: a = b * 2;
+        //4: if a < 9
+        //5: return c;
+        let got = cfg.immediate_dominators();
+        assert_eq!(got, vec![None, Some(0), Some(1), Some(2), Some(3), Some(4)]);
+    }
+    #[test]
+    fn idom_if() {
+        let s = "i = 0;
+        if ( a < 1337 ) {
+            a = a+1;
+            a = a*2;
+        }
+        return a;";
+        let cfg = cfg_from_str(s);
+        let dom = cfg.dominator_tree();
+        assert_eq!(dom.get_idom(4), Some(1), "The cond should be idom of the join");
+        assert!(dom.dominates(1, 3));
+        assert!(!dom.dominates(2, 4));
+        assert!(!dom.strictly_dominates(4, 4));
+        assert_eq!(dom.get_children(1), &vec![2, 4]);
+    }
+    #[test]
+    fn frontier_if() {
+        let s = "i = 0;
+        if ( a < 1337 ) {
+            a = a+1;
+            a = a*2;
+        }
+        return a;";
+        let cfg = cfg_from_str(s);
+        let df = cfg.dominance_frontiers();
+        assert!(df[2].contains(&4) && df[2].len() == 1);
+        assert!(df[3].contains(&4) && df[3].len() == 1);
+        assert!(df[1].is_empty());
+        assert!(df[0].is_empty());
+    }
+    #[test]
+    fn frontier_do_while() {
+        let s = get_str_from_path("examples/book_ex").unwrap();
+        let cfg = cfg_from_str(&s);
+        let df = cfg.dominance_frontiers();
+        // The loop header is in the frontier of every node in the body
+        for (n, f) in df.iter().enumerate().take(5).skip(1) {
+            assert!(f.contains(&1), "Node 1 was not in DF({n})");
+        }
+        assert!(df[0].is_empty());
+        assert!(df[5].is_empty());
+    }
+    #[test]
+    fn frontier_loop_at_entry() {
+        let s = "do { a = a + 1; } while ( a < 3 ); return a;";
+        let cfg = cfg_from_str(s);
+        let df = cfg.dominance_frontiers();
+        assert!(df[0].contains(&0));
+        assert!(df[1].contains(&0));
+    }
+    #[test]
+    fn post_dominators_if() {
+        let s = "i = 0;
+        if ( a < 1337 ) {
+            a = a+1;
+            a = a*2;
+        }
+        return a;";
+        let cfg = cfg_from_str(s);
+        let pdom = cfg.post_dominator_tree();
+        assert_eq!(pdom.get_roots(), &vec![4]);
+        assert_eq!(pdom.get_idom(1), Some(4));
+        assert_eq!(pdom.get_idom(2), Some(3));
+        assert_eq!(pdom.get_idom(0), Some(1));
+        // The body is control dependent on the cond
+        assert!(pdom.get_frontier(2).contains(&1));
+        assert!(pdom.get_frontier(3).contains(&1));
+        assert!(pdom.get_frontier(4).is_empty());
+    }
+    #[test]
+    fn post_dominators_two_exits() {
+        let s = "if ( a < 1 ) { return a; } return b;";
+        let cfg = cfg_from_str(s);
+        let pdom = cfg.post_dominator_tree();
+        assert_eq!(pdom.get_roots(), &vec![0, 1, 2]);
+        assert_eq!(pdom.get_idom(0), None);
+    }
+    #[test]
+    fn post_dominators_branch_to_exit() {
+        // the false branch of the trailing if ends the program
+        let s = "a = 1; if ( a < 2 ) { a = 3; }";
+        let cfg = cfg_from_str(s);
+        let pdom = cfg.post_dominator_tree();
+        assert_eq!(pdom.get_roots(), &vec![1, 2]);
+        assert_eq!(pdom.get_idom(0), Some(1));
+        assert_eq!(pdom.get_idom(1), None);
+        assert_eq!(pdom.get_idom(2), None);
+        assert!(pdom.get_frontier(2).contains(&1));
+    }
+    #[test]
+    fn unreachable_node() {
+        let s = "return a; b = 1;";
+        let cfg = cfg_from_str(s);
+        let dom = cfg.dominator_tree();
+        assert!(dom.is_reachable(0));
+        assert!(!dom.is_reachable(1));
+        assert!(!dom.dominates(0, 1));
+    }
+    #[test]
+    fn dominator_dot() {
+        let s = get_str_from_path("examples/book_ex").unwrap();
+        let cfg = cfg_from_str(&s);
+        let dot = cfg.generate_dominator_dot();
+        assert!(dot.starts_with("digraph DomTree {"));
+        assert!(dot.contains("\tblock4 [label=\"4: if a < 9\"];"));
+        assert!(dot.contains("\tblock4 -> block5;"));
+        assert!(!dot.contains("-> block0"));
+    }
+}
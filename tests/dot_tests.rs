@@ -0,0 +1,68 @@
+use live_crab::dot::DotOptions;
+use live_crab::lexer::Lexer;
+use live_crab::liveness::ControlFlowGraph;
+use live_crab::parser::Parser;
+
+mod test_utils;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cfg_from_str(s: &str) -> ControlFlowGraph {
+        let prog = Parser::new(Lexer::new(s).tokenize()).parse();
+        let mut cfg = ControlFlowGraph::from(&prog);
+        cfg.fast_perform_liveness_analysis();
+        cfg
+    }
+
+    const LOOP: &str = "i = 0; s = 0; while (i < 3) { s = s + i; i = i + 1; } return s;";
+
+    #[test]
+    fn defaults_match_generate_dot() {
+        let cfg = cfg_from_str(LOOP);
+        assert_eq!(cfg.generate_dot_with(&DotOptions::new()), cfg.generate_dot());
+        let unanalyzed = ControlFlowGraph::from(&Parser::new(Lexer::new(LOOP).tokenize()).parse());
+        assert_eq!(unanalyzed.generate_dot_with(&DotOptions::default()), unanalyzed.generate_dot());
+    }
+    #[test]
+    fn html_labels() {
+        let dot = cfg_from_str(LOOP).generate_dot_with(&DotOptions::new().with_html_labels(true));
+        assert!(dot.contains(concat!(
+            "block2 [shape=plaintext, label=<<TABLE BORDER=\"0\" CELLBORDER=\"1\" CELLSPACING=\"0\">",
+            "<TR><TD COLSPAN=\"2\"><B>2</B>: if i &lt; 3</TD></TR>",
+            "<TR><TD>use</TD><TD>i</TD></TR>",
+            "<TR><TD>def</TD><TD></TD></TR>",
+            "<TR><TD>in</TD><TD>i, s</TD></TR>",
+            "<TR><TD>out</TD><TD>i, s</TD></TR>",
+            "</TABLE>>];"
+        )), "{dot}");
+        // without liveness there is nothing to show in and out
+        let cfg = ControlFlowGraph::from(&Parser::new(Lexer::new("a = 1;").tokenize()).parse());
+        let dot = cfg.generate_dot_with(&DotOptions::new().with_html_labels(true));
+        assert!(dot.contains("<TR><TD>def</TD><TD>a</TD></TR></TABLE>>];"), "{dot}");
+    }
+    #[test]
+    fn highlight() {
+        let opts = DotOptions::new().with_highlight("i").with_highlight_color("#ff0000");
+        let dot = cfg_from_str(LOOP).generate_dot_with(&opts);
+        assert!(dot.contains("\tblock0 [label=\"i = 0;\", style=filled, fillcolor=\"#ff0000\"];"), "{dot}");
+        assert!(dot.contains("\tblock5 [label=\"return s;\"];"), "{dot}");
+        assert!(dot.contains("\tblock2 -> block3 [label=\"i, s\", color=\"#ff0000\", penwidth=2];"), "{dot}");
+        assert!(dot.contains("\tblock2 -> block5 [label=\"s\"];"), "{dot}");
+
+        let dot = cfg_from_str(LOOP).generate_dot_with(&opts.with_html_labels(true));
+        assert!(dot.contains("CELLSPACING=\"0\" BGCOLOR=\"#ff0000\"><TR><TD COLSPAN=\"2\"><B>4</B>"), "{dot}");
+        assert!(!dot.contains("style=filled"));
+    }
+    #[test]
+    fn clusters_can_be_turned_off() {
+        let cfg = cfg_from_str(LOOP);
+        assert!(cfg.generate_dot().contains("subgraph cluster_loop0 {"));
+        let dot = cfg.generate_dot_with(&DotOptions::new().with_clusters(false));
+        assert!(!dot.contains("subgraph"));
+        for n in 0..cfg.get_nodes().len() {
+            assert!(dot.contains(&format!("\n\tblock{n} [")), "{dot}");
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use live_crab::lexer::Lexer;
+use live_crab::liveness::ControlFlowGraph;
+use live_crab::parser::Parser;
+
+mod test_utils;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cfg_from_str(s: &str) -> ControlFlowGraph {
+        let prog = Parser::new(Lexer::new(s).tokenize()).parse();
+        let mut cfg = ControlFlowGraph::from(&prog);
+        cfg.fast_perform_liveness_analysis();
+        cfg
+    }
+
+    #[test]
+    fn rows_are_sorted() {
+        let src = "z = 1; y = 2; x = 3; if (x) { y = x + z; } return z + y;";
+        let cfg = cfg_from_str(src);
+        let table = cfg.liveness_table();
+        assert_eq!(table.get_rows().len(), cfg.get_nodes().len());
+        let row = &table.get_rows()[2];
+        assert_eq!(row.get_node(), 2);
+        assert_eq!(row.get_statement(), "x = 3;");
+        assert_eq!(row.get_defs(), &vec![String::from("x")]);
+        assert_eq!(row.get_live_out(), &vec![String::from("x"), String::from("y"), String::from("z")]);
+        let cond = &table.get_rows()[3];
+        assert_eq!(cond.get_succs(), &vec![4, 5]);
+        assert_eq!(cond.get_uses(), &vec![String::from("x")]);
+        // the same whatever order the sets were built in
+        for _ in 0..5 {
+            assert_eq!(cfg_from_str(src).liveness_table(), table);
+        }
+    }
+    #[test]
+    fn markdown() {
+        let md = cfg_from_str("b = 1; a = b; return a + b;").liveness_table().to_markdown();
+        assert_eq!(
+            md,
+            "| node | statement | use | def | succ | in | out |
+|---:|---|---|---|---|---|---|
+| 0 | `b = 1;` |  | b | 1 |  | b |
+| 1 | `a = b;` | b | a | 2 | b | a, b |
+| 2 | `return a + b;` | a, b |  |  | a, b |  |
+"
+        );
+    }
+    #[test]
+    fn csv() {
+        let csv = cfg_from_str("b = 1; a = b; return a + b;").liveness_table().to_csv();
+        assert_eq!(
+            csv,
+            "node,statement,use,def,succ,in,out
+0,b = 1;,,b,1,,b
+1,a = b;,b,a,2,b,a b
+2,return a + b;,a b,,,a b,
+"
+        );
+    }
+    #[test]
+    fn latex() {
+        let tex = cfg_from_str("a = 1; while (a < 3) { a = a + 1; }").liveness_table().to_latex();
+        assert!(tex.starts_with("\\begin{tabular}{rllllll}\n\\hline\nnode & statement & use & def & succ & in & out \\\\\n"));
+        assert!(tex.contains("1 & \\texttt{if a \\textless{} 3} & a &  & 2 & a & a \\\\\n"), "{tex}");
+        assert!(tex.ends_with("\\hline\n\\end{tabular}\n"));
+    }
+}
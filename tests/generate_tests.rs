@@ -0,0 +1,110 @@
+use live_crab::ast::*;
+use live_crab::constprop::propagate_constants;
+use live_crab::generate::*;
+use live_crab::interpreter::Interpreter;
+use live_crab::liveness::ControlFlowGraph;
+use live_crab::oracle::check_liveness;
+
+mod test_utils;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_utils::*;
+
+    const SEEDS: u64 = 200;
+
+    // The program as source, without the "Program :" header
+    fn source(p: &Program) -> String {
+        p.stmts.iter().map(Program::pretty_print_statement).collect()
+    }
+
+    fn depth(stmts: &[Statement]) -> usize {
+        stmts
+            .iter()
+            .map(|s| match s {
+                Statement::If(_, body) | Statement::While(_, body) | Statement::DoWhile(body, _) => 1 + depth(body),
+                _ => 0,
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    #[test]
+    fn same_seed_same_program() {
+        for seed in 0..20 {
+            assert_eq!(random_program(seed), random_program(seed));
+        }
+        let progs: Vec<Program> = ProgramGenerator::new(1).take(20).collect();
+        let again: Vec<Program> = ProgramGenerator::new(1).take(20).collect();
+        assert_eq!(progs, again);
+        assert!(progs.windows(2).any(|w| w[0] != w[1]));
+    }
+    #[test]
+    fn respects_config() {
+        for seed in 0..SEEDS {
+            let p = ProgramGenerator::new(seed)
+                .with_vars(&["x", "y"])
+                .with_max_depth(2)
+                .with_max_stmts(3)
+                .generate();
+            assert!(!p.stmts.is_empty());
+            assert!(depth(&p.stmts) <= 2, "{}", source(&p));
+            let cfg = ControlFlowGraph::from(&p);
+            for n in cfg.get_nodes() {
+                assert!(n.get_uses().iter().chain(n.get_defs()).all(|v| v == "x" || v == "y"));
+            }
+            let flat = ProgramGenerator::new(seed).with_max_depth(0).generate();
+            assert_eq!(depth(&flat.stmts), 0);
+        }
+    }
+    #[test]
+    fn extreme_bounds() {
+        let mut rng = Rng::new(7);
+        for _ in 0..100 {
+            rng.range(i32::MIN, i32::MAX);
+            assert_eq!(rng.range(i32::MAX, i32::MAX), i32::MAX);
+            assert!(rng.range(i32::MIN, i32::MIN + 1) <= i32::MIN + 1);
+        }
+        for max in [i32::MAX, i32::MIN, 0] {
+            for seed in 0..20 {
+                let p = ProgramGenerator::new(seed).with_max_const(max).generate();
+                assert_eq!(prog_from_str(&source(&p)), p, "seed {seed}:\n{}", source(&p));
+            }
+        }
+    }
+    #[test]
+    fn print_parse_round_trip() {
+        for seed in 0..SEEDS {
+            let p = random_program(seed);
+            assert_eq!(prog_from_str(&source(&p)), p, "seed {seed}:\n{}", source(&p));
+            let folded = propagate_constants(&p).get_program().clone();
+            assert_eq!(prog_from_str(&source(&folded)), folded, "seed {seed}:\n{}", source(&folded));
+        }
+    }
+    #[test]
+    fn liveness_solvers_agree() {
+        for seed in 0..SEEDS {
+            let p = random_program(seed);
+            let mut slow = ControlFlowGraph::from(&p);
+            let mut fast = ControlFlowGraph::from(&p);
+            slow.perform_liveness_analysis();
+            fast.fast_perform_liveness_analysis();
+            assert_eq!(slow.get_live_sets(), fast.get_live_sets(), "seed {seed}:\n{}", source(&p));
+        }
+    }
+    #[test]
+    fn dynamic_oracle_holds() {
+        for seed in 0..SEEDS {
+            let p = ProgramGenerator::new(seed).with_vars(&["a", "b", "c"]).generate();
+            for input in -1..2 {
+                let interp = Interpreter::new()
+                    .with_input("a", input)
+                    .with_input("b", 1)
+                    .with_input("c", 2)
+                    .with_step_limit(500);
+                check_liveness(&p, &interp).assert_sound();
+            }
+        }
+    }
+}
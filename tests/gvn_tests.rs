@@ -0,0 +1,73 @@
+use live_crab::ast::*;
+use live_crab::gvn::global_value_numbering;
+
+mod test_utils;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_utils::*;
+
+    fn classes(cs: &[&[&str]]) -> Vec<Vec<String>> {
+        cs.iter().map(|c| c.iter().map(|s| s.to_string()).collect()).collect()
+    }
+
+    #[test]
+    fn gvn_across_blocks() {
+        let s = "x = a + b; if ( c < 1 ) { y = a + b; } return x;";
+        let got = global_value_numbering(&prog_from_str(s));
+        assert_eq!(got.get_replaced(), &vec![2]);
+        assert_eq!(got.get_classes(), classes(&[&["x_1", "y_1"]]));
+        assert!(got.get_ssa().to_string().contains("2: y_1 = x_1;"), "Got:\n{}", got.get_ssa());
+    }
+    #[test]
+    fn gvn_not_from_sibling() {
+        let s = "if ( c < 1 ) { x = a + b; } y = a + b; return y;";
+        let got = global_value_numbering(&prog_from_str(s));
+        assert!(got.get_replaced().is_empty());
+        assert!(got.get_classes().is_empty());
+    }
+    #[test]
+    fn gvn_commutative_and_identity() {
+        let s = "x = a + b; y = b + a + 0; return x + y;";
+        let got = global_value_numbering(&prog_from_str(s));
+        assert_eq!(got.get_classes(), classes(&[&["x_1", "y_1"]]));
+        let want = "0: x_1 = a_0 + b_0;\n1: y_1 = x_1;\n2: return x_1 + x_1;\n";
+        assert_eq!(got.get_ssa().to_string(), want);
+    }
+    #[test]
+    fn gvn_meaningless_phi() {
+        let s = "x = a + b; if ( c < 1 ) { y = a + b; x = y; } return x;";
+        let got = global_value_numbering(&prog_from_str(s));
+        assert_eq!(got.get_value_number("x_3"), Some(&Expr::Id(String::from("x_1"))));
+        assert_eq!(got.get_classes(), classes(&[&["x_1", "x_2", "x_3", "y_1"]]));
+        assert!(got.get_ssa().to_string().contains("4: return x_1;"), "Got:\n{}", got.get_ssa());
+    }
+    #[test]
+    fn gvn_redundant_phi() {
+        let s = "x = a; y = a; if ( c < 1 ) { x = b; y = b; } return x + y;";
+        let got = global_value_numbering(&prog_from_str(s));
+        let want = classes(&[&["a_0", "x_1", "y_1"], &["b_0", "x_2", "y_2"], &["x_3", "y_3"]]);
+        assert_eq!(got.get_classes(), want);
+        let ssa = got.get_ssa().to_string();
+        assert!(ssa.contains("5: x_3 = phi(a_0 [2], b_0 [4]);"), "Got:\n{}", ssa);
+        assert!(ssa.contains("5: return x_3 + x_3;"), "Got:\n{}", ssa);
+    }
+    #[test]
+    fn gvn_constants() {
+        let s = "x = 2; y = x * 3; z = 6; return y + z;";
+        let got = global_value_numbering(&prog_from_str(s));
+        assert_eq!(got.get_value_number("y_1"), Some(&Expr::Int(6)));
+        assert_eq!(got.get_classes(), classes(&[&["y_1", "z_1"]]));
+        assert_eq!(got.get_replaced(), &vec![1, 3]);
+        assert!(got.get_ssa().to_string().contains("3: return 12;"));
+    }
+    #[test]
+    fn gvn_loop_not_looked_through() {
+        let s = "i = 0; j = 0; while ( i < n ) { i = i + 1; j = j + 1; } return i + j;";
+        let got = global_value_numbering(&prog_from_str(s));
+        // i and j are always equal, but that needs an optimistic analysis,
+        // only their starting values are found equal
+        assert_eq!(got.get_classes(), classes(&[&["i_1", "j_1"]]));
+    }
+}
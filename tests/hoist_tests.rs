@@ -0,0 +1,103 @@
+use live_crab::ast::*;
+use live_crab::hoist::hoist_very_busy_expressions;
+use live_crab::interpreter::Interpreter;
+use live_crab::liveness::ControlFlowGraph;
+
+mod test_utils;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_utils::*;
+
+    fn a_plus_b() -> Expr {
+        Expr::BinOp(
+            Box::new(Expr::Id(String::from("a"))),
+            Operator::Plus,
+            Box::new(Expr::Id(String::from("b"))),
+        )
+    }
+
+    #[test]
+    fn very_busy_on_all_paths() {
+        let s = "if ( c < 1 ) { x = a + b; } y = a + b; return x + y;";
+        let cfg = ControlFlowGraph::from(&prog_from_str(s));
+        let got = cfg.very_busy_expressions();
+        assert!(got.get_out(0).contains(&a_plus_b()), "Got: {:?}", got.get_out(0));
+        assert!(got.get_in(0).contains(&a_plus_b()));
+        assert!(!got.get_out(2).contains(&a_plus_b()));
+        assert!(got.get_out(3).is_empty());
+    }
+    #[test]
+    fn very_busy_killed_or_missing() {
+        let s = "if ( c < 1 ) { a = 1; } y = a + b; return y;";
+        let cfg = ControlFlowGraph::from(&prog_from_str(s));
+        let got = cfg.very_busy_expressions();
+        assert!(!got.get_out(0).contains(&a_plus_b()), "a = 1 did not kill a + b");
+
+        let s = "if ( c < 1 ) { x = a + b; } return x;";
+        let cfg = ControlFlowGraph::from(&prog_from_str(s));
+        let got = cfg.very_busy_expressions();
+        assert!(!got.get_out(0).contains(&a_plus_b()));
+    }
+    #[test]
+    fn very_busy_uses_before_def() {
+        let s = "a = a + b; return a;";
+        let cfg = ControlFlowGraph::from(&prog_from_str(s));
+        let got = cfg.very_busy_expressions();
+        assert!(got.get_in(0).contains(&a_plus_b()));
+        assert!(got.get_out(0).is_empty());
+    }
+    #[test]
+    fn hoist_if() {
+        let s = "if ( c < 1 ) { x = a + b; } y = a + b; return x + y;";
+        let got = hoist_very_busy_expressions(&prog_from_str(s));
+        let want = prog_from_str("h0 = a + b; if ( c < 1 ) { x = h0; } y = h0; return x + y;");
+        assert_eq!(got.get_program(), &want, "Got:\n{}", got.get_program());
+        assert_eq!(got.get_hoisted().len(), 1);
+    }
+    #[test]
+    fn hoist_to_outermost_branch() {
+        let s = "if ( p < 1 ) { if ( q < 1 ) { x = a + b; } } y = a + b; return x + y;";
+        let got = hoist_very_busy_expressions(&prog_from_str(s));
+        let want = prog_from_str("h0 = a + b; if ( p < 1 ) { if ( q < 1 ) { x = h0; } } y = h0; return x + y;");
+        assert_eq!(got.get_program(), &want, "Got:\n{}", got.get_program());
+    }
+    #[test]
+    fn hoist_not_past_join() {
+        // y can be reached without going through the inner if
+        let s = "if ( p < 1 ) { a = 1; if ( q < 1 ) { x = a + b; } z = a + b; } y = a + b; return x + y + z;";
+        let got = hoist_very_busy_expressions(&prog_from_str(s));
+        let want = prog_from_str(
+            "if ( p < 1 ) { a = 1; h0 = a + b; if ( q < 1 ) { x = h0; } z = h0; } y = a + b; return x + y + z;",
+        );
+        assert_eq!(got.get_program(), &want, "Got:\n{}", got.get_program());
+    }
+    #[test]
+    fn no_hoist_when_not_busy() {
+        let s = "if ( c < 1 ) { a = 1; x = a + b; } y = a + b; return x + y;";
+        let prog = prog_from_str(s);
+        let got = hoist_very_busy_expressions(&prog);
+        assert_eq!(got.get_program(), &prog, "Got:\n{}", got.get_program());
+        assert!(got.get_hoisted().is_empty());
+    }
+    #[test]
+    fn no_hoist_loop_condition() {
+        let s = "while ( i < n ) { x = a + b; i = i + 1; } y = a + b; return x + y;";
+        let prog = prog_from_str(s);
+        let got = hoist_very_busy_expressions(&prog);
+        assert_eq!(got.get_program(), &prog, "Got:\n{}", got.get_program());
+    }
+    #[test]
+    fn no_hoist_past_branch_to_exit() {
+        // The false branch ends the program without dividing by zero
+        let s = "a = 5; b = 0; if ( a < 2 ) { x = a / b; y = a / b; return x + y; }";
+        let prog = prog_from_str(s);
+        let cfg = ControlFlowGraph::from(&prog);
+        assert!(cfg.very_busy_expressions().get_out(2).is_empty());
+        let got = hoist_very_busy_expressions(&prog);
+        assert_eq!(got.get_program(), &prog, "Got:\n{}", got.get_program());
+        assert!(Interpreter::new().run(&prog).is_ok());
+        assert_eq!(Interpreter::new().run(got.get_program()), Interpreter::new().run(&prog));
+    }
+}
@@ -0,0 +1,76 @@
+use live_crab::ast::*;
+use live_crab::generate::random_program;
+use live_crab::lexer::Lexer;
+use live_crab::liveness::ControlFlowGraph;
+use live_crab::parser::Parser;
+
+mod test_utils;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cfg_from_str(s: &str) -> ControlFlowGraph {
+        let lexer = Lexer::new(s);
+        let tokens = lexer.tokenize();
+        let mut parser = Parser::new(tokens);
+        let prog: Program = parser.parse();
+        let mut cfg = ControlFlowGraph::from(&prog);
+        cfg.fast_perform_liveness_analysis();
+        cfg
+    }
+
+    #[test]
+    fn color_without_spills() {
+        let graph = cfg_from_str("a = 0; do { b = a + 1; c = c + 1; a = b * 2; } while ( a < 9 ); return c;")
+            .interference_graph();
+        let got = graph.color(2);
+        assert!(got.get_spilled().is_empty());
+        assert_eq!(got.get_k(), 2);
+        for (a, b) in graph.get_edges() {
+            assert_ne!(got.get_color(a), got.get_color(b));
+        }
+        // a and b never interfere, so they share a register
+        assert_eq!(got.get_color("a"), got.get_color("b"));
+    }
+    #[test]
+    fn graph_without_liveness() {
+        let s = "a = 1; b = 2; c = 3; return a + b + c;";
+        let prog = Parser::new(Lexer::new(s).tokenize()).parse();
+        let cfg = ControlFlowGraph::from(&prog);
+        assert!(!cfg.is_analyzed());
+        assert_eq!(cfg.interference_graph(), cfg_from_str(s).interference_graph());
+        assert!(cfg.interference_graph().interferes("a", "c"));
+    }
+    #[test]
+    fn color_spills_clique() {
+        // a, b and c are all live at once
+        let graph = cfg_from_str("a = 1; b = 2; c = 3; return a + b + c;").interference_graph();
+        let got = graph.color(2);
+        assert_eq!(got.get_spilled().len(), 1);
+        assert_eq!(got.get_colors().len(), 2);
+        let got = graph.color(3);
+        assert!(got.get_spilled().is_empty());
+        let colors: Vec<usize> = got.get_colors().values().cloned().collect();
+        assert_eq!(colors, vec![2, 1, 0]);
+    }
+    #[test]
+    fn color_generated_programs() {
+        for seed in 0..100 {
+            let mut cfg = ControlFlowGraph::from(&random_program(seed));
+            cfg.fast_perform_liveness_analysis();
+            let graph = cfg.interference_graph();
+            for k in 1..4 {
+                let got = graph.color(k);
+                assert_eq!(got.get_colors().len() + got.get_spilled().len(), graph.get_vars().len());
+                for (a, b) in graph.get_edges() {
+                    if let (Some(ca), Some(cb)) = (got.get_color(a), got.get_color(b)) {
+                        assert_ne!(ca, cb, "{a} and {b}");
+                    }
+                }
+                assert!(got.get_colors().values().all(|c| *c < k));
+            }
+            assert!(graph.color(graph.get_vars().len().max(1)).get_spilled().is_empty());
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use live_crab::ast::*;
+use live_crab::constprop::propagate_constants;
+use live_crab::interpreter::{eval_expr, Env, Interpreter, RuntimeError};
+use live_crab::liveness::ControlFlowGraph;
+use live_crab::licm::hoist_loop_invariants;
+
+mod test_utils;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_utils::*;
+
+    fn run(s: &str) -> Result<Option<i32>, RuntimeError> {
+        Interpreter::new().run(&prog_from_str(s)).map(|e| e.get_returned())
+    }
+
+    #[test]
+    fn straight_line() {
+        assert_eq!(run("a = 2; b = a * 3; return b - 1;"), Ok(Some(5)));
+        assert_eq!(run("a = 7; b = a % 4; c = a / 2; return b + c;"), Ok(Some(6)));
+        assert_eq!(run("a = 1;"), Ok(None));
+    }
+    #[test]
+    fn operators_left_to_right() {
+        // there is no precedence, 2 + 3 * 4 is (2 + 3) * 4
+        assert_eq!(run("return 2 + 3 * 4;"), Ok(Some(20)));
+        assert_eq!(run("return 1 < 2;"), Ok(Some(1)));
+        assert_eq!(run("return 2 < 1;"), Ok(Some(0)));
+    }
+    #[test]
+    fn control_flow() {
+        let s = "i = 0; s = 0; while ( i < 5 ) { i = i + 1; if ( i % 2 < 1 ) { s = s + i; } } return s;";
+        assert_eq!(run(s), Ok(Some(6)));
+        let s = "i = 10; do { i = i + 1; } while ( i < 5 ); return i;";
+        assert_eq!(run(s), Ok(Some(11)));
+        let s = "i = 0; while ( i < 10 ) { if ( 4 < i ) { return i; } i = i + 1; } return 0;";
+        assert_eq!(run(s), Ok(Some(5)));
+    }
+    #[test]
+    fn steps_count_nodes() {
+        let got = Interpreter::new().run(&prog_from_str("i = 0; while ( i < 2 ) { i = i + 1; } return i;")).unwrap();
+        // i = 0, 3 conditions, 2 increments, return
+        assert_eq!(got.get_steps(), 7);
+        assert_eq!(got.get_env(), &Env::from([(String::from("i"), 2)]));
+    }
+    #[test]
+    fn division_by_zero() {
+        assert_eq!(run("a = 0; return 1 / a;"), Err(RuntimeError::DivisionByZero));
+        assert_eq!(run("return 1 % 0;"), Err(RuntimeError::DivisionByZero));
+    }
+    #[test]
+    fn overflow_wraps() {
+        assert_eq!(run("a = 2147483647; return a + 1;"), Ok(Some(i32::MIN)));
+        assert_eq!(run("a = 0 - 2147483647; b = a - 1; return b / -1;"), Ok(Some(i32::MIN)));
+    }
+    #[test]
+    fn uninitialized_read() {
+        assert_eq!(run("a = b + 1; return a;"), Err(RuntimeError::Uninitialized(String::from("b"))));
+        assert_eq!(
+            RuntimeError::Uninitialized(String::from("b")).to_string(),
+            "read of uninitialized variable `b`"
+        );
+    }
+    #[test]
+    fn inputs() {
+        let p = prog_from_str("a = b * 2; return a + c;");
+        let got = Interpreter::new().with_input("b", 4).with_input("c", 1).run(&p);
+        assert_eq!(got.map(|e| e.get_returned()), Ok(Some(9)));
+        let inputs = Env::from([(String::from("b"), 1), (String::from("c"), 1)]);
+        let got = Interpreter::new().with_inputs(&inputs).run(&p);
+        assert_eq!(got.map(|e| e.get_returned()), Ok(Some(3)));
+    }
+    #[test]
+    fn step_limit() {
+        let p = prog_from_str("i = 0; while ( 0 < 1 ) { i = i + 1; } return i;");
+        assert_eq!(Interpreter::new().run(&p), Err(RuntimeError::StepLimitExceeded(10_000)));
+        let p = prog_from_str("a = 1; b = 2; return a + b;");
+        assert_eq!(Interpreter::new().with_step_limit(2).run(&p), Err(RuntimeError::StepLimitExceeded(2)));
+        assert!(Interpreter::new().with_step_limit(3).run(&p).is_ok());
+    }
+    #[test]
+    fn eval_expression() {
+        let env = Env::from([(String::from("x"), 3)]);
+        let e = Expr::BinOp(Box::new(Expr::Id(String::from("x"))), Operator::Mult, Box::new(Expr::Int(5)));
+        assert_eq!(eval_expr(&e, &env), Ok(15));
+    }
+    #[test]
+    fn optimizations_preserve_behavior() {
+        let s = "a = 3; b = a + 4; i = 0; s = 0; while ( i < n ) { t = a * b; s = s + t; i = i + 1; } return s;";
+        let p = prog_from_str(s);
+        for n in 0..4 {
+            let interp = Interpreter::new().with_input("n", n);
+            let want = interp.run(&p).unwrap().get_returned();
+            let folded = propagate_constants(&p);
+            let hoisted = hoist_loop_invariants(&p);
+            assert_eq!(interp.run(folded.get_program()).unwrap().get_returned(), want);
+            assert_eq!(interp.run(hoisted.get_program()).unwrap().get_returned(), want);
+        }
+    }
+    #[test]
+    fn cfg_trace() {
+        let p = prog_from_str("i = 0; while ( i < 2 ) { i = i + 1; } return i;");
+        let trace = Interpreter::new().trace(&ControlFlowGraph::from(&p));
+        assert_eq!(trace.get_nodes(), vec![0, 1, 2, 1, 2, 1, 3]);
+        assert_eq!(trace.get_steps()[4].get_env(), &Env::from([(String::from("i"), 2)]));
+        assert_eq!(trace.get_outcome().as_ref().map(|e| e.get_returned()), Ok(Some(2)));
+        let want = "step\tnode\tenv\n0\t0\ti = 0\n1\t1\ti = 0\n2\t2\ti = 1\n3\t1\ti = 1\n4\t2\ti = 2\n5\t1\ti = 2\n6\t3\ti = 2\nreturned 2\n";
+        assert_eq!(trace.to_string(), want);
+    }
+    #[test]
+    fn cfg_trace_matches_ast() {
+        let progs = [
+            "a = 1; if ( a ) { b = 2; } c = 3;",
+            "i = 0; do { i = i + 1; if ( 2 < i ) { return i; } } while ( i < 10 ); return 0;",
+            "i = 0; while ( i < n ) { j = 0; while ( j < i ) { j = j + 1; } i = i + 1; } return j;",
+            "a = 1; b = a / n; return b;",
+            "i = 0; while ( i < n ) { } return i;",
+        ];
+        for s in progs {
+            let p = prog_from_str(s);
+            for n in 0..3 {
+                let interp = Interpreter::new().with_input("n", n).with_step_limit(50);
+                let ast = interp.trace_program(&p);
+                let cfg = interp.trace(&ControlFlowGraph::from(&p));
+                assert_eq!(ast, cfg, "{s} with n = {n}");
+                let run = interp.run(&p);
+                assert_eq!(&run, ast.get_outcome());
+            }
+        }
+    }
+    #[test]
+    fn cfg_trace_stops_at_error() {
+        let p = prog_from_str("a = 1; b = a / 0; return b;");
+        let trace = Interpreter::new().trace(&ControlFlowGraph::from(&p));
+        assert_eq!(trace.get_nodes(), vec![0]);
+        assert_eq!(trace.get_outcome(), &Err(RuntimeError::DivisionByZero));
+        assert!(trace.to_string().ends_with("error: division by zero\n"));
+    }
+    #[test]
+    fn cfg_trace_normalized() {
+        // Empty nodes are visited but change nothing
+        let p = prog_from_str("while ( i < 2 ) { i = i + 1; } return i;");
+        let mut cfg = ControlFlowGraph::from(&p);
+        cfg.add_loop_preheaders();
+        let trace = Interpreter::new().with_input("i", 1).trace(&cfg);
+        assert_eq!(trace.get_nodes(), vec![0, 1, 2, 1, 3]);
+        assert_eq!(trace.get_steps()[0].get_env(), &Env::from([(String::from("i"), 1)]));
+        assert_eq!(trace.get_outcome().as_ref().map(|e| e.get_returned()), Ok(Some(2)));
+    }
+}
@@ -0,0 +1,103 @@
+use live_crab::generate::ProgramGenerator;
+use live_crab::json::*;
+use live_crab::lexer::Lexer;
+use live_crab::liveness::ControlFlowGraph;
+
+mod test_utils;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_utils::*;
+
+    #[test]
+    fn prints_and_parses_values() {
+        let src = r#"{"a": [1, -2, true, null], "b": {"c": "x\"\\\n\u00e9\ud83e\udd80"}, "d": []}"#;
+        let j = Json::parse(src).unwrap();
+        assert_eq!(j.get("a").unwrap().as_array().unwrap()[1], Json::Int(-2));
+        assert_eq!(j.get("b").unwrap().get("c").unwrap().as_str(), Some("x\"\\\né🦀"));
+        assert_eq!(j.to_string(), r#"{"a":[1,-2,true,null],"b":{"c":"x\"\\\né🦀"},"d":[]}"#);
+        assert_eq!(Json::parse(&j.to_string()).unwrap(), j);
+        assert_eq!(Json::parse(&j.pretty()).unwrap(), j);
+        assert_eq!(
+            j.pretty(),
+            "{\n  \"a\": [1, -2, true, null],\n  \"b\": {\n    \"c\": \"x\\\"\\\\\\né🦀\"\n  },\n  \"d\": []\n}"
+        );
+    }
+    #[test]
+    fn parse_errors() {
+        for (src, msg, offset) in [
+            ("[1, 2", "expected `,` or `]`", 5),
+            ("{\"a\" 1}", "expected `:`, found `1`", 5),
+            ("1.5", "only integers are supported", 1),
+            ("\"abc", "unterminated string", 4),
+            ("[1] 2", "trailing characters", 4),
+            ("nul", "expected a value", 0),
+            ("\"\\ud800\"", "unpaired surrogate", 7),
+        ] {
+            let e = Json::parse(src).unwrap_err();
+            assert_eq!((e.get_msg().as_str(), e.get_offset()), (msg, Some(offset)), "{src}");
+        }
+    }
+    #[test]
+    fn programs_round_trip() {
+        let p = prog_from_str("a = 1; if (a < 2) { a = a - 3; } while (a) { a = a % 2; } do { return a / 2; } while (a);");
+        let doc = document("program", p.to_json()).to_string();
+        assert_eq!(read_program(&doc), Ok(p));
+        for p in ProgramGenerator::new(7).take(50) {
+            assert_eq!(read_program(&document("program", p.to_json()).pretty()), Ok(p));
+        }
+    }
+    #[test]
+    fn reading_checks_the_document() {
+        let p = prog_from_str("return 1;");
+        let wrong_kind = document("cfg", p.to_json()).to_string();
+        assert_eq!(read_program(&wrong_kind).unwrap_err().to_string(), "expected a program document, found cfg");
+        let future = wrong_kind.replace("\"version\":1", "\"version\":2").replace("cfg", "program");
+        assert_eq!(read_program(&future).unwrap_err().to_string(), "unsupported version 2, expected 1");
+        let bad = r#"{"version":1,"kind":"program","data":{"stmts":[{"type":"assign","target":{"type":"int","value":1},"value":{"type":"int","value":2}}]}}"#;
+        assert_eq!(read_program(bad).unwrap_err().to_string(), "the target of an assignment should be an id");
+        let bad = r#"{"version":1,"kind":"program","data":{"stmts":[{"type":"return","value":{"type":"binop","op":"^"}}]}}"#;
+        assert!(read_program(bad).unwrap_err().to_string().starts_with("a binop is missing `left`"));
+    }
+    #[test]
+    fn cfg_json() {
+        let p = prog_from_str("a = 1; if (a < 2) { a = a + 1; } return a;");
+        let j = ControlFlowGraph::from(&p).to_json();
+        assert_eq!(j.get("analyzed"), Some(&Json::Bool(false)));
+        assert_eq!(j.get("nodes").unwrap().as_array().unwrap()[1].get("live_in"), None);
+
+        let mut cfg = ControlFlowGraph::from(&p);
+        cfg.fast_perform_liveness_analysis();
+        let j = cfg.to_json();
+        let cond = &j.get("nodes").unwrap().as_array().unwrap()[1];
+        assert_eq!(
+            cond.to_string(),
+            r#"{"id":1,"kind":"condition","statement":"if a < 2","uses":["a"],"defs":[],"preds":[0],"succs":[2,3],"live_in":["a"],"live_out":["a"]}"#
+        );
+        let edges: Vec<String> = j.get("edges").unwrap().as_array().unwrap().iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            edges,
+            vec![
+                r#"{"from":0,"to":1,"live":["a"]}"#,
+                r#"{"from":1,"to":2,"branch":true,"live":["a"]}"#,
+                r#"{"from":1,"to":3,"branch":false,"live":["a"]}"#,
+                r#"{"from":2,"to":3,"live":["a"]}"#,
+            ]
+        );
+    }
+    #[test]
+    fn tokens_json() {
+        let tokens = Lexer::new("x = -1;").tokenize_with_spans().unwrap();
+        assert_eq!(
+            tokens_to_json(&tokens).to_string(),
+            concat!(
+                r#"[{"token":{"type":"id","value":"x"},"span":{"start":0,"end":1}},"#,
+                r#"{"token":{"type":"equals"},"span":{"start":2,"end":3}},"#,
+                r#"{"token":{"type":"minus"},"span":{"start":4,"end":5}},"#,
+                r#"{"token":{"type":"int","value":1},"span":{"start":5,"end":6}},"#,
+                r#"{"token":{"type":"semicolon"},"span":{"start":6,"end":7}}]"#
+            )
+        );
+    }
+}
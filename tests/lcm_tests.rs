@@ -0,0 +1,130 @@
+use live_crab::ast::*;
+use live_crab::interpreter::Interpreter;
+use live_crab::lcm::{lazy_code_motion, LcmPoint};
+use live_crab::liveness::ControlFlowGraph;
+
+mod test_utils;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_utils::*;
+
+    fn a_plus_b() -> Expr {
+        Expr::BinOp(
+            Box::new(Expr::Id(String::from("a"))),
+            Operator::Plus,
+            Box::new(Expr::Id(String::from("b"))),
+        )
+    }
+
+    #[test]
+    fn anticipated_is_very_busy() {
+        let s = "if ( c < 1 ) { x = a + b; } y = a + b; return y;";
+        let cfg = ControlFlowGraph::from(&prog_from_str(s));
+        let got = cfg.anticipated_expressions();
+        assert!(got.get_out(0).contains(&a_plus_b()));
+        assert_eq!(got.get_ins(), cfg.very_busy_expressions().get_ins());
+    }
+    #[test]
+    fn nothing_anticipated_at_branch_to_exit() {
+        // the false branch of the trailing if ends the program
+        let s = "b = 0; if ( b < 0 ) { x = a / b; y = a / b; return x + y; }";
+        let prog = prog_from_str(s);
+        let cfg = ControlFlowGraph::from(&prog);
+        let a_div_b = Expr::BinOp(
+            Box::new(Expr::Id(String::from("a"))),
+            Operator::Div,
+            Box::new(Expr::Id(String::from("b"))),
+        );
+        assert!(cfg.anticipated_expressions().get_out(1).is_empty());
+        assert!(!cfg.lazy_code_motion().get_anticipated()[1].contains(&a_div_b));
+
+        let got = lazy_code_motion(&prog);
+        assert_eq!(got.get_inserted_at(LcmPoint::Node(1)), Vec::<&Expr>::new());
+        let run = Interpreter::new().with_input("a", 1);
+        assert_eq!(run.run(got.get_program()), run.run(&prog), "Got:\n{}", got.get_program());
+    }
+    #[test]
+    fn split_edges_into_joins() {
+        let s = "if ( c < 1 ) { x = a + b; } y = a + b; return y;";
+        let cfg = ControlFlowGraph::from(&prog_from_str(s));
+        let got = cfg.lazy_code_motion();
+        let want = vec![
+            LcmPoint::Node(0),
+            LcmPoint::Node(1),
+            LcmPoint::Node(2),
+            LcmPoint::Node(3),
+            LcmPoint::Entry,
+            LcmPoint::Edge(0, 2),
+            LcmPoint::Edge(1, 2),
+        ];
+        assert_eq!(got.get_points(), &want);
+        assert_eq!(got.get_succs()[0], vec![1, 5]);
+        assert_eq!(got.get_succs()[5], vec![2]);
+        // a + b goes on the edge skipping the if body
+        assert_eq!(got.get_insertions(&a_plus_b()), vec![LcmPoint::Node(1), LcmPoint::Edge(0, 2)]);
+        assert_eq!(got.get_replacements(&a_plus_b()), vec![1, 2]);
+    }
+    #[test]
+    fn lcm_full_redundancy() {
+        let s = "x = a + b; y = a + b; return x + y;";
+        let got = lazy_code_motion(&prog_from_str(s));
+        let want = prog_from_str("t0 = a + b; x = t0; y = t0; return x + y;");
+        assert_eq!(got.get_program(), &want, "Got:\n{}", got.get_program());
+        assert_eq!(got.get_inserted(), &vec![(LcmPoint::Node(0), a_plus_b())]);
+        assert_eq!(got.get_deleted(), &vec![(0, a_plus_b()), (1, a_plus_b())]);
+    }
+    #[test]
+    fn lcm_out_of_do_while() {
+        let s = "i = 0; do { x = a + b; i = i + 1; } while ( i < n ); return x;";
+        let got = lazy_code_motion(&prog_from_str(s));
+        let want = prog_from_str("i = 0; t0 = a + b; do { x = t0; i = i + 1; } while ( i < n ); return x;");
+        assert_eq!(got.get_program(), &want, "Got:\n{}", got.get_program());
+        assert_eq!(got.get_inserted_at(LcmPoint::Edge(0, 1)), vec![&a_plus_b()]);
+        assert_eq!(got.get_deleted_at(1), vec![&a_plus_b()]);
+    }
+    #[test]
+    fn lcm_do_while_first() {
+        let s = "do { x = a + b; i = i + 1; } while ( i < n ); return x;";
+        let got = lazy_code_motion(&prog_from_str(s));
+        let want = prog_from_str("t0 = a + b; do { x = t0; i = i + 1; } while ( i < n ); return x;");
+        assert_eq!(got.get_program(), &want, "Got:\n{}", got.get_program());
+        assert_eq!(got.get_inserted_at(LcmPoint::Entry), vec![&a_plus_b()]);
+    }
+    #[test]
+    fn lcm_not_out_of_while() {
+        // the loop may not run, so a + b is not anticipated before it
+        let s = "i = 0; while ( i < n ) { x = a + b; i = i + 1; } return x;";
+        let prog = prog_from_str(s);
+        let got = lazy_code_motion(&prog);
+        assert_eq!(got.get_program(), &prog, "Got:\n{}", got.get_program());
+        assert!(got.get_inserted().is_empty());
+    }
+    #[test]
+    fn lcm_redundant_after_join() {
+        let s = "x = a + b; if ( c < 1 ) { a = 1; y = a + b; } z = a + b; return z;";
+        let got = lazy_code_motion(&prog_from_str(s));
+        let want = prog_from_str("t0 = a + b; x = t0; if ( c < 1 ) { a = 1; t0 = a + b; y = t0; } z = t0; return z;");
+        assert_eq!(got.get_program(), &want, "Got:\n{}", got.get_program());
+        assert_eq!(got.get_deleted_at(4), vec![&a_plus_b()]);
+    }
+    #[test]
+    fn lcm_out_of_while_when_used_after() {
+        // a + b is computed after the loop anyway, so it is anticipated before
+        // it and leaves the body
+        let s = "while ( i < n ) { i = i + 1; y = a + b; } z = a + b; return y + z;";
+        let got = lazy_code_motion(&prog_from_str(s));
+        let want = prog_from_str("t0 = a + b; while ( i < n ) { i = i + 1; y = t0; } z = t0; return y + z;");
+        assert_eq!(got.get_program(), &want, "Got:\n{}", got.get_program());
+        assert_eq!(got.get_inserted(), &vec![(LcmPoint::Entry, a_plus_b())]);
+    }
+    #[test]
+    fn lcm_skips_if_false_edge() {
+        let s = "if ( c < 1 ) { x = a + b; } y = a + b; return x + y;";
+        let prog = prog_from_str(s);
+        let got = lazy_code_motion(&prog);
+        assert_eq!(got.get_program(), &prog, "Got:\n{}", got.get_program());
+        assert_eq!(got.get_skipped(), &vec![a_plus_b()]);
+    }
+}
@@ -1,3 +1,6 @@
+// The expected tokens are pushed one per line on purpose
+#![allow(clippy::vec_init_then_push)]
+
 use live_crab::lexer::Lexer;
 use live_crab::lexer::Token;
 
@@ -284,4 +287,20 @@ mod tests {
         want.push(Token::Semicolon);
         assert_eq!(got, &want, "Got: {:?}\n\n", got);
     }
+    #[test]
+    fn try_tokenize_reports_errors() {
+        let got = Lexer::new("a = 1;\n").try_tokenize();
+        assert_eq!(got.map(|t| t.len()), Ok(4));
+        let err = Lexer::new("a = 1 : 2;").try_tokenize().unwrap_err();
+        assert_eq!(err.get_offset(), 6);
+        assert_eq!(err.to_string(), "unexpected character `:` at offset 6");
+        let err = Lexer::new("a = 99999999999;").try_tokenize().unwrap_err();
+        assert_eq!(err.get_offset(), 4);
+        assert_eq!(err.get_msg(), "integer literal too large");
+        let err = Lexer::new("a = 2²;").try_tokenize().unwrap_err();
+        assert_eq!(err.get_offset(), 5);
+        assert_eq!(err.get_msg(), "unexpected character `²`");
+        // tokenize stops where try_tokenize fails
+        assert_eq!(Lexer::new("a = 1 : 2;").tokenize().len(), 3);
+    }
 }
@@ -0,0 +1,180 @@
+use live_crab::interpreter::Interpreter;
+use live_crab::licm::hoist_loop_invariants;
+use live_crab::liveness::ControlFlowGraph;
+
+mod test_utils;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_utils::*;
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn reaching_defs_loop() {
+        let s = "i = 0; while ( i < 9 ) { i = i + 1; } return i;";
+        let cfg = ControlFlowGraph::from(&prog_from_str(s));
+        let reaching = cfg.reaching_definitions();
+        //0: i = 0;
+        //1: if i < 9
+        //2: i = i + 1;
+        //3: return i;
+        assert_eq!(cfg.reaching_defs_of(&reaching, 1, "i").len(), 2);
+        assert!(reaching.get_in(3).contains(&0));
+        assert!(reaching.get_in(3).contains(&2));
+        assert!(!reaching.get_out(2).contains(&0));
+        // sets iterate in order, so results print the same on every run
+        assert_eq!(reaching.get_in(3).iter().collect::<Vec<_>>(), vec![&0, &2]);
+        assert_eq!(cfg.reaching_defs_of(&reaching, 1, "i"), BTreeSet::from([0, 2]));
+    }
+    #[test]
+    fn hoist_dead_after_while() {
+        let s = "i = 0;
+        while ( i < n ) {
+            t = a * b;
+            i = i + t;
+        }
+        return i;";
+        let got = hoist_loop_invariants(&prog_from_str(s));
+        let want = prog_from_str(
+            "i = 0;
+            t = a * b;
+            while ( i < n ) {
+                i = i + t;
+            }
+            return i;",
+        );
+        assert_eq!(got.get_program(), &want, "Got:\n{}", got.get_program());
+        assert_eq!(got.get_hoisted().len(), 1);
+    }
+    #[test]
+    fn no_hoist_live_after_while() {
+        // t may never be assigned if the loop is not entered
+        let s = "i = 0;
+        while ( i < n ) {
+            t = a * b;
+            i = i + t;
+        }
+        return t;";
+        let prog = prog_from_str(s);
+        let got = hoist_loop_invariants(&prog);
+        assert_eq!(got.get_program(), &prog);
+        assert!(got.get_hoisted().is_empty());
+    }
+    #[test]
+    fn hoist_do_while_dominating_exit() {
+        let s = "i = 0;
+        do {
+            t = a + 1;
+            u = t * 2;
+            i = i + u;
+        } while ( i < n );
+        return t;";
+        let got = hoist_loop_invariants(&prog_from_str(s));
+        let want = prog_from_str(
+            "i = 0;
+            t = a + 1;
+            u = t * 2;
+            do {
+                i = i + u;
+            } while ( i < n );
+            return t;",
+        );
+        assert_eq!(got.get_program(), &want, "Got:\n{}", got.get_program());
+    }
+    #[test]
+    fn no_hoist_variant_or_redefined() {
+        let s = "i = 0;
+        do {
+            t = i + 1;
+            u = 2;
+            i = i + t;
+            u = u + 1;
+        } while ( i < n );
+        return u;";
+        let prog = prog_from_str(s);
+        let got = hoist_loop_invariants(&prog);
+        assert_eq!(got.get_program(), &prog, "Got:\n{}", got.get_program());
+    }
+    #[test]
+    fn no_hoist_division_out_of_while() {
+        let s = "i = 0;
+        while ( i < n ) {
+            t = a / b;
+            i = i + t;
+        }
+        return i;";
+        let prog = prog_from_str(s);
+        let got = hoist_loop_invariants(&prog);
+        assert_eq!(got.get_program(), &prog);
+    }
+    #[test]
+    fn no_hoist_division_out_of_trailing_while() {
+        // The loop is left through the cond's branch to the exit
+        let s = "i = 0;
+        while ( i < n ) {
+            t = a / b;
+            i = i + t;
+        }";
+        let prog = prog_from_str(s);
+        let got = hoist_loop_invariants(&prog);
+        assert_eq!(got.get_program(), &prog, "Got:\n{}", got.get_program());
+        let run = Interpreter::new().with_input("n", 0).with_input("b", 0);
+        assert_eq!(run.run(got.get_program()), run.run(&prog));
+    }
+    #[test]
+    fn hoist_through_nested_loops() {
+        let s = "i = 0;
+        while ( i < n ) {
+            j = 0;
+            while ( j < n ) {
+                t = a * b;
+                j = j + t;
+            }
+            i = i + 1;
+        }
+        return i;";
+        let got = hoist_loop_invariants(&prog_from_str(s));
+        let want = prog_from_str(
+            "i = 0;
+            t = a * b;
+            while ( i < n ) {
+                j = 0;
+                while ( j < n ) {
+                    j = j + t;
+                }
+                i = i + 1;
+            }
+            return i;",
+        );
+        assert_eq!(got.get_program(), &want, "Got:\n{}", got.get_program());
+        assert_eq!(got.get_hoisted().len(), 2);
+    }
+    #[test]
+    fn hoist_do_while_sharing_header() {
+        // the while cond heads both loops, its preheader goes before the do
+        let s = "i = 0;
+        do {
+            while ( i < n ) {
+                t = a + b;
+                i = i + t;
+            }
+            j = j + 1;
+        } while ( j < m );
+        return i;";
+        let got = hoist_loop_invariants(&prog_from_str(s));
+        let want = prog_from_str(
+            "i = 0;
+            t = a + b;
+            do {
+                while ( i < n ) {
+                    i = i + t;
+                }
+                j = j + 1;
+            } while ( j < m );
+            return i;",
+        );
+        assert_eq!(got.get_program(), &want, "Got:\n{}", got.get_program());
+        assert_eq!(got.get_hoisted(), &prog_from_str("t = a + b;").stmts);
+    }
+}
@@ -1,6 +1,7 @@
 use live_crab::ast::*;
 use live_crab::lexer::Lexer;
 use live_crab::liveness::ControlFlowGraph;
+use live_crab::liveness::LivenessStats;
 use live_crab::liveness::NodeKind;
 use live_crab::parser::Parser;
 
@@ -335,6 +336,26 @@ mod tests {
         assert!(cond_branch_false, "Cond did not branch to after the body");
     }
     #[test]
+    fn succ_while_back_edge() {
+        let s = "a = 901;
+        while ( a < 4 ) {
+            a = a+2;
+            a = a-1;
+        } 
+        return a;";
+        let lexer = Lexer::new(s);
+        let tokens = lexer.tokenize();
+        let mut parser = Parser::new(tokens);
+        let prog = parser.parse();
+        let cfg = ControlFlowGraph::from(&prog);
+        let body_to_cond = cfg.get_node(3).get_succs().contains(&1);
+        let body_not_to_rest = !cfg.get_node(3).get_succs().contains(&4);
+        let cond_pred_body = cfg.get_node(1).get_preds().contains(&3);
+        assert!(body_to_cond, "Last node in body did not loop back to the cond");
+        assert!(body_not_to_rest, "Last node in body skipped the cond");
+        assert!(cond_pred_body, "Last node in body was not a pred to the cond");
+    }
+    #[test]
     fn succ_if() {
         let s = "i = 0;
         if ( a < 1337 ) {
@@ -591,4 +612,123 @@ mod tests {
         assert_eq!(a_live.len(), 3);
     }
 
+    // iteration traces
+    #[test]
+    fn trace_ends_at_the_fixpoint() {
+        let s = get_str_from_path("examples/book_ex").unwrap();
+        let prog = Parser::new(Lexer::new(&s).tokenize()).parse();
+        for fast in [false, true] {
+            let mut cfg = ControlFlowGraph::from(&prog);
+            let trace = match fast {
+                true => cfg.fast_trace_liveness_analysis(),
+                false => cfg.trace_liveness_analysis(),
+            };
+            let last = trace.get_iterations().last().unwrap();
+            assert!(last.get_changed_nodes().is_empty());
+            for idx in 0..cfg.get_nodes().len() {
+                assert_eq!(last.get_live_in(idx), cfg.get_live_in(idx));
+                assert_eq!(last.get_live_out(idx), cfg.get_live_out(idx));
+            }
+            // every other pass changed something
+            let n = trace.get_iteration_count();
+            assert!(trace.get_iterations()[..n - 1].iter().all(|it| !it.get_changed_nodes().is_empty()));
+        }
+    }
+    #[test]
+    fn trace_marks_changes() {
+        // a = 1; return a;
+        let prog = Parser::new(Lexer::new("a = 1; return a;").tokenize()).parse();
+        let mut cfg = ControlFlowGraph::from(&prog);
+        let trace = cfg.trace_liveness_analysis();
+        assert_eq!(trace.get_order(), &vec![0, 1]);
+        assert_eq!(trace.get_iteration_count(), 3);
+
+        // in order, node 0 sees the in of node 1 only on the second pass
+        let first = &trace.get_iterations()[0];
+        assert_eq!(first.get_changed_nodes(), vec![1]);
+        assert!(first.in_changed(1) && !first.out_changed(1));
+        let second = &trace.get_iterations()[1];
+        assert_eq!(second.get_changed_nodes(), vec![0]);
+        assert!(second.out_changed(0) && !second.in_changed(0));
+        assert!(second.get_live_out(0).contains("a"));
+
+        let mut cfg = ControlFlowGraph::from(&prog);
+        let trace = cfg.fast_trace_liveness_analysis();
+        assert_eq!(trace.get_order(), &vec![1, 0]);
+        assert_eq!(trace.get_iteration_count(), 2);
+    }
+    #[test]
+    fn stats_count_the_work() {
+        let s = get_str_from_path("examples/book_ex").unwrap();
+        let prog = Parser::new(Lexer::new(&s).tokenize()).parse();
+        let mut cfg = ControlFlowGraph::from(&prog);
+        let trace = cfg.clone().fast_trace_liveness_analysis();
+        let mut stats = LivenessStats::new();
+        cfg.fast_perform_liveness_analysis_with(&mut stats);
+
+        let nodes = cfg.get_nodes().len();
+        assert_eq!(stats.get_iterations(), trace.get_iteration_count());
+        assert_eq!(stats.get_nodes_visited(), nodes * trace.get_iteration_count());
+        let changes: usize = trace
+            .get_iterations()
+            .iter()
+            .map(|it| (0..nodes).map(|i| it.in_changed(i) as usize + it.out_changed(i) as usize).sum::<usize>())
+            .sum();
+        assert_eq!(stats.get_set_changes(), changes);
+
+        // the naive order needs more passes for the same result
+        let mut naive = LivenessStats::new();
+        let mut slow = ControlFlowGraph::from(&prog);
+        slow.perform_liveness_analysis_with(&mut naive);
+        assert!(naive.get_iterations() > stats.get_iterations());
+        assert_eq!(slow.get_live_sets(), cfg.get_live_sets());
+        let want = format!(
+            "{} iterations, {} nodes visited, {} set changes",
+            naive.get_iterations(),
+            naive.get_nodes_visited(),
+            naive.get_set_changes()
+        );
+        assert_eq!(naive.to_string(), want);
+    }
+
+    // output order
+    #[test]
+    fn display_is_sorted() {
+        let prog = Parser::new(Lexer::new("a = 3; while (a) { a = a - 1; } return a;").tokenize()).parse();
+        let cfg = ControlFlowGraph::from(&prog);
+        assert_eq!(
+            cfg.to_string(),
+            "0: a = 3;
+\tdef: {\"a\"}, use: {}
+\tpred: {}, succ: {1}
+1: if a
+\tdef: {}, use: {\"a\"}
+\tpred: {0, 2}, succ: {2, 3}
+2: a = a - 1;
+\tdef: {\"a\"}, use: {\"a\"}
+\tpred: {1}, succ: {1}
+3: return a;
+\tdef: {}, use: {\"a\"}
+\tpred: {1}, succ: {}
+"
+        );
+    }
+    #[test]
+    fn dot_is_sorted() {
+        let s = "z = 1; y = 2; if (z < y) { x = z; } return x + y + z;";
+        let prog = Parser::new(Lexer::new(s).tokenize()).parse();
+        let mut cfg = ControlFlowGraph::from(&prog);
+        cfg.fast_perform_liveness_analysis();
+        let dot = cfg.generate_dot();
+        assert!(dot.ends_with(
+            "\tblock0 -> block1 [label=\"x, z\"];
+\tblock1 -> block2 [label=\"x, y, z\"];
+\tblock2 -> block3 [label=\"y, z\"];
+\tblock2 -> block4 [label=\"x, y, z\"];
+\tblock3 -> block4 [label=\"x, y, z\"];
+}"
+        ), "{dot}");
+        let live: Vec<&String> = cfg.get_live_in(4).iter().collect();
+        assert_eq!(live, vec!["x", "y", "z"]);
+    }
 }
@@ -0,0 +1,101 @@
+use live_crab::lexer::Lexer;
+use live_crab::liveness::ControlFlowGraph;
+use live_crab::parser::Parser;
+
+mod test_utils;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_utils::*;
+
+    fn cfg_from_str(s: &str) -> ControlFlowGraph {
+        let lexer = Lexer::new(s);
+        let tokens = lexer.tokenize();
+        let mut parser = Parser::new(tokens);
+        let prog = parser.parse();
+        ControlFlowGraph::from(&prog)
+    }
+
+    #[test]
+    fn back_edge_book_ex() {
+        let s = get_str_from_path("examples/book_ex").unwrap();
+        let cfg = cfg_from_str(&s);
+        assert_eq!(cfg.back_edges(), vec![(4, 1)]);
+    }
+    #[test]
+    fn back_edge_while() {
+        let s = get_str_from_path("examples/loop1").unwrap();
+        let cfg = cfg_from_str(&s);
+        //0: i = 0;
+        //1: if i < 9
+        //2: i = i + 1;
+        //3: return i;
+        assert_eq!(cfg.back_edges(), vec![(2, 1)]);
+        let forest = cfg.loop_forest();
+        assert_eq!(forest.get_loops().len(), 1);
+        let lp = forest.get_loop(0);
+        assert_eq!(lp.get_header(), 1);
+        assert_eq!(lp.get_latches(), &vec![2]);
+        assert_eq!(lp.get_body().len(), 2);
+        assert_eq!(lp.get_exit_edges(&cfg), vec![(1, 3)]);
+    }
+    #[test]
+    fn no_loops() {
+        let s = get_str_from_path("examples/s2").unwrap();
+        let cfg = cfg_from_str(&s);
+        assert!(cfg.back_edges().is_empty());
+        assert!(cfg.loop_forest().is_empty());
+        assert_eq!(cfg.get_loop_depths(), vec![0, 0, 0, 0]);
+    }
+    #[test]
+    fn nested_loops() {
+        let s = "i = 0;
+        while ( i < 9 ) {
+            j = 0;
+            do {
+                j = j + 1;
+            } while ( j < i );
+            i = i + 1;
+        }
+        return i;";
+        let cfg = cfg_from_str(s);
+        //0: i = 0;
+        //1: if i < 9
+        //2: j = 0;
+        //3: j = j + 1;
+        //4: if j < i
+        //5: i = i + 1;
+        //6: return i;
+        assert_eq!(cfg.back_edges(), vec![(4, 3), (5, 1)]);
+        let forest = cfg.loop_forest();
+        assert_eq!(forest.get_roots(), vec![0]);
+        let outer = forest.get_loop(0);
+        let inner = forest.get_loop(1);
+        assert_eq!(outer.get_header(), 1);
+        assert_eq!(inner.get_header(), 3);
+        assert_eq!(inner.get_parent(), Some(0));
+        assert_eq!(outer.get_children(), &vec![1]);
+        assert_eq!(outer.get_body().len(), 5);
+        assert_eq!(inner.get_body().len(), 2);
+        assert_eq!(cfg.get_loop_depths(), vec![0, 1, 1, 2, 2, 1, 0]);
+    }
+    #[test]
+    fn dot_clusters() {
+        let s = "i = 0;
+        while ( i < 9 ) {
+            do {
+                i = i + 1;
+            } while ( i < 3 );
+        }
+        return i;";
+        let cfg = cfg_from_str(s);
+        let dot = cfg.generate_dot();
+        let outer = dot.find("subgraph cluster_loop0 {").unwrap();
+        let inner = dot.find("\t\tsubgraph cluster_loop1 {").unwrap();
+        let block2 = dot.find("\t\t\tblock2 [label=\"i = i + 1;\"];").unwrap();
+        assert!(outer < inner && inner < block2);
+        assert!(dot.contains("\tblock0 [label=\"i = 0;\"];"));
+        assert!(dot.contains("\tblock3 -> block1"));
+    }
+}
@@ -0,0 +1,151 @@
+use live_crab::ast::Expr;
+use live_crab::lexer::Lexer;
+use live_crab::liveness::{ControlFlowGraph, NodeKind};
+use live_crab::parser::Parser;
+
+mod test_utils;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    fn cfg_from_str(s: &str) -> ControlFlowGraph {
+        let lexer = Lexer::new(s);
+        let tokens = lexer.tokenize();
+        let mut parser = Parser::new(tokens);
+        ControlFlowGraph::from(&parser.parse())
+    }
+
+    fn set(v: &[usize]) -> BTreeSet<usize> {
+        v.iter().cloned().collect()
+    }
+
+    #[test]
+    fn flattened_cfgs_are_consistent() {
+        let progs = [
+            "a = 1; return a;",
+            "if ( c < 1 ) { x = 1; } return x;",
+            "i = 0; while ( i < n ) { if ( i < 2 ) { i = i + 2; } i = i + 1; } return i;",
+            "do { i = i + 1; do { j = j + 1; } while ( j < i ); } while ( i < n ); return i;",
+            "while ( i < n ) { } x = 1;",
+        ];
+        for s in progs {
+            assert!(cfg_from_str(s).is_consistent(), "Inconsistent: {s}");
+        }
+    }
+    #[test]
+    fn split_if_false_edge() {
+        let mut cfg = cfg_from_str("if ( c < 1 ) { x = 1; } return x;");
+        assert_eq!(cfg.critical_edges(), vec![(0, 2)]);
+        assert_eq!(cfg.split_critical_edges(), vec![3]);
+        assert!(cfg.is_consistent());
+        assert!(cfg.critical_edges().is_empty());
+        assert_eq!(cfg.get_node(3).get_node_kind(), &NodeKind::Empty);
+        assert_eq!(cfg.get_node(0).get_false_succ(), Some(3));
+        assert_eq!(cfg.get_node(0).get_true_succ(), Some(1));
+        assert_eq!(cfg.get_node(2).get_preds(), &set(&[1, 3]));
+    }
+    #[test]
+    fn split_do_while_back_edge() {
+        let mut cfg = cfg_from_str("i = 0; do { i = i + 1; } while ( i < n ); return i;");
+        assert_eq!(cfg.split_critical_edges(), vec![4]);
+        assert_eq!(cfg.get_node(2).get_true_succ(), Some(4));
+        assert_eq!(cfg.get_node(4).get_succs(), &set(&[1]));
+        assert!(cfg.is_consistent());
+    }
+    #[test]
+    fn split_trailing_do_while_back_edge() {
+        // the cond also branches to the exit, so its back edge is critical
+        let mut cfg = cfg_from_str("i = 0; do { i = i + 1; } while ( i < n );");
+        assert_eq!(cfg.critical_edges(), vec![(2, 1)]);
+        assert_eq!(cfg.split_critical_edges(), vec![3]);
+        assert_eq!(cfg.get_node(2).get_true_succ(), Some(3));
+        assert_eq!(cfg.get_node(2).get_false_succ(), None);
+        assert!(cfg.is_consistent());
+    }
+    #[test]
+    fn split_keeps_liveness() {
+        let mut cfg = cfg_from_str("if ( c < 1 ) { x = 1; } return x;");
+        cfg.fast_perform_liveness_analysis();
+        cfg.split_critical_edges();
+        let (ins, outs) = cfg.get_live_sets();
+        assert_eq!(ins.len(), 4);
+        assert_eq!(outs.len(), 4);
+        assert_eq!(cfg.get_live_in(3), &BTreeSet::from([String::from("x")]));
+        assert_eq!(cfg.get_live_out(0), &BTreeSet::from([String::from("x")]));
+    }
+    #[test]
+    fn unique_exit() {
+        let mut cfg = cfg_from_str("if ( c < 1 ) { return 1; } x = 2;");
+        assert_eq!(cfg.ensure_unique_exit(), Some(3));
+        assert_eq!(cfg.get_node(3).get_preds(), &set(&[1, 2]));
+        assert!(cfg.is_consistent());
+
+        let mut cfg = cfg_from_str("x = 1; while ( x < 5 ) { x = x + 1; }");
+        assert_eq!(cfg.ensure_unique_exit(), Some(3));
+        assert_eq!(cfg.get_node(1).get_false_succ(), Some(3));
+        assert!(cfg.is_consistent());
+
+        let mut cfg = cfg_from_str("x = 1; return x;");
+        assert_eq!(cfg.ensure_unique_exit(), Some(1));
+        assert_eq!(cfg.get_nodes().len(), 2);
+    }
+    #[test]
+    fn empty_nodes_have_no_expression() {
+        let mut cfg = cfg_from_str("if ( c < 1 ) { return a + b; } x = a + b;");
+        let exit = cfg.ensure_unique_exit().unwrap();
+        let kind = cfg.get_node(exit).get_node_kind();
+        assert_eq!(kind.get_expr(), None);
+        assert!(kind.get_binops().is_empty());
+        assert_eq!(cfg.get_node(2).get_node_kind().get_binops().len(), 1);
+        // passes let them through
+        let avail = cfg.available_expressions();
+        assert_eq!(avail.get_in(exit).len(), 2, "c < 1 and a + b");
+        assert_eq!(avail.get_out(exit), avail.get_in(exit));
+        assert_eq!(cfg.ssa_form().get_node(exit).get_node_kind(), &NodeKind::Empty);
+    }
+    #[test]
+    #[should_panic(expected = "An empty node has no expression to replace")]
+    fn empty_node_with_expr() {
+        NodeKind::Empty.with_expr(Expr::Int(1));
+    }
+    #[test]
+    fn unique_entry() {
+        let mut cfg = cfg_from_str("do { i = i + 1; } while ( i < n ); return i;");
+        cfg.fast_perform_liveness_analysis();
+        assert!(cfg.ensure_unique_entry());
+        assert!(!cfg.ensure_unique_entry());
+        assert_eq!(cfg.get_node(0).get_node_kind(), &NodeKind::Empty);
+        assert_eq!(cfg.get_node(2).get_true_succ(), Some(1));
+        assert_eq!(cfg.get_node(2).get_false_succ(), Some(3));
+        assert_eq!(cfg.get_node(1).get_preds(), &set(&[0, 2]));
+        assert!(cfg.is_consistent());
+        assert!(cfg.get_live_in(0).contains("i"));
+    }
+    #[test]
+    fn preheaders() {
+        let mut cfg = cfg_from_str("i = 0; while ( i < n ) { i = i + 1; } return i;");
+        assert_eq!(cfg.add_loop_preheaders(), vec![(1, 0)]);
+        assert_eq!(cfg.get_nodes().len(), 4);
+
+        let mut cfg = cfg_from_str("if ( c < 1 ) { i = 0; } while ( i < n ) { i = i + 1; } return i;");
+        cfg.fast_perform_liveness_analysis();
+        assert_eq!(cfg.add_loop_preheaders(), vec![(2, 5)]);
+        assert_eq!(cfg.get_node(2).get_preds(), &set(&[3, 5]));
+        assert_eq!(cfg.get_node(5).get_preds(), &set(&[0, 1]));
+        assert_eq!(cfg.get_node(0).get_false_succ(), Some(5));
+        assert_eq!(cfg.get_live_in(5), cfg.get_live_in(2));
+        assert!(cfg.is_consistent());
+
+        let mut cfg = cfg_from_str("do { i = i + 1; } while ( i < n ); return i;");
+        assert_eq!(cfg.add_loop_preheaders(), vec![(1, 0)]);
+        assert!(cfg.is_consistent());
+
+        // The if's cond also leaves the program, so it is no preheader
+        let mut cfg = cfg_from_str("if ( c < 1 ) { while ( i < n ) { i = i + 1; } }");
+        assert_eq!(cfg.add_loop_preheaders(), vec![(1, 3)]);
+        assert_eq!(cfg.get_node(0).get_true_succ(), Some(3));
+        assert!(cfg.is_consistent());
+    }
+}
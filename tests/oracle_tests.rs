@@ -0,0 +1,93 @@
+use live_crab::interpreter::Interpreter;
+use live_crab::liveness::ControlFlowGraph;
+use live_crab::oracle::*;
+use std::collections::BTreeSet;
+
+mod test_utils;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_utils::*;
+
+    fn set(vars: &[&str]) -> BTreeSet<String> {
+        vars.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn dynamic_straight_line() {
+        let p = prog_from_str("a = 1; b = a + 2; a = 3; return a + b;");
+        let cfg = ControlFlowGraph::from(&p);
+        let trace = Interpreter::new().trace(&cfg);
+        let got = dynamic_live_in(&cfg, &trace);
+        assert_eq!(got, vec![set(&[]), set(&["a"]), set(&["b"]), set(&["a", "b"])]);
+    }
+    #[test]
+    fn dynamic_depends_on_path() {
+        // b is only read when the branch is taken
+        let p = prog_from_str("b = 1; if ( c ) { a = b; } return c;");
+        let cfg = ControlFlowGraph::from(&p);
+        let taken = Interpreter::new().with_input("c", 1).trace(&cfg);
+        let skipped = Interpreter::new().with_input("c", 0).trace(&cfg);
+        assert_eq!(dynamic_live_in(&cfg, &taken)[1], set(&["b", "c"]));
+        assert_eq!(dynamic_live_in(&cfg, &skipped)[1], set(&["c"]));
+    }
+    #[test]
+    fn static_liveness_is_sound() {
+        let progs = [
+            "a = 1; b = a + 2; a = 3; return a + b;",
+            "i = 0; s = 0; while ( i < n ) { s = s + i; i = i + 1; } return s;",
+            "i = 0; do { j = i; i = i + 1; } while ( i < n ); return j;",
+            "x = n; if ( x < 2 ) { y = x; } if ( 1 < x ) { y = 0 - x; } return y;",
+            "a = 0; while ( a < n ) { if ( a % 2 ) { b = a; } a = a + 1; } c = a;",
+        ];
+        for s in progs {
+            let p = prog_from_str(s);
+            for n in 0..4 {
+                let check = check_liveness(&p, &Interpreter::new().with_input("n", n));
+                check.assert_sound();
+                assert_eq!(check.get_dynamic_live_in().len(), check.get_trace().get_steps().len());
+            }
+        }
+    }
+    #[test]
+    fn normalized_liveness_is_sound() {
+        let p = prog_from_str("while ( i < n ) { if ( i ) { i = i + 2; } i = i + 1; } return i;");
+        let mut cfg = ControlFlowGraph::from(&p);
+        cfg.split_critical_edges();
+        cfg.add_loop_preheaders();
+        cfg.ensure_unique_exit();
+        for n in 0..5 {
+            let interp = Interpreter::new().with_input("i", 0).with_input("n", n);
+            check_cfg_liveness(&cfg, &interp).assert_sound();
+        }
+    }
+    #[test]
+    fn reports_unsoundness() {
+        let p = prog_from_str("a = 1; b = a + 2; return b;");
+        let cfg = ControlFlowGraph::from(&p);
+        let trace = Interpreter::new().trace(&cfg);
+        let live_in = vec![BTreeSet::new(); 3];
+        let check = check_trace(&cfg, &live_in, trace);
+        assert!(!check.is_sound());
+        let found: Vec<(usize, usize, BTreeSet<String>)> = check
+            .get_violations()
+            .iter()
+            .map(|v| (v.get_step(), v.get_node(), v.get_missing().clone()))
+            .collect();
+        assert_eq!(found, vec![(1, 1, set(&["a"])), (2, 2, set(&["b"]))]);
+        let report = check.to_string();
+        assert!(report.starts_with("liveness unsound\n"));
+        assert!(report.contains("1: b = a + 2;"));
+        assert!(report.contains("step\tnode\tenv"));
+        assert!(report.contains("step 1: live_in(1) is missing a\n"));
+    }
+    #[test]
+    #[should_panic(expected = "liveness unsound")]
+    fn assert_sound_panics() {
+        let p = prog_from_str("a = 1; return a;");
+        let cfg = ControlFlowGraph::from(&p);
+        let trace = Interpreter::new().trace(&cfg);
+        check_trace(&cfg, &[BTreeSet::new(), BTreeSet::new()], trace).assert_sound();
+    }
+}
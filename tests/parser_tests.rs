@@ -57,6 +57,20 @@ mod tests {
         assert_eq!(got, want, "Got: {:?}\n\n", got);
     }
     #[test]
+    fn parser_negative_literal() {
+        let s = "a = b - -3;";
+        let lexer = Lexer::new(s);
+        let tokens = lexer.tokenize();
+        let mut parser = Parser::new(tokens);
+        let got = parser.parse();
+        let want_vec = vec![Statement::Assignment(
+            Box::new(Expr::Id("a".to_string())),
+            Box::new(create_binop_rlit(Expr::Id("b".to_string()), Operator::Minus, -3)),
+        )];
+        let want = Program::new(want_vec);
+        assert_eq!(got, want, "Got: {:?}\n\n", got);
+    }
+    #[test]
     fn parser_simple_example1() {
         // a = 2; b = 3; return a;
         let file = get_str_from_path("examples/s1").unwrap();
@@ -199,4 +213,35 @@ mod tests {
         let want = Program::new(want_vec);
         assert_eq!(got, want, "\nGot: {}\n\nWant: {}\n\n", &got, &want);
     }
+    #[test]
+    fn try_parse_reports_errors() {
+        let parse = |s: &str| Parser::new(Lexer::new(s).tokenize()).try_parse();
+        assert!(parse("a = 1; while ( a ) { a = a - 1; }").is_ok());
+        let err = parse("a = 1 b = 2;").unwrap_err();
+        assert_eq!(err.get_token_idx(), 3);
+        assert_eq!(err.get_msg(), "expected `;`, found `b`");
+        let err = parse("if ( a ) { b = 1;").unwrap_err();
+        assert_eq!(err.get_msg(), "expected `}`, found end of input");
+        let err = parse("a = 1; }").unwrap_err();
+        assert_eq!(err.to_string(), "expected a statement, found `}` (token 4)");
+        let err = parse("a = ;").unwrap_err();
+        assert_eq!(err.get_msg(), "expected an expression, found `;`");
+        assert_eq!(parse("break;").unwrap_err().get_token_idx(), 0);
+    }
+    #[test]
+    fn parse_stops_at_non_statements() {
+        // what try_parse rejects, parse cuts off
+        let want = Program::new(vec![Statement::Assignment(
+            Box::new(Expr::Id("a".to_string())),
+            Box::new(Expr::Int(1)),
+        )]);
+        assert_eq!(Parser::new(Lexer::new("a = 1; } b = 2;").tokenize()).parse(), want);
+        assert_eq!(Parser::new(Lexer::new("a = 1; break; b = 2;").tokenize()).parse(), want);
+        assert_eq!(Parser::new(Lexer::new("a = 1; 3").tokenize()).parse(), want);
+    }
+    #[test]
+    #[should_panic(expected = "expected `;`, found end of input")]
+    fn parse_panics_on_errors() {
+        Parser::new(Lexer::new("a = 1").tokenize()).parse();
+    }
 }
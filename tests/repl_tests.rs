@@ -0,0 +1,104 @@
+use live_crab::cli;
+use live_crab::liveness::ControlFlowGraph;
+use live_crab::repl::*;
+
+mod test_utils;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_utils::*;
+
+    fn feed(repl: &mut Repl, lines: &[&str]) -> String {
+        lines.iter().map(|l| repl.handle_line(l)).collect()
+    }
+
+    #[test]
+    fn statements_accumulate() {
+        let mut repl = Repl::new();
+        assert_eq!(feed(&mut repl, &["a = 1;", "b = a + 2;", "", "return b;"]), "");
+        assert_eq!(repl.get_program(), prog_from_str("a = 1; b = a + 2; return b;"));
+    }
+    #[test]
+    fn statements_span_lines() {
+        let mut repl = Repl::new();
+        assert_eq!(feed(&mut repl, &["a = 3;", "while (a) {"]), "");
+        assert!(repl.is_pending());
+        assert_eq!(repl.get_prompt(), "... ");
+        feed(&mut repl, &["  a = a - 1;", "}"]);
+        assert!(!repl.is_pending());
+        assert_eq!(repl.get_prompt(), "> ");
+        assert_eq!(repl.get_program(), prog_from_str("a = 3; while (a) { a = a - 1; }"));
+    }
+    #[test]
+    fn errors_are_rendered_and_dropped() {
+        let mut repl = Repl::new();
+        feed(&mut repl, &["a = 1;"]);
+        let out = repl.handle_line("a = 1 b;");
+        assert!(out.starts_with("error: expected `;`, found `b`"), "{out}");
+        assert!(out.contains("--> <input>:1:7"), "{out}");
+        assert!(!repl.is_pending());
+        let out = repl.handle_line("a = $;");
+        assert!(out.starts_with("error: unexpected character `$`"), "{out}");
+        assert_eq!(repl.get_program(), prog_from_str("a = 1;"));
+    }
+    #[test]
+    fn undo_and_clear() {
+        let mut repl = Repl::new();
+        feed(&mut repl, &["a = 1;", "b = 2; c = 3;"]);
+        assert_eq!(repl.handle_line(":undo"), "");
+        assert_eq!(repl.get_program(), prog_from_str("a = 1;"));
+
+        feed(&mut repl, &["if (a) {"]);
+        assert_eq!(repl.handle_line(":undo"), "dropped the unfinished input\n");
+        assert_eq!(repl.get_program(), prog_from_str("a = 1;"));
+
+        feed(&mut repl, &[":clear"]);
+        assert_eq!(repl.get_program(), prog_from_str(""));
+        feed(&mut repl, &[":undo", ":undo"]);
+        assert_eq!(repl.get_program(), prog_from_str(""));
+        assert_eq!(repl.handle_line(":undo"), "nothing to undo\n");
+    }
+    #[test]
+    fn commands_show_the_program() {
+        let mut repl = Repl::new();
+        feed(&mut repl, &["a = 2;", "b = 3;", "return a;"]);
+        let p = prog_from_str("a = 2; b = 3; return a;");
+        assert_eq!(repl.handle_line(":cfg"), ControlFlowGraph::from(&p).to_string());
+        let mut cfg = ControlFlowGraph::from(&p);
+        cfg.fast_perform_liveness_analysis();
+        assert_eq!(repl.handle_line(":dot"), cfg.generate_dot() + "\n");
+        assert_eq!(repl.handle_line(":range a"), "a: 0 -> 1, 1 -> 2\n");
+        let live = repl.handle_line(":live");
+        assert!(live.starts_with("node\tstatement\tin\tout\n"), "{live}");
+        assert!(live.contains("2\treturn a;\t{a}\t{}\n"), "{live}");
+        assert_eq!(repl.handle_line(":range"), "usage: :range <x>\n");
+        assert_eq!(repl.handle_line(":frob"), "unknown command `:frob`, see :help\n");
+        assert_eq!(repl.handle_line(":help"), HELP);
+    }
+    #[test]
+    fn load_replaces_the_program() {
+        let mut repl = Repl::new();
+        feed(&mut repl, &["x = 1;"]);
+        assert_eq!(repl.handle_line(":load examples/s1"), "");
+        assert_eq!(repl.get_program(), prog_from_str("a = 2; b = 3; return a;"));
+        feed(&mut repl, &[":undo"]);
+        assert_eq!(repl.get_program(), prog_from_str("x = 1;"));
+        assert!(repl.handle_line(":load examples/nope").starts_with("cannot read examples/nope"));
+    }
+    #[test]
+    fn runs_a_session() {
+        let mut out = Vec::new();
+        let input = "a = 1;\nwhile (a) {\na = a - 1;\n}\n:range a\n:quit\nb = 2;\n";
+        let mut repl = Repl::new();
+        repl.run(&mut input.as_bytes(), &mut out).unwrap();
+        assert!(repl.is_done());
+        assert_eq!(String::from_utf8(out).unwrap(), "> > ... ... > a: 0 -> 1, 1 -> 2, 2 -> 1\n> ");
+
+        let args: Vec<String> = ["repl", "examples/s1"].iter().map(|s| s.to_string()).collect();
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        let code = cli::run(&args, &mut ":program\n".as_bytes(), &mut out, &mut err);
+        assert_eq!(code, cli::EXIT_OK);
+        assert_eq!(String::from_utf8(out).unwrap(), "> a = 2;\nb = 3;\nreturn a;\n> \n");
+    }
+}
@@ -0,0 +1,76 @@
+use live_crab::lexer::Lexer;
+use live_crab::liveness::{ControlFlowGraph, NodeKind};
+use live_crab::parser::Parser;
+
+mod test_utils;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cfg_from_str(s: &str) -> ControlFlowGraph {
+        let lexer = Lexer::new(s);
+        let tokens = lexer.tokenize();
+        let mut parser = Parser::new(tokens);
+        ControlFlowGraph::from(&parser.parse())
+    }
+
+    #[test]
+    fn ssa_straight_line() {
+        let ssa = cfg_from_str("a = 1; a = a + 1; return a;").ssa_form();
+        let want = "0: a_1 = 1;\n1: a_2 = a_1 + 1;\n2: return a_2;\n";
+        assert_eq!(ssa.to_string(), want);
+        assert!(ssa.get_entry_names().is_empty());
+    }
+    #[test]
+    fn ssa_if_join() {
+        let ssa = cfg_from_str("x = 1; if ( c < 1 ) { x = 2; } return x;").ssa_form();
+        let want = "0: x_1 = 1;\n1: if c_0 < 1\n2: x_2 = 2;\n3: x_3 = phi(x_1 [1], x_2 [2]);\n3: return x_3;\n";
+        assert_eq!(ssa.to_string(), want);
+        assert_eq!(ssa.get_def("x_3"), Some(3));
+        assert_eq!(ssa.get_def("x_2"), Some(2));
+        assert_eq!(ssa.get_def("c_0"), None);
+        assert!(ssa.get_entry_names().contains("c_0"));
+    }
+    #[test]
+    fn ssa_pruned() {
+        let ssa = cfg_from_str("x = 1; if ( c < 1 ) { x = 2; } return 0;").ssa_form();
+        assert!(ssa.get_nodes().iter().all(|n| n.get_phis().is_empty()));
+    }
+    #[test]
+    fn ssa_while() {
+        let ssa = cfg_from_str("i = 0; while ( i < n ) { i = i + 1; } return i;").ssa_form();
+        let want = "0: i_1 = 0;\n1: i_2 = phi(i_1 [0], i_3 [2]);\n1: if i_2 < n_0\n2: i_3 = i_2 + 1;\n3: return i_2;\n";
+        assert_eq!(ssa.to_string(), want);
+    }
+    #[test]
+    fn ssa_do_while_first() {
+        let ssa = cfg_from_str("do { i = i + 1; } while ( i < n ); return i;").ssa_form();
+        let phi = &ssa.get_node(0).get_phis()[0];
+        assert_eq!(phi.get_var(), "i");
+        assert_eq!(phi.get_dest(), "i_1");
+        assert_eq!(phi.get_arg(None), Some(&String::from("i_0")));
+        assert_eq!(phi.get_arg(Some(1)), Some(&String::from("i_2")));
+        let want = "0: i_1 = phi(i_0 [entry], i_2 [1]);\n0: i_2 = i_1 + 1;\n1: if i_2 < n_0\n2: return i_2;\n";
+        assert_eq!(ssa.to_string(), want);
+    }
+    #[test]
+    fn ssa_single_assignment() {
+        let s = "a = 0; b = 1; while ( a < 10 ) { if ( b < a ) { b = b + a; } a = a + 1; } return b;";
+        let ssa = cfg_from_str(s).ssa_form();
+        let mut defs = Vec::new();
+        for n in ssa.get_nodes() {
+            defs.extend(n.get_phis().iter().map(|phi| phi.get_dest().clone()));
+            if let NodeKind::Assignment(lvl, _) = n.get_node_kind() {
+                defs.push(lvl.to_string());
+            }
+        }
+        let mut unique = defs.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(defs.len(), unique.len(), "Got: {:?}", defs);
+        // phis for a and b at the loop header, and for b after the if
+        assert_eq!(ssa.get_node(2).get_phis().len(), 2);
+        assert_eq!(ssa.get_node(5).get_phis().len(), 1);
+    }
+}
@@ -1,5 +1,14 @@
-use live_crab::{ast::{Expr, Operator}, liveness::{ControlFlowGraph, NodeKind}};
+// Every test file includes these helpers and none uses all of them
+#![allow(dead_code)]
 
+use live_crab::{ast::{Expr, Operator, Program}, lexer::Lexer, liveness::{ControlFlowGraph, NodeKind}, parser::Parser};
+
+pub fn prog_from_str(s: &str) -> Program {
+    let lexer = Lexer::new(s);
+    let tokens = lexer.tokenize();
+    let mut parser = Parser::new(tokens);
+    parser.parse()
+}
 pub fn get_str_from_path(path: &str) -> Option<String> {
     std::fs::read_to_string(path).ok()
 }
@@ -0,0 +1,97 @@
+use live_crab::ast::*;
+use live_crab::lexer::Lexer;
+use live_crab::liveness::ControlFlowGraph;
+use live_crab::parser::Parser;
+use live_crab::valuenum::{number_values, Value};
+
+mod test_utils;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_utils::*;
+
+    #[test]
+    fn basic_blocks_split_at_branches() {
+        let s = "a = 1; b = 2; while ( a < b ) { a = a + 1; c = a; } d = c; return d;";
+        let cfg = ControlFlowGraph::from(&prog_from_str(s));
+        let got = cfg.basic_blocks();
+        assert_eq!(got, vec![vec![0, 1], vec![2], vec![3, 4], vec![5, 6]]);
+
+        // The trailing cond can also end the program, so it ends its block
+        let s = "a = 1; if ( a < 2 ) { b = a + 1; c = b; }";
+        let cfg = ControlFlowGraph::from(&prog_from_str(s));
+        assert_eq!(cfg.basic_blocks(), vec![vec![0, 1], vec![2, 3]]);
+    }
+    #[test]
+    fn redundant_expression() {
+        let s = "x = a + b; y = a + b; return x + y;";
+        let got = number_values(&prog_from_str(s));
+        let want = prog_from_str("x = a + b; y = x; return x + x;");
+        assert_eq!(got.get_program(), &want, "Got:\n{}", got.get_program());
+        assert_eq!(got.get_rewritten(), &vec![1, 2]);
+    }
+    #[test]
+    fn commutative_operands() {
+        let s = "x = a * b; y = b * a; z = b - a; w = a - b; return x + y + z + w;";
+        let got = number_values(&prog_from_str(s));
+        let want = prog_from_str("x = a * b; y = x; z = b - a; w = a - b; return x + x + z + w;");
+        assert_eq!(got.get_program(), &want, "Got:\n{}", got.get_program());
+    }
+    #[test]
+    fn identities_and_constants() {
+        let s = "x = a + 0; y = 1 * b; z = 2 + 3; w = c / d * 1; return x + y + z + w;";
+        let got = number_values(&prog_from_str(s));
+        let want = prog_from_str("x = a; y = b; z = 5; w = c / d; return a + b + 5 + w;");
+        assert_eq!(got.get_program(), &want, "Got:\n{}", got.get_program());
+    }
+    #[test]
+    fn no_fold_to_min() {
+        let s = "a = 2147483647 + 1; return a;";
+        let got = number_values(&prog_from_str(s));
+        assert_eq!(got.get_program(), &prog_from_str(s), "Got:\n{}", got.get_program());
+        let printed = format!("{}", got.get_program()).replace("Program :", "");
+        let tokens = Lexer::new(&printed).try_tokenize().unwrap();
+        assert_eq!(&Parser::new(tokens).parse(), got.get_program());
+    }
+    #[test]
+    fn redefinition_changes_value() {
+        let s = "x = a + b; a = 7; y = a + b; z = 7 + b; return x + y + z;";
+        let got = number_values(&prog_from_str(s));
+        let want = prog_from_str("x = a + b; a = 7; y = 7 + b; z = y; return x + y + y;");
+        assert_eq!(got.get_program(), &want, "Got:\n{}", got.get_program());
+    }
+    #[test]
+    fn only_within_blocks() {
+        let s = "x = a + b; if ( x < 1 ) { y = a + b; } return y;";
+        let prog = prog_from_str(s);
+        let got = number_values(&prog);
+        assert_eq!(got.get_program(), &prog, "Got:\n{}", got.get_program());
+        assert_eq!(got.get_tables().len(), 3);
+    }
+    #[test]
+    fn value_table() {
+        let s = "x = a + b; y = b + a; return y;";
+        let got = number_values(&prog_from_str(s));
+        let table = &got.get_tables()[0];
+        assert_eq!(table.get_block(), &vec![0, 1, 2]);
+        assert_eq!(
+            table.get_values(),
+            &vec![
+                Value::Initial(String::from("a")),
+                Value::Initial(String::from("b")),
+                Value::Op(Operator::Plus, 0, 1),
+            ]
+        );
+        assert_eq!(table.get_value_number("y"), Some(2));
+        let want = "block 0 1 2\nvn\tvalue\tvars\n#0\ta@entry\ta\n#1\tb@entry\tb\n#2\t#0 + #1\tx, y\n";
+        assert_eq!(table.to_string(), want);
+    }
+    #[test]
+    fn initial_values_differ_from_variables() {
+        // x's starting value must not read like the variable x0
+        let got = number_values(&prog_from_str("x0 = 1; y = x; return x0 + y;"));
+        let want = "block 0 1 2\nvn\tvalue\tvars\n#0\t1\tx0\n#1\tx@entry\tx, y\n#2\t#0 + #1\t\n";
+        assert_eq!(got.get_tables()[0].to_string(), want);
+    }
+}
@@ -0,0 +1,94 @@
+use live_crab::cli::*;
+use live_crab::watch::*;
+
+mod test_utils;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    // A fresh directory per test, they run in parallel
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("live_crab_watch_{name}_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn watcher(args: &[&str]) -> Watcher {
+        let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+        Watcher::new(&parse_args(&args).unwrap().unwrap())
+    }
+
+    fn poll(w: &mut Watcher) -> (bool, String) {
+        let mut out = Vec::new();
+        let changed = w.poll(&mut out).unwrap();
+        (changed, String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn parses_watch_arguments() {
+        let args: Vec<String> = ["watch", "prog.txt", "--dot", "out.dot", "--interval", "100"]
+            .iter()
+            .map(|a| a.to_string())
+            .collect();
+        let opts = parse_args(&args).unwrap().unwrap();
+        assert_eq!(opts.get_command(), Command::Watch);
+        assert_eq!(opts.get_watched(), Command::Live);
+        assert_eq!(opts.get_format(), Format::Dot);
+        assert_eq!(opts.get_output().map(|s| s.as_str()), Some("out.dot"));
+        assert_eq!(opts.get_interval(), 100);
+
+        for bad in [
+            vec!["watch"],
+            vec!["watch", "-"],
+            vec!["watch", "f", "-c", "repl"],
+            vec!["watch", "f", "-c", "ranges", "-f", "dot"],
+            vec!["live", "f", "-c", "cfg"],
+            vec!["watch", "f", "--interval", "0"],
+        ] {
+            let args: Vec<String> = bad.iter().map(|a| a.to_string()).collect();
+            assert!(matches!(parse_args(&args), Err(CliError::Usage(_))), "{bad:?}");
+        }
+    }
+    #[test]
+    fn rebuilds_on_change() {
+        let dir = temp_dir("rebuild");
+        let (src, dot) = (dir.join("prog"), dir.join("out.dot"));
+        std::fs::write(&src, "a = 1; return a;").unwrap();
+        let mut w = watcher(&["watch", src.to_str().unwrap(), "--dot", dot.to_str().unwrap()]);
+
+        let (changed, out) = poll(&mut w);
+        assert!(changed);
+        assert_eq!(out, format!("{}: wrote {}\n", src.display(), dot.display()));
+        let first = std::fs::read_to_string(&dot).unwrap();
+        assert!(first.starts_with("digraph"), "{first}");
+        assert_eq!(poll(&mut w), (false, String::new()));
+
+        std::fs::write(&src, "a = 1; b = 2; return a;").unwrap();
+        let (changed, out) = poll(&mut w);
+        assert!(changed);
+        assert!(out.starts_with("warning: value assigned to `b` is never read"), "{out}");
+        assert!(out.ends_with(&format!("wrote {}\n", dot.display())), "{out}");
+        assert_ne!(std::fs::read_to_string(&dot).unwrap(), first);
+    }
+    #[test]
+    fn keeps_the_output_on_errors() {
+        let dir = temp_dir("errors");
+        let src = dir.join("prog");
+        std::fs::write(&src, "a = 1; return a;").unwrap();
+        let mut w = watcher(&["watch", src.to_str().unwrap(), "-c", "ast"]);
+        assert_eq!(poll(&mut w).1, "Program :\na = 1;\nreturn a;\n");
+
+        std::fs::write(&src, "a = 1; return a").unwrap();
+        let out = poll(&mut w).1;
+        assert!(out.starts_with("error: expected `;`, found end of input"), "{out}");
+        assert!(out.ends_with(&format!("{}: not rebuilt\n", src.display())), "{out}");
+
+        std::fs::remove_file(&src).unwrap();
+        let out = poll(&mut w).1;
+        assert!(out.starts_with(&format!("cannot read {}", src.display())), "{out}");
+        assert_eq!(poll(&mut w), (false, String::new()));
+    }
+}